@@ -0,0 +1,27 @@
+//! Initialize audio(buzzer) boilerplate of RMK
+//!
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+
+use crate::{keyboard_config::KeyboardConfig, pwm::expand_pwm_output};
+
+/// Expand the buzzer pwm and audio service initialization, the audio service is `audio_service`.
+///
+/// Returns `None` if there's no `[audio]` section.
+pub(crate) fn expand_audio_config(keyboard_config: &KeyboardConfig) -> Option<TokenStream2> {
+    let audio = keyboard_config.audio.as_ref()?;
+    let pwm_init = expand_pwm_output(
+        &keyboard_config.chip,
+        "audio",
+        &format_ident!("BuzzerPwm"),
+        &format_ident!("buzzer_pwm"),
+        &audio.pin,
+        audio.pwm.as_deref().unwrap_or("PWM0"),
+        true,
+    );
+
+    Some(quote! {
+        #pwm_init
+        let mut audio_service = ::rmk::audio::AudioService::new(buzzer_pwm, ::rmk::config::AudioConfig::default());
+    })
+}
@@ -1,7 +1,7 @@
 //! Initialize behavior config boilerplate of RMK
 //!
 
-use crate::config::{OneShotConfig, TapHoldConfig, TriLayerConfig};
+use crate::config::{DurationMillis, HostLayout, OneShotConfig, TapHoldConfig, TriLayerConfig};
 use crate::keyboard_config::KeyboardConfig;
 use quote::quote;
 
@@ -82,16 +82,39 @@ fn expand_tap_hold(tap_hold: &Option<TapHoldConfig>) -> proc_macro2::TokenStream
     }
 }
 
+fn expand_hold_time(hold_time: &Option<DurationMillis>) -> proc_macro2::TokenStream {
+    // Hold 2s by default, to avoid triggering the key accidentally
+    let millis = hold_time.as_ref().map_or(2000, |t| t.0);
+    quote! { ::embassy_time::Duration::from_millis(#millis) }
+}
+
+fn expand_host_layout(host_layout: &Option<HostLayout>) -> proc_macro2::TokenStream {
+    let layout = match host_layout {
+        Some(HostLayout::Us) | None => quote! { Us },
+        Some(HostLayout::Uk) => quote! { Uk },
+        Some(HostLayout::German) => quote! { German },
+        Some(HostLayout::French) => quote! { French },
+        Some(HostLayout::Dvorak) => quote! { Dvorak },
+    };
+    quote! { ::rmk::host_layout::HostLayout::#layout }
+}
+
 pub(crate) fn expand_behavior_config(keyboard_config: &KeyboardConfig) -> proc_macro2::TokenStream {
     let tri_layer = expand_tri_layer(&keyboard_config.behavior.tri_layer);
     let tap_hold = expand_tap_hold(&keyboard_config.behavior.tap_hold);
     let one_shot = expand_one_shot(&keyboard_config.behavior.one_shot);
+    let bootloader_hold_time = expand_hold_time(&keyboard_config.behavior.bootloader_hold_time);
+    let reset_hold_time = expand_hold_time(&keyboard_config.behavior.reset_hold_time);
+    let host_layout = expand_host_layout(&keyboard_config.behavior.host_layout);
 
     quote! {
         let behavior_config = ::rmk::config::BehaviorConfig {
             tri_layer: #tri_layer,
             tap_hold: #tap_hold,
             one_shot: #one_shot,
+            bootloader_hold_time: #bootloader_hold_time,
+            reset_hold_time: #reset_hold_time,
+            host_layout: #host_layout,
         };
     }
 }
@@ -0,0 +1,29 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use crate::{keyboard_config::KeyboardConfig, ChipSeries};
+
+// Default bootloader of the chip, used by the `Bootloader` keycode and Via.
+// Returns two TokenStreams, one for initialization of the bootloader, another one for filling this field into `RmkConfig`.
+//
+// STM32 isn't supported, because the address of the system memory varies between chips,
+// and `Stm32Bootloader::check` should be called before the chip is initialized.
+// ESP32 isn't supported either, because the download boot register varies between chip variants.
+pub(crate) fn expand_bootloader(keyboard_config: &KeyboardConfig) -> (TokenStream2, TokenStream2) {
+    let bootloader = match keyboard_config.chip.series {
+        ChipSeries::Rp2040 => quote! { ::rmk::bootloader::Rp2040Bootloader },
+        // Adafruit UF2 bootloader
+        ChipSeries::Nrf52 => quote! { ::rmk::bootloader::Nrf52Bootloader },
+        ChipSeries::Esp32 | ChipSeries::Stm32 => return (quote! {}, quote! {}),
+    };
+    (
+        quote! {
+            // `main` runs only once, so there's only one reference to `BOOTLOADER`
+            static mut BOOTLOADER: #bootloader = #bootloader;
+            let bootloader = unsafe { &mut *::core::ptr::addr_of_mut!(BOOTLOADER) };
+        },
+        quote! {
+            bootloader: ::core::option::Option::Some(bootloader),
+        },
+    )
+}
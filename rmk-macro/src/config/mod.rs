@@ -16,6 +16,10 @@ pub struct KeyboardTomlConfig {
     pub behavior: Option<BehaviorConfig>,
     /// Light config
     pub light: Option<LightConfig>,
+    /// Audio config
+    pub audio: Option<AudioConfig>,
+    /// Per-key RGB matrix config
+    pub rgb_matrix: Option<RgbMatrixConfig>,
     /// Storage config
     pub storage: Option<StorageConfig>,
     /// Ble config
@@ -26,6 +30,9 @@ pub struct KeyboardTomlConfig {
     pub split: Option<SplitConfig>,
     /// Input device config
     pub input_device: Option<InputDeviceConfig>,
+    /// Default keyboard macros
+    #[serde(rename = "macro")]
+    pub macros: Option<Vec<MacroConfig>>,
 }
 
 /// Configurations for keyboard info
@@ -102,6 +109,58 @@ pub struct LightConfig {
     pub capslock: Option<PinConfig>,
     pub scrolllock: Option<PinConfig>,
     pub numslock: Option<PinConfig>,
+    pub backlight: Option<BacklightConfig>,
+    #[serde(default)]
+    pub indicators: Vec<IndicatorConfig>,
+}
+
+/// Config for an indicator LED, which is on when the condition is met
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct IndicatorConfig {
+    pub pin: String,
+    #[serde(default)]
+    pub low_active: bool,
+    /// One of `capslock`, `numslock`, `scrolllock`, `layer`, `usb`, `ble_profile`, `battery_low` and `one_shot_modifier`
+    pub condition: String,
+    /// Layer number of the `layer` condition
+    pub layer: Option<u8>,
+    /// Profile number of the `ble_profile` condition
+    pub profile: Option<u8>,
+}
+
+/// Config for PWM backlight
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct BacklightConfig {
+    /// Pin of the backlight, which is driven by PWM
+    pub pin: String,
+    /// PWM instance used by the backlight, only for nRF52. `PWM1` is used by default
+    pub pwm: Option<String>,
+    /// Number of brightness levels, 1 ~ 31
+    pub levels: Option<u8>,
+    /// Duration of a breathing period in seconds
+    pub breathing_period: Option<u8>,
+}
+
+/// Config for audio(buzzer)
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct AudioConfig {
+    /// Pin of the buzzer, which is driven by PWM
+    pub pin: String,
+    /// PWM instance used by the buzzer, only for nRF52. `PWM0` is used by default
+    pub pwm: Option<String>,
+}
+
+/// Config for per-key RGB matrix
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct RgbMatrixConfig {
+    /// Matrix position `[row, col]` of each LED, in the order of the LED strip
+    pub led_map: Vec<[u8; 2]>,
+    /// HSV color `[h, s, v]` of each layer
+    pub layer_colors: Option<Vec<[u8; 3]>>,
+    /// Effect used at startup, "solid_layer", "reactive" or "heatmap"
+    pub effect: Option<String>,
+    /// Max brightness of LEDs
+    pub max_brightness: Option<u8>,
 }
 
 /// Config for a single pin
@@ -140,6 +199,20 @@ pub struct BehaviorConfig {
     pub tri_layer: Option<TriLayerConfig>,
     pub tap_hold: Option<TapHoldConfig>,
     pub one_shot: Option<OneShotConfig>,
+    pub bootloader_hold_time: Option<DurationMillis>,
+    pub reset_hold_time: Option<DurationMillis>,
+    pub host_layout: Option<HostLayout>,
+}
+
+/// Keyboard layout of the host, which is used to type text in macros
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostLayout {
+    Us,
+    Uk,
+    German,
+    French,
+    Dvorak,
 }
 
 /// Configurations for tap hold
@@ -151,6 +224,23 @@ pub struct TapHoldConfig {
     pub hold_timeout: Option<DurationMillis>,
 }
 
+/// Config for a keyboard macro
+#[derive(Clone, Debug, Deserialize)]
+pub struct MacroConfig {
+    pub steps: Vec<MacroStep>,
+}
+
+/// A step of keyboard macro, such as `{ text = "Hello" }`, `{ tap = "Enter" }` or `{ delay = "100ms" }`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroStep {
+    Text(String),
+    Tap(String),
+    Press(String),
+    Release(String),
+    Delay(DurationMillis),
+}
+
 /// Configurations for tri layer
 #[derive(Clone, Debug, Deserialize)]
 pub struct TriLayerConfig {
@@ -4,9 +4,11 @@ use quote::quote;
 use syn::ItemMod;
 
 use crate::{
+    audio::expand_audio_config,
     behavior::expand_behavior_config,
     bind_interrupt::expand_bind_interrupt,
     ble::expand_ble_config,
+    bootloader::expand_bootloader,
     chip_init::expand_chip_init,
     comm::expand_usb_init,
     entry::expand_rmk_entry,
@@ -16,9 +18,11 @@ use crate::{
     keyboard_config::{
         expand_keyboard_info, expand_vial_config, read_keyboard_toml_config, KeyboardConfig,
     },
+    keyboard_macro::expand_macros,
     layout::expand_layout_init,
-    light::expand_light_config,
+    light::{expand_backlight_config, expand_light_config},
     matrix::expand_matrix_config,
+    rgb_matrix::expand_rgb_matrix_config,
     ChipSeries,
 };
 
@@ -72,6 +76,9 @@ pub(crate) fn gen_imports(config: &KeyboardConfig) -> TokenStream2 {
     // Create vial config
     let vial_static_var = expand_vial_config();
 
+    // Create per-key RGB matrix config
+    let rgb_matrix = expand_rgb_matrix_config(config);
+
     let imports = match config.chip.series {
         ChipSeries::Esp32 => quote! {}, // For ESP32s, no panic handler and defmt logger are used
         _ => {
@@ -106,6 +113,7 @@ pub(crate) fn gen_imports(config: &KeyboardConfig) -> TokenStream2 {
         #keyboard_info_static_var
         #vial_static_var
         #layout
+        #rgb_matrix
     }
 }
 
@@ -124,7 +132,29 @@ fn expand_main(
     let behavior_config = expand_behavior_config(keyboard_config);
     let matrix_config = expand_matrix_config(keyboard_config, async_matrix);
     let run_rmk = expand_rmk_entry(keyboard_config, &item_mod);
+    // Run the audio service together with RMK if there's a buzzer
+    let (audio_config, run_rmk) = match expand_audio_config(keyboard_config) {
+        Some(audio_config) => (
+            audio_config,
+            quote! {
+                ::embassy_futures::join::join(audio_service.run(), async { #run_rmk }).await;
+            },
+        ),
+        None => (quote! {}, run_rmk),
+    };
+    // Run the backlight service together with RMK if there's a PWM backlight
+    let (backlight_config, run_rmk) = match expand_backlight_config(keyboard_config) {
+        Some(backlight_config) => (
+            backlight_config,
+            quote! {
+                ::embassy_futures::join::join(backlight_service.run(), async { #run_rmk }).await;
+            },
+        ),
+        None => (quote! {}, run_rmk),
+    };
     let (ble_config, set_ble_config) = expand_ble_config(keyboard_config);
+    let (bootloader, set_bootloader) = expand_bootloader(keyboard_config);
+    let (macros, set_macros) = expand_macros(keyboard_config);
 
     let main_function_sig = if keyboard_config.chip.series == ChipSeries::Esp32 {
         quote! {
@@ -166,6 +196,18 @@ fn expand_main(
 
             #ble_config
 
+            // Initialize the default bootloader of the chip as `bootloader`
+            #bootloader
+
+            // Default macros in `[[macro]]` as `MACROS`
+            #macros
+
+            // Initialize buzzer and audio service as `audio_service`
+            #audio_config
+
+            // Initialize backlight pwm and backlight service as `backlight_service`
+            #backlight_config
+
             // Set all keyboard config
             let keyboard_config = ::rmk::config::RmkConfig {
                 usb_config: KEYBOARD_USB_CONFIG,
@@ -174,6 +216,8 @@ fn expand_main(
                 storage_config,
                 behavior_config,
                 #set_ble_config
+                #set_bootloader
+                #set_macros
                 ..Default::default()
             };
 
@@ -4,8 +4,9 @@ use serde::Deserialize;
 use std::fs;
 
 use crate::config::{
-    BehaviorConfig, BleConfig, DependencyConfig, KeyboardInfo, KeyboardTomlConfig, LayoutConfig,
-    LightConfig, MatrixConfig, MatrixType, SplitConfig, StorageConfig,
+    AudioConfig, BehaviorConfig, BleConfig, DependencyConfig, KeyboardInfo, KeyboardTomlConfig,
+    LayoutConfig, LightConfig, MacroConfig, MacroStep, MatrixConfig, MatrixType, RgbMatrixConfig,
+    SplitConfig, StorageConfig,
 };
 use crate::{
     default_config::{
@@ -16,6 +17,9 @@ use crate::{
     ChipModel, ChipSeries,
 };
 
+/// Max number of indicator LEDs, which is `rmk::config::MAX_INDICATORS`
+const MAX_INDICATORS: usize = 8;
+
 macro_rules! rmk_compile_error {
     ($msg:expr) => {
         Err(syn::Error::new_spanned(quote! {}, $msg).to_compile_error())
@@ -75,10 +79,16 @@ pub(crate) struct KeyboardConfig {
     pub(crate) behavior: BehaviorConfig,
     // Light config
     pub(crate) light: LightConfig,
+    // Audio config
+    pub(crate) audio: Option<AudioConfig>,
+    // Per-key RGB matrix config
+    pub(crate) rgb_matrix: Option<RgbMatrixConfig>,
     // Storage config
     pub(crate) storage: StorageConfig,
     // Dependency config
     pub(crate) dependency: DependencyConfig,
+    // Default macros
+    pub(crate) macros: Vec<MacroConfig>,
 }
 
 #[derive(Clone, Debug)]
@@ -171,7 +181,13 @@ impl KeyboardConfig {
             Self::get_behavior_from_toml(config.behavior, toml_config.behavior, &config.layout)?;
 
         // Light config
-        config.light = Self::get_light_from_toml(config.light, toml_config.light);
+        config.light = Self::get_light_from_toml(config.light, toml_config.light)?;
+
+        // Audio config
+        config.audio = toml_config.audio;
+
+        // Per-key RGB matrix config
+        config.rgb_matrix = Self::get_rgb_matrix_from_toml(toml_config.rgb_matrix, &config.layout)?;
 
         // Storage config
         config.storage = Self::get_storage_from_toml(config.storage, toml_config.storage);
@@ -179,6 +195,9 @@ impl KeyboardConfig {
         // Dependency config
         config.dependency = toml_config.dependency.unwrap_or_default();
 
+        // Default macros
+        config.macros = Self::get_macros_from_toml(toml_config.macros)?;
+
         Ok(config)
     }
 
@@ -449,17 +468,107 @@ impl KeyboardConfig {
         }
     }
 
-    fn get_light_from_toml(default: LightConfig, toml: Option<LightConfig>) -> LightConfig {
+    fn get_light_from_toml(
+        default: LightConfig,
+        toml: Option<LightConfig>,
+    ) -> Result<LightConfig, TokenStream2> {
         match toml {
             Some(mut light_config) => {
                 // Use default setting if the corresponding field is not set
                 light_config.capslock = light_config.capslock.or(default.capslock);
                 light_config.numslock = light_config.numslock.or(default.numslock);
                 light_config.scrolllock = light_config.scrolllock.or(default.scrolllock);
-                light_config
+                light_config.backlight = light_config.backlight.or(default.backlight);
+
+                if light_config.indicators.len() > MAX_INDICATORS {
+                    let message = format!(
+                        "keyboard.toml: At most {} indicators are supported in [light]",
+                        MAX_INDICATORS
+                    );
+                    return rmk_compile_error!(message);
+                }
+                for indicator in light_config.indicators.iter() {
+                    let valid = match indicator.condition.as_str() {
+                        "layer" => indicator.layer.is_some(),
+                        "ble_profile" => indicator.profile.is_some(),
+                        "capslock" | "numslock" | "scrolllock" | "usb" | "battery_low"
+                        | "one_shot_modifier" => true,
+                        _ => false,
+                    };
+                    if !valid {
+                        let message = format!(
+                            "keyboard.toml: Invalid condition \"{}\" of indicator {}, available conditions: capslock, numslock, scrolllock, layer(with `layer`), usb, ble_profile(with `profile`), battery_low, one_shot_modifier",
+                            indicator.condition, indicator.pin
+                        );
+                        return rmk_compile_error!(message);
+                    }
+                }
+                Ok(light_config)
+            }
+            None => Ok(default),
+        }
+    }
+
+    fn get_rgb_matrix_from_toml(
+        toml: Option<RgbMatrixConfig>,
+        layout: &LayoutConfig,
+    ) -> Result<Option<RgbMatrixConfig>, TokenStream2> {
+        let Some(rgb_matrix) = toml else {
+            return Ok(None);
+        };
+        for [row, col] in rgb_matrix.led_map.iter() {
+            if *row >= layout.rows || *col >= layout.cols {
+                let message = format!(
+                    "keyboard.toml: LED at [{}, {}] in [rgb_matrix.led_map] is out of the matrix",
+                    row, col
+                );
+                return rmk_compile_error!(message);
+            }
+        }
+        match rgb_matrix.effect.as_deref() {
+            None | Some("solid_layer") | Some("reactive") | Some("heatmap") => Ok(Some(rgb_matrix)),
+            Some(effect) => {
+                let message = format!(
+                    "keyboard.toml: Unknown effect \"{}\" in [rgb_matrix], available effects: solid_layer, reactive, heatmap",
+                    effect
+                );
+                rmk_compile_error!(message)
+            }
+        }
+    }
+
+    fn get_macros_from_toml(
+        toml: Option<Vec<MacroConfig>>,
+    ) -> Result<Vec<MacroConfig>, TokenStream2> {
+        let macros = toml.unwrap_or_default();
+        // Macro0 ~ Macro31
+        if macros.len() > 32 {
+            return rmk_compile_error!(
+                "keyboard.toml: At most 32 macros are supported in [[macro]]"
+            );
+        }
+        for (i, m) in macros.iter().enumerate() {
+            for step in m.steps.iter() {
+                match step {
+                    MacroStep::Text(text) if text.bytes().any(|b| b < 2) => {
+                        let message = format!(
+                            "keyboard.toml: Characters `\\0` and `\\x01` are not supported in text of macro {}",
+                            i
+                        );
+                        return rmk_compile_error!(message);
+                    }
+                    MacroStep::Delay(delay) if delay.0 > 65024 => {
+                        let message = format!(
+                            "keyboard.toml: Delay of macro {} should be less than 65024ms",
+                            i
+                        );
+                        return rmk_compile_error!(message);
+                    }
+                    _ => (),
+                }
             }
-            None => default,
         }
+        Ok(macros)
     }
 
     fn get_storage_from_toml(default: StorageConfig, toml: Option<StorageConfig>) -> StorageConfig {
@@ -0,0 +1,43 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use crate::{config::MacroStep, keyboard_config::KeyboardConfig, layout::parse_key};
+
+// Default macros in `[[macro]]`, which are encoded by `MacroSequence` at compile time.
+// Returns two TokenStreams, one for the definition of macros, another one for filling this field into `RmkConfig`.
+pub(crate) fn expand_macros(keyboard_config: &KeyboardConfig) -> (TokenStream2, TokenStream2) {
+    if keyboard_config.macros.is_empty() {
+        return (quote! {}, quote! {});
+    }
+    let macros = keyboard_config.macros.iter().map(|m| {
+        let steps = m.steps.iter().map(|step| match step {
+            MacroStep::Text(text) => quote! { .text(#text) },
+            MacroStep::Tap(key) => {
+                let action = parse_key(key.clone());
+                quote! { .tap(#action) }
+            }
+            MacroStep::Press(key) => {
+                let action = parse_key(key.clone());
+                quote! { .press(#action) }
+            }
+            MacroStep::Release(key) => {
+                let action = parse_key(key.clone());
+                quote! { .release(#action) }
+            }
+            MacroStep::Delay(delay) => {
+                let ms = delay.0 as u16;
+                quote! { .delay(#ms) }
+            }
+        });
+        quote! { #(#steps)*.end() }
+    });
+    (
+        quote! {
+            static MACROS: ::rmk::keyboard_macro::MacroSequence =
+                ::rmk::keyboard_macro::MacroSequence::new()#(#macros)*;
+        },
+        quote! {
+            default_macros: ::core::option::Option::Some(&MACROS),
+        },
+    )
+}
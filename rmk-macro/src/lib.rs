@@ -1,6 +1,8 @@
+mod audio;
 mod behavior;
 mod bind_interrupt;
 mod ble;
+mod bootloader;
 mod chip_init;
 mod comm;
 mod config;
@@ -12,9 +14,12 @@ mod gpio_config;
 mod import;
 mod keyboard;
 mod keyboard_config;
+mod keyboard_macro;
 mod layout;
 mod light;
 mod matrix;
+mod pwm;
+mod rgb_matrix;
 mod split;
 #[rustfmt::skip]
 mod usb_interrupt_map;
@@ -1,10 +1,13 @@
 //! Initialize light config boilerplate of RMK, including USB or BLE
 //!
-use quote::quote;
+use quote::{format_ident, quote};
 
 use crate::{
-    config::PinConfig, gpio_config::convert_gpio_str_to_output_pin,
-    keyboard_config::KeyboardConfig, ChipModel,
+    config::{IndicatorConfig, PinConfig},
+    gpio_config::convert_gpio_str_to_output_pin,
+    keyboard_config::KeyboardConfig,
+    pwm::expand_pwm_output,
+    ChipModel,
 };
 
 pub(crate) fn build_light_config(
@@ -13,23 +16,66 @@ pub(crate) fn build_light_config(
 ) -> proc_macro2::TokenStream {
     match pin_config {
         Some(c) => {
-            let p = convert_gpio_str_to_output_pin(chip, c.pin.clone(), c.low_active);
-            let low_active = c.low_active;
-            quote! {
-                Some(::rmk::config::LightPinConfig {
-                    pin: #p,
-                    low_active: #low_active,
-                })
-            }
+            let p = build_light_pin_config(chip, &c.pin, c.low_active);
+            quote! { Some(#p) }
         }
         None => quote! {None},
     }
 }
 
+fn build_light_pin_config(
+    chip: &ChipModel,
+    pin: &str,
+    low_active: bool,
+) -> proc_macro2::TokenStream {
+    let p = convert_gpio_str_to_output_pin(chip, pin.to_string(), low_active);
+    quote! {
+        ::rmk::config::LightPinConfig {
+            pin: #p,
+            low_active: #low_active,
+        }
+    }
+}
+
+/// Build an indicator LED, the condition has been validated when reading `keyboard.toml`
+fn build_indicator_config(
+    chip: &ChipModel,
+    indicator: &IndicatorConfig,
+) -> proc_macro2::TokenStream {
+    let pin = build_light_pin_config(chip, &indicator.pin, indicator.low_active);
+    let condition = match indicator.condition.as_str() {
+        "capslock" => quote! { CapsLock },
+        "numslock" => quote! { NumsLock },
+        "scrolllock" => quote! { ScrollLock },
+        "layer" => {
+            let layer = indicator.layer.unwrap_or_default();
+            quote! { Layer(#layer) }
+        }
+        "usb" => quote! { UsbActive },
+        "ble_profile" => {
+            let profile = indicator.profile.unwrap_or_default();
+            quote! { BleProfileConnected(#profile) }
+        }
+        "battery_low" => quote! { BatteryLow },
+        _ => quote! { OneShotModifier },
+    };
+    quote! {
+        ::rmk::config::IndicatorConfig {
+            pin: #pin,
+            condition: ::rmk::config::IndicatorCondition::#condition,
+        }
+    }
+}
+
 pub(crate) fn expand_light_config(keyboard_config: &KeyboardConfig) -> proc_macro2::TokenStream {
     let numslock = build_light_config(&keyboard_config.chip, &keyboard_config.light.numslock);
     let capslock = build_light_config(&keyboard_config.chip, &keyboard_config.light.capslock);
     let scrolllock = build_light_config(&keyboard_config.chip, &keyboard_config.light.scrolllock);
+    let indicators = keyboard_config
+        .light
+        .indicators
+        .iter()
+        .map(|i| build_indicator_config(&keyboard_config.chip, i));
 
     // Generate a macro that does light config
     quote! {
@@ -37,6 +83,43 @@ pub(crate) fn expand_light_config(keyboard_config: &KeyboardConfig) -> proc_macr
             capslock: #capslock,
             numslock: #numslock,
             scrolllock: #scrolllock,
+            indicators: [#(#indicators),*].into_iter().collect(),
         };
     }
 }
+
+/// Expand the backlight pwm and backlight service initialization, the backlight service is `backlight_service`.
+///
+/// Returns `None` if there's no `[light.backlight]` section.
+pub(crate) fn expand_backlight_config(
+    keyboard_config: &KeyboardConfig,
+) -> Option<proc_macro2::TokenStream> {
+    let backlight = keyboard_config.light.backlight.as_ref()?;
+    let pwm_init = expand_pwm_output(
+        &keyboard_config.chip,
+        "light.backlight",
+        &format_ident!("BacklightPwm"),
+        &format_ident!("backlight_pwm"),
+        &backlight.pin,
+        backlight.pwm.as_deref().unwrap_or("PWM1"),
+        false,
+    );
+    let levels = match backlight.levels {
+        Some(levels) => quote! { levels: #levels, },
+        None => quote! {},
+    };
+    let breathing_period = match backlight.breathing_period {
+        Some(period) => quote! { breathing_period: #period, },
+        None => quote! {},
+    };
+
+    Some(quote! {
+        #pwm_init
+        let backlight_config = ::rmk::config::BacklightConfig {
+            #levels
+            #breathing_period
+            ..Default::default()
+        };
+        let mut backlight_service = ::rmk::backlight::BacklightService::new(backlight_pwm, backlight_config);
+    })
+}
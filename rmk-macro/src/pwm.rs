@@ -0,0 +1,133 @@
+//! Initialize PWM outputs, which are used by the buzzer and the backlight
+//!
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+
+use crate::{ChipModel, ChipSeries};
+
+/// Expand a PWM output on `pin` as `#var`, whose type `#ty` implements `SetDutyCycle`.
+///
+/// If `tone` is true, `#ty` implements `::rmk::audio::TonePwm` as well.
+/// `pwm` is the PWM instance, only used by nRF52. `section` is the name of the toml section, used in error messages.
+pub(crate) fn expand_pwm_output(
+    chip: &ChipModel,
+    section: &str,
+    ty: &Ident,
+    var: &Ident,
+    pin: &str,
+    pwm: &str,
+    tone: bool,
+) -> TokenStream2 {
+    match chip.series {
+        ChipSeries::Nrf52 => {
+            let pin = format_ident!("{}", pin);
+            let pwm = format_ident!("{}", pwm);
+            let tone_impl = if tone {
+                quote! {
+                    impl ::rmk::audio::TonePwm for #ty<'_> {
+                        fn set_frequency(&mut self, freq: u32) {
+                            self.0.set_period(freq);
+                        }
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            quote! {
+                struct #ty<'d>(::embassy_nrf::pwm::SimplePwm<'d, ::embassy_nrf::peripherals::#pwm>);
+                impl ::rmk::embedded_hal::pwm::ErrorType for #ty<'_> {
+                    type Error = ::core::convert::Infallible;
+                }
+                impl ::rmk::embedded_hal::pwm::SetDutyCycle for #ty<'_> {
+                    fn max_duty_cycle(&self) -> u16 {
+                        self.0.max_duty()
+                    }
+                    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+                        self.0.set_duty(0, duty);
+                        Ok(())
+                    }
+                }
+                #tone_impl
+                let #var = #ty(::embassy_nrf::pwm::SimplePwm::new_1ch(p.#pwm, p.#pin));
+            }
+        }
+        ChipSeries::Rp2040 => {
+            // Each gpio of rp2040 is connected to a fixed pwm slice and channel
+            let pin_num: u8 = match pin.strip_prefix("PIN_").and_then(|n| n.parse().ok()) {
+                Some(n) => n,
+                None => {
+                    let msg = format!(
+                        "Invalid [{}] pin for rp2040, it should be like \"PIN_0\"",
+                        section
+                    );
+                    return quote! { compile_error!(#msg); };
+                }
+            };
+            let pin = format_ident!("{}", pin);
+            let slice = format_ident!("PWM_SLICE{}", (pin_num / 2) % 8);
+            let channel_a = pin_num.is_multiple_of(2);
+            let (new_output, set_compare) = if channel_a {
+                (
+                    quote! { new_output_a },
+                    quote! { self.config.compare_a = duty; },
+                )
+            } else {
+                (
+                    quote! { new_output_b },
+                    quote! { self.config.compare_b = duty; },
+                )
+            };
+            let tone_impl = if tone {
+                quote! {
+                    impl ::rmk::audio::TonePwm for #ty<'_> {
+                        fn set_frequency(&mut self, freq: u32) {
+                            let clk = ::embassy_rp::clocks::clk_sys_freq();
+                            let freq = freq.max(1);
+                            // Use the smallest divider which makes `top` fit in u16
+                            let divider = (clk / (freq * 65536) + 1).min(255);
+                            self.config.divider = (divider as u8).into();
+                            self.config.top = (clk / (divider * freq)).saturating_sub(1).min(u16::MAX as u32) as u16;
+                            self.pwm.set_config(&self.config);
+                        }
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            quote! {
+                struct #ty<'d> {
+                    pwm: ::embassy_rp::pwm::Pwm<'d>,
+                    config: ::embassy_rp::pwm::Config,
+                }
+                impl ::rmk::embedded_hal::pwm::ErrorType for #ty<'_> {
+                    type Error = ::core::convert::Infallible;
+                }
+                impl ::rmk::embedded_hal::pwm::SetDutyCycle for #ty<'_> {
+                    fn max_duty_cycle(&self) -> u16 {
+                        self.config.top
+                    }
+                    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+                        #set_compare
+                        self.pwm.set_config(&self.config);
+                        Ok(())
+                    }
+                }
+                #tone_impl
+                let #var = {
+                    let config = ::embassy_rp::pwm::Config::default();
+                    #ty {
+                        pwm: ::embassy_rp::pwm::Pwm::#new_output(p.#slice, p.#pin, config.clone()),
+                        config,
+                    }
+                };
+            }
+        }
+        _ => {
+            let msg = format!(
+                "[{}] in keyboard.toml is only supported for nRF52 and RP2040, use Rust API instead",
+                section
+            );
+            quote! { compile_error!(#msg); }
+        }
+    }
+}
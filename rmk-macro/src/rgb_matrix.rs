@@ -0,0 +1,47 @@
+//! Initialize per-key RGB matrix config of RMK
+//!
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+
+use crate::keyboard_config::KeyboardConfig;
+
+/// Expand the LED map and layer colors in `[rgb_matrix]` as constants, and `get_rgb_matrix_config()` which returns `RgbMatrixConfig`.
+///
+/// Use them in an overwritten entry to run `RgbMatrixService` with a LED strip writer.
+pub(crate) fn expand_rgb_matrix_config(keyboard_config: &KeyboardConfig) -> TokenStream2 {
+    let Some(rgb_matrix) = &keyboard_config.rgb_matrix else {
+        return quote! {};
+    };
+
+    let num_leds = rgb_matrix.led_map.len();
+    let led_map = rgb_matrix
+        .led_map
+        .iter()
+        .map(|[row, col]| quote! { (#row, #col) });
+    let layer_colors = rgb_matrix.layer_colors.clone().unwrap_or(vec![[0, 0, 255]]);
+    let num_layer_colors = layer_colors.len();
+    let layer_colors = layer_colors
+        .iter()
+        .map(|[h, s, v]| quote! { ::rmk::rgb::color::Hsv::new(#h, #s, #v) });
+    let effect = match rgb_matrix.effect.as_deref() {
+        Some("reactive") => format_ident!("Reactive"),
+        Some("heatmap") => format_ident!("Heatmap"),
+        _ => format_ident!("SolidLayer"),
+    };
+    let max_brightness = rgb_matrix.max_brightness.unwrap_or(255);
+
+    quote! {
+        pub const RGB_MATRIX_NUM_LEDS: usize = #num_leds;
+        pub const RGB_MATRIX_LED_MAP: [(u8, u8); RGB_MATRIX_NUM_LEDS] = [#(#led_map), *];
+        pub const RGB_MATRIX_LAYER_COLORS: [::rmk::rgb::color::Hsv; #num_layer_colors] = [#(#layer_colors), *];
+
+        pub fn get_rgb_matrix_config() -> ::rmk::config::RgbMatrixConfig<'static> {
+            ::rmk::config::RgbMatrixConfig {
+                led_map: &RGB_MATRIX_LED_MAP,
+                layer_colors: &RGB_MATRIX_LAYER_COLORS,
+                effect: ::rmk::rgb::matrix::RgbMatrixEffect::#effect,
+                max_brightness: #max_brightness,
+            }
+        }
+    }
+}
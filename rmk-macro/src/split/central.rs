@@ -5,9 +5,11 @@ use quote::{format_ident, quote};
 use syn::ItemMod;
 
 use crate::{
+    audio::expand_audio_config,
     behavior::expand_behavior_config,
     bind_interrupt::expand_bind_interrupt,
     ble::expand_ble_config,
+    bootloader::expand_bootloader,
     chip_init::expand_chip_init,
     comm::expand_usb_init,
     config::{MatrixType, SerialConfig, SplitConfig},
@@ -16,7 +18,8 @@ use crate::{
     import::expand_imports,
     keyboard::gen_imports,
     keyboard_config::{read_keyboard_toml_config, BoardConfig, KeyboardConfig},
-    light::expand_light_config,
+    keyboard_macro::expand_macros,
+    light::{expand_backlight_config, expand_light_config},
     matrix::{expand_matrix_direct_pins, expand_matrix_input_output_pins},
     ChipModel, ChipSeries,
 };
@@ -129,7 +132,29 @@ fn expand_split_central(
     let split_communication_config =
         expand_split_communication_config(&keyboard_config.chip, split_config);
     let run_rmk = expand_split_central_entry(keyboard_config, split_config);
+    // Run the audio service together with RMK if there's a buzzer
+    let (audio_config, run_rmk) = match expand_audio_config(keyboard_config) {
+        Some(audio_config) => (
+            audio_config,
+            quote! {
+                ::embassy_futures::join::join(audio_service.run(), async { #run_rmk }).await;
+            },
+        ),
+        None => (quote! {}, run_rmk),
+    };
+    // Run the backlight service together with RMK if there's a PWM backlight
+    let (backlight_config, run_rmk) = match expand_backlight_config(keyboard_config) {
+        Some(backlight_config) => (
+            backlight_config,
+            quote! {
+                ::embassy_futures::join::join(backlight_service.run(), async { #run_rmk }).await;
+            },
+        ),
+        None => (quote! {}, run_rmk),
+    };
     let (ble_config, set_ble_config) = expand_ble_config(keyboard_config);
+    let (bootloader, set_bootloader) = expand_bootloader(keyboard_config);
+    let (macros, set_macros) = expand_macros(keyboard_config);
 
     let main_function_sig = if keyboard_config.chip.series == ChipSeries::Esp32 {
         quote! {
@@ -172,6 +197,18 @@ fn expand_split_central(
             // Initialize split central ble config
             #ble_config
 
+            // Initialize the default bootloader of the chip as `bootloader`
+            #bootloader
+
+            // Default macros in `[[macro]]` as `MACROS`
+            #macros
+
+            // Initialize buzzer and audio service as `audio_service`
+            #audio_config
+
+            // Initialize backlight pwm and backlight service as `backlight_service`
+            #backlight_config
+
             // Set all keyboard config
             let keyboard_config = ::rmk::config::RmkConfig {
                 usb_config: KEYBOARD_USB_CONFIG,
@@ -180,6 +217,8 @@ fn expand_split_central(
                 storage_config,
                 behavior_config,
                 #set_ble_config
+                #set_bootloader
+                #set_macros
                 ..Default::default()
             };
 
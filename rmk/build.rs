@@ -0,0 +1,43 @@
+//! Generate constants which can be configured at compile time by environment variables.
+//!
+//! For example, set them in `.cargo/config.toml` of your project:
+//! ```toml
+//! [env]
+//! RMK_MACRO_SPACE_SIZE = "512"
+//! RMK_NUM_MACRO = "16"
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// (constant name, environment variable, default value, max value)
+const CONSTANTS: [(&str, &str, usize, usize); 2] = [
+    // The macro buffer is saved as a single storage item, which should fit in a 4KB flash sector,
+    // the smallest sector of most supported chips. It's also checked against the actual sector size at runtime
+    // The max value should be same as `MAX_MACRO_SPACE_SIZE` in `src/storage/mod.rs`
+    ("MACRO_SPACE_SIZE", "RMK_MACRO_SPACE_SIZE", 256, 4000),
+    // There are 32 macro keycodes, `Macro0` ~ `Macro31`
+    ("NUM_MACRO", "RMK_NUM_MACRO", 8, 32),
+];
+
+fn main() {
+    let mut constants = String::new();
+    for (name, var, default, max) in CONSTANTS {
+        println!("cargo:rerun-if-env-changed={}", var);
+        let value = match env::var(var) {
+            Ok(v) => v
+                .trim()
+                .parse::<usize>()
+                .unwrap_or_else(|_| panic!("{} should be a number, got \"{}\"", var, v)),
+            Err(_) => default,
+        };
+        if value == 0 || value > max {
+            panic!("{} should be in 1..={}, got {}", var, max, value);
+        }
+        constants.push_str(&format!("pub(crate) const {}: usize = {};\n", name, value));
+    }
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out.join("constants.rs"), constants).unwrap();
+}
@@ -1,144 +1,240 @@
+use byteorder::{BigEndian, ByteOrder};
+use num_enum::FromPrimitive;
+
 use crate::keycode::{KeyCode, ModifierCombination};
 
 /// A KeyAction is the action at a keyboard position, stored in keymap.
 /// It can be a single action like triggering a key, or a composite keyboard action like tap/hold
 ///
-/// Each `KeyAction` can be serialized to a u16 action code. There are 2 patterns of action code's bit-field composition of `KeyAction`:
-///
-/// - KeyActionType(8bits) + BasicAction(8bits)
-///
-/// - KeyActionType(4bits) + Action(12bits)
-///
-/// The `BasicAction` represents only a single key action of keycodes defined in HID spec. The `Action` represents all actions defined in the following `Action` enum, including modifier combination and layer switch.
-///
-/// The KeyActionType bits varies between different types of a KeyAction, see docs of each enum variant.
+/// Each `KeyAction` can be serialized to at most [`KEY_ACTION_MAX_SIZE`] bytes, which is used by the storage.
+/// The first byte is the type of the `KeyAction`, followed by 16-bit big-endian action codes and other arguments, see docs of each enum variant.
+/// The encoding is versioned by [`KEY_ACTION_ENCODING_VERSION`].
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum KeyAction {
-    /// No action. Serialized as 0x0000.
+    /// No action. Serialized as 0x00.
     No,
-    /// Transparent action, next layer will be checked. Serialized as 0x0001.
+    /// Transparent action, next layer will be checked. Serialized as 0x01.
     Transparent,
     /// A single action, such as triggering a key, or activating a layer. Action is triggered when pressed and cancelled when released.
     ///
-    /// Serialized as 0000|Action(12bits).
+    /// Serialized as 0x02|Action(16bits).
     Single(Action),
     /// Don't wait the release of the key, auto-release after a time threshold.
     ///
-    /// Serialized as 0001|Action(12bits).
+    /// Serialized as 0x03|Action(16bits).
     Tap(Action),
     /// Keep current key pressed until the next key is triggered.
     ///
-    /// Serialized as 0010|Action(12bits).
+    /// Serialized as 0x04|Action(16bits).
     OneShot(Action),
     /// Layer tap/hold will trigger different actions: tap for basic action, hold for layer activation.
     ///
-    /// Serialized as 0011|layer(4bits)|BasicAction(8bits).
+    /// Serialized as 0x05|Action(16bits)|layer(8bits).
     LayerTapHold(Action, u8),
     /// Action with the modifier combination triggered.
     ///
-    /// Serialized as 010|modifier(5bits)|BasicAction(8bits).
+    /// Serialized as 0x06|Action(16bits)|modifier(8bits).
     WithModifier(Action, ModifierCombination),
     /// Modifier tap/hold will trigger different actions: tap for basic action, hold for modifier activation.
     ///
-    /// Serialized as 011|modifier(5bits)|BasicAction(8bits).
+    /// Serialized as 0x07|Action(16bits)|modifier(8bits).
     ModifierTapHold(Action, ModifierCombination),
-    /// General tap/hold action. Because current BaseAction actually uses at most 7 bits, so we borrow 1 bit as the identifier of general tap/hold action.
+    /// General tap/hold action, both tap and hold action can be any `Action`.
     ///
-    /// Serialized as 1|BasicAction(7bits)|BasicAction(8bits).
+    /// Serialized as 0x08|TapAction(16bits)|HoldAction(16bits).
     TapHold(Action, Action),
 }
 
+/// Version of the `KeyAction` serialization.
+///
+/// Bump it when the encoding is changed, and migrate the old data in storage.
+pub(crate) const KEY_ACTION_ENCODING_VERSION: u8 = 1;
+
+/// Max size of a serialized `KeyAction`
+pub(crate) const KEY_ACTION_MAX_SIZE: usize = 5;
+
 impl KeyAction {
-    /// Convert a `KeyAction` to corresponding key action code.
-    pub(crate) fn to_key_action_code(self) -> u16 {
+    /// Serialize a `KeyAction` into the buffer, returns the number of written bytes.
+    ///
+    /// The buffer should be at least [`KEY_ACTION_MAX_SIZE`] bytes.
+    pub(crate) fn serialize_into(self, buffer: &mut [u8]) -> usize {
         match self {
-            KeyAction::No => 0x0000,
-            KeyAction::Transparent => 0x0001,
-            KeyAction::Single(a) => a.to_action_code(),
-            KeyAction::Tap(a) => 0x0001 | a.to_action_code(),
-            KeyAction::OneShot(a) => 0x0010 | a.to_action_code(),
+            KeyAction::No => {
+                buffer[0] = 0x00;
+                1
+            }
+            KeyAction::Transparent => {
+                buffer[0] = 0x01;
+                1
+            }
+            KeyAction::Single(a) => Self::write_action(buffer, 0x02, a),
+            KeyAction::Tap(a) => Self::write_action(buffer, 0x03, a),
+            KeyAction::OneShot(a) => Self::write_action(buffer, 0x04, a),
+            KeyAction::LayerTapHold(a, layer) => {
+                Self::write_action(buffer, 0x05, a);
+                buffer[3] = layer;
+                4
+            }
             KeyAction::WithModifier(a, m) => {
-                0x4000 | ((m.into_bits() as u16) << 8) | a.to_basic_action_code()
+                Self::write_action(buffer, 0x06, a);
+                buffer[3] = m.into_bits();
+                4
             }
             KeyAction::ModifierTapHold(a, m) => {
-                0x6000 | ((m.into_bits() as u16) << 8) | a.to_basic_action_code()
-            }
-            KeyAction::LayerTapHold(action, layer) => {
-                if layer < 16 {
-                    0x3000 | ((layer as u16) << 15) | action.to_basic_action_code()
-                } else {
-                    error!("LayerTapHold supports only layer 0~15, got {}", layer);
-                    0x0000
-                }
+                Self::write_action(buffer, 0x07, a);
+                buffer[3] = m.into_bits();
+                4
             }
             KeyAction::TapHold(tap, hold) => {
-                0x8000 | (hold.to_basic_action_code() << 15) | tap.to_basic_action_code()
+                Self::write_action(buffer, 0x08, tap);
+                BigEndian::write_u16(&mut buffer[3..5], hold.to_action_code());
+                5
+            }
+        }
+    }
+
+    /// Deserialize a `KeyAction` from the buffer, returns `None` if the data is invalid.
+    pub(crate) fn deserialize_from(buffer: &[u8]) -> Option<Self> {
+        let ty = *buffer.first()?;
+        let action = || -> Option<Action> {
+            Action::from_action_code(BigEndian::read_u16(buffer.get(1..3)?))
+        };
+        let arg = || buffer.get(3).copied();
+        match ty {
+            0x00 => Some(KeyAction::No),
+            0x01 => Some(KeyAction::Transparent),
+            0x02 => Some(KeyAction::Single(action()?)),
+            0x03 => Some(KeyAction::Tap(action()?)),
+            0x04 => Some(KeyAction::OneShot(action()?)),
+            0x05 => Some(KeyAction::LayerTapHold(action()?, arg()?)),
+            0x06 => Some(KeyAction::WithModifier(
+                action()?,
+                ModifierCombination::from_bits(arg()?),
+            )),
+            0x07 => Some(KeyAction::ModifierTapHold(
+                action()?,
+                ModifierCombination::from_bits(arg()?),
+            )),
+            0x08 => {
+                let hold = Action::from_action_code(BigEndian::read_u16(buffer.get(3..5)?))?;
+                Some(KeyAction::TapHold(action()?, hold))
             }
+            _ => None,
         }
     }
+
+    fn write_action(buffer: &mut [u8], ty: u8, action: Action) -> usize {
+        buffer[0] = ty;
+        BigEndian::write_u16(&mut buffer[1..3], action.to_action_code());
+        3
+    }
 }
 
 /// A single basic action that a keyboard can execute.
-/// An Action can be represented in 12 bits, aka 0x000 ~ 0xFFF
+/// An Action can be represented in 16 bits, see [`Action::to_action_code`]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Action {
     /// A normal key stroke, uses for all keycodes defined in `KeyCode` enum, including mouse key, consumer/system control, etc.
     ///
-    /// Uses 0x000 ~ 0xCFF
+    /// Uses 0x0000 ~ 0xDFFF
     Key(KeyCode),
     /// Modifier Combination, used for oneshot keyaction.
     ///
-    /// Uses 0xE00 ~ 0xE1F. Serialized as 1110|000|modifier(5bits)
+    /// Uses 0xE000 ~ 0xE0FF. Serialized as 0xE0|modifier(8bits)
     Modifier(ModifierCombination),
     /// Activate a layer
     ///
-    /// Uses 0xE20 ~ 0xE3F. Serialized as 1110|001|layer_num(5bits)
+    /// Uses 0xE100 ~ 0xE1FF. Serialized as 0xE1|layer_num(8bits)
     LayerOn(u8),
     /// Deactivate a layer
     ///
-    /// Uses 0xE40 ~ 0xE5F. Serialized as 1110|010|layer_num(5bits)
+    /// Uses 0xE200 ~ 0xE2FF. Serialized as 0xE2|layer_num(8bits)
     LayerOff(u8),
     /// Toggle a layer
     ///
-    /// Uses 0xE60 ~ 0xE7F. Serialized as 1110|011|layer_num(5bits)
+    /// Uses 0xE300 ~ 0xE3FF. Serialized as 0xE3|layer_num(8bits)
     LayerToggle(u8),
     /// Set default layer
     ///
-    /// Uses 0xE80 ~ 0xE9F. Serialized as 1110|100|layer_num(5bits)
+    /// Uses 0xE400 ~ 0xE4FF. Serialized as 0xE4|layer_num(8bits)
     DefaultLayer(u8),
     /// Activate a layer and deactivate all other layers(except default layer)
     ///
-    /// Uses 0xEA0 ~ 0xEBF. Serialized as 1110|101|layer_num(5bits)
+    /// Uses 0xE500 ~ 0xE5FF. Serialized as 0xE5|layer_num(8bits)
     LayerToggleOnly(u8),
 }
 
 impl Action {
-    /// Convert an `Action` to 12-bit action code
+    /// Convert an `Action` to 16-bit action code
+    ///
+    /// - 0x0000 ~ 0xDFFF: `Key(KeyCode)`, the action code is the keycode
+    /// - 0xE0XX: `Modifier`, XX is the bits of modifier combination
+    /// - 0xE1XX ~ 0xE5XX: layer actions, XX is the layer number
     pub(crate) fn to_action_code(self) -> u16 {
         match self {
             Action::Key(k) => k as u16,
-            Action::Modifier(m) => 0xE00 | (m.into_bits() as u16),
-            Action::LayerOn(layer) => 0xE20 | (layer as u16),
-            Action::LayerOff(layer) => 0xE40 | (layer as u16),
-            Action::LayerToggle(layer) => 0xE60 | (layer as u16),
-            Action::DefaultLayer(layer) => 0xE80 | (layer as u16),
-            Action::LayerToggleOnly(layer) => 0xEA0 | (layer as u16),
+            Action::Modifier(m) => 0xE000 | (m.into_bits() as u16),
+            Action::LayerOn(layer) => 0xE100 | (layer as u16),
+            Action::LayerOff(layer) => 0xE200 | (layer as u16),
+            Action::LayerToggle(layer) => 0xE300 | (layer as u16),
+            Action::DefaultLayer(layer) => 0xE400 | (layer as u16),
+            Action::LayerToggleOnly(layer) => 0xE500 | (layer as u16),
         }
     }
 
-    /// Convert an `Action` to 8-bit basic action code, only applicable for `Key(BasicKeyCode)`
-    pub(crate) fn to_basic_action_code(self) -> u16 {
-        match self {
-            Action::Key(kc) => {
-                if kc.is_basic() {
-                    kc as u16
-                } else {
-                    0
-                }
-            }
-            _ => 0,
+    /// Convert a 16-bit action code to `Action`, returns `None` if the code is invalid
+    pub(crate) fn from_action_code(code: u16) -> Option<Self> {
+        let arg = code as u8;
+        match code >> 8 {
+            0x00..=0xDF => Some(Action::Key(KeyCode::from_primitive(code))),
+            0xE0 => Some(Action::Modifier(ModifierCombination::from_bits(arg))),
+            0xE1 => Some(Action::LayerOn(arg)),
+            0xE2 => Some(Action::LayerOff(arg)),
+            0xE3 => Some(Action::LayerToggle(arg)),
+            0xE4 => Some(Action::DefaultLayer(arg)),
+            0xE5 => Some(Action::LayerToggleOnly(arg)),
+            _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key_action_serialization() {
+        let actions = [
+            KeyAction::No,
+            KeyAction::Transparent,
+            KeyAction::Single(Action::Key(KeyCode::A)),
+            KeyAction::Tap(Action::Key(KeyCode::AudioVolUp)),
+            KeyAction::OneShot(Action::LayerOn(20)),
+            KeyAction::LayerTapHold(Action::Key(KeyCode::Space), 31),
+            KeyAction::WithModifier(
+                Action::Key(KeyCode::C),
+                ModifierCombination::new_from(false, false, false, false, true),
+            ),
+            KeyAction::ModifierTapHold(
+                Action::Key(KeyCode::Escape),
+                ModifierCombination::new_from(true, false, true, false, false),
+            ),
+            // Tap/hold with non-basic actions
+            KeyAction::TapHold(
+                Action::Key(KeyCode::MediaPlayPause),
+                Action::LayerToggle(17),
+            ),
+        ];
+        let mut buffer = [0; KEY_ACTION_MAX_SIZE];
+        for action in actions {
+            let len = action.serialize_into(&mut buffer);
+            assert_eq!(Some(action), KeyAction::deserialize_from(&buffer[..len]));
+        }
+
+        // Invalid type and truncated data
+        assert_eq!(None, KeyAction::deserialize_from(&[0x09, 0x00, 0x04]));
+        assert_eq!(None, KeyAction::deserialize_from(&[0x08, 0x00, 0x04]));
+    }
+}
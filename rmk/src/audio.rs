@@ -0,0 +1,259 @@
+//! Audio(buzzer) support
+//!
+//! The [`AudioService`] drives a buzzer via a PWM output, which implements [`TonePwm`].
+//! It plays songs when the keyboard starts, the layer changes or capslock is turned on,
+//! and plays key clicks when clicky mode is enabled.
+//! Audio settings are saved to the storage and restored at startup.
+//!
+//! The audio service should be run concurrently with RMK:
+//! ```rust
+//! let mut audio = AudioService::new(pwm, AudioConfig::default());
+//! embassy_futures::join::join(audio.run(), run_rmk(/* .. arguments */)).await;
+//! ```
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::Timer;
+use embedded_hal::pwm::{Error, SetDutyCycle};
+
+use crate::{
+    config::AudioConfig,
+    keycode::KeyCode,
+    storage::{eeconfig::EeAudioConfig, FlashOperationMessage, FLASH_CHANNEL},
+};
+
+pub(crate) static AUDIO_CHANNEL: Channel<CriticalSectionRawMutex, AudioEvent, 8> = Channel::new();
+
+/// Maximum audio level, which is the max value of the 6-bit level in [`EeAudioConfig`]
+const MAX_AUDIO_LEVEL: u8 = 63;
+/// Audio level step of `AudioVolumeUp` and `AudioVolumeDown`
+const AUDIO_LEVEL_STEP: u8 = 8;
+
+/// Range and step factor of the clicky frequency, same as QMK
+const CLICKY_FREQ_MIN: u16 = 65;
+const CLICKY_FREQ_MAX: u16 = 1500;
+/// 2^(1/4), in percent
+const CLICKY_FREQ_FACTOR: u32 = 119;
+
+/// PWM output which can play tones.
+///
+/// The duty cycle controls the volume, and the PWM frequency is the tone frequency.
+pub trait TonePwm: SetDutyCycle {
+    /// Set the output frequency in Hz
+    fn set_frequency(&mut self, freq: u32);
+}
+
+/// A single note of a song
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Note {
+    /// Frequency in Hz, 0 means rest
+    pub freq: u16,
+    /// Duration in milliseconds
+    pub duration: u16,
+}
+
+impl Note {
+    pub const fn new(freq: u16, duration: u16) -> Self {
+        Self { freq, duration }
+    }
+
+    pub const fn rest(duration: u16) -> Self {
+        Self { freq: 0, duration }
+    }
+}
+
+/// A song is a sequence of notes
+pub type Song = &'static [Note];
+
+/// Predefined songs
+pub mod songs {
+    use super::Note;
+
+    pub const STARTUP: &[Note] = &[
+        Note::new(523, 100),
+        Note::new(659, 100),
+        Note::new(784, 100),
+        Note::new(1047, 200),
+    ];
+    pub const LAYER_CHANGE: &[Note] = &[Note::new(880, 60), Note::new(1175, 60)];
+    pub const CAPS_LOCK_ON: &[Note] = &[Note::new(784, 80), Note::new(1047, 120)];
+    pub const CAPS_LOCK_OFF: &[Note] = &[Note::new(1047, 80), Note::new(784, 120)];
+    pub const AUDIO_ON: &[Note] = &[Note::new(659, 80), Note::new(988, 120)];
+}
+
+/// Events which trigger the audio service
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum AudioEvent {
+    /// The keyboard is started
+    Startup,
+    /// A key is pressed
+    KeyPress,
+    /// The layer is changed
+    LayerChange,
+    /// Capslock state is changed
+    CapsLock(bool),
+    /// An audio keycode is pressed
+    Key(KeyCode),
+    /// Audio config read from the storage
+    LoadConfig(EeAudioConfig),
+}
+
+/// Send an event to the audio service.
+///
+/// The event is dropped if the audio service isn't running or is busy, so that the keyboard is never blocked.
+pub(crate) fn send_audio_event(event: AudioEvent) {
+    let _ = AUDIO_CHANNEL.try_send(event);
+}
+
+/// Buzzer service
+pub struct AudioService<P: TonePwm> {
+    pwm: P,
+    config: AudioConfig,
+    /// Settings which are saved in the storage
+    settings: EeAudioConfig,
+    /// Current frequency of key clicks
+    clicky_freq: u16,
+    /// Current capslock state, used to play the capslock song only when the state changes
+    capslock: bool,
+    started: bool,
+}
+
+impl<P: TonePwm> AudioService<P> {
+    pub fn new(pwm: P, config: AudioConfig) -> Self {
+        Self {
+            pwm,
+            settings: EeAudioConfig::new()
+                .with_enable(true)
+                .with_clicky_enable(config.clicky_enable)
+                .with_level(MAX_AUDIO_LEVEL),
+            clicky_freq: config.clicky_freq,
+            config,
+            capslock: false,
+            started: false,
+        }
+    }
+
+    /// Run the audio service
+    pub async fn run(&mut self) {
+        self.silence();
+        loop {
+            let event = AUDIO_CHANNEL.receive().await;
+            self.process_event(event).await;
+        }
+    }
+
+    async fn process_event(&mut self, event: AudioEvent) {
+        match event {
+            AudioEvent::Startup => {
+                // The startup event is sent every time the keyboard reconnects
+                if !self.started {
+                    self.started = true;
+                    self.play_song(self.config.startup_song).await;
+                }
+            }
+            AudioEvent::KeyPress => {
+                if self.settings.clicky_enable() {
+                    self.play_note(Note::new(self.clicky_freq, self.config.clicky_duration))
+                        .await;
+                }
+            }
+            AudioEvent::LayerChange => self.play_song(self.config.layer_change_song).await,
+            AudioEvent::CapsLock(on) => {
+                if on != self.capslock {
+                    self.capslock = on;
+                    let song = if on {
+                        self.config.caps_lock_on_song
+                    } else {
+                        self.config.caps_lock_off_song
+                    };
+                    self.play_song(song).await;
+                }
+            }
+            AudioEvent::Key(key) => self.process_key(key).await,
+            AudioEvent::LoadConfig(settings) => {
+                debug!("Load audio config: {:?}", settings);
+                self.settings = settings;
+            }
+        }
+    }
+
+    async fn process_key(&mut self, key: KeyCode) {
+        let settings = self.settings;
+        match key {
+            KeyCode::AudioOn => self.settings.set_enable(true),
+            KeyCode::AudioOff => self.settings.set_enable(false),
+            KeyCode::AudioToggle => self.settings.set_enable(!settings.enable()),
+            KeyCode::AudioClickyOn => self.settings.set_clicky_enable(true),
+            KeyCode::AudioClickyOff => self.settings.set_clicky_enable(false),
+            KeyCode::AudioClickyToggle => {
+                self.settings.set_clicky_enable(!settings.clicky_enable())
+            }
+            KeyCode::AudioClickyUp => {
+                self.clicky_freq = (self.clicky_freq as u32 * CLICKY_FREQ_FACTOR / 100)
+                    .min(CLICKY_FREQ_MAX as u32) as u16
+            }
+            KeyCode::AudioClickyDown => {
+                self.clicky_freq = (self.clicky_freq as u32 * 100 / CLICKY_FREQ_FACTOR)
+                    .max(CLICKY_FREQ_MIN as u32) as u16
+            }
+            KeyCode::AudioClickyReset => self.clicky_freq = self.config.clicky_freq,
+            KeyCode::AudioVolumeUp => self
+                .settings
+                .set_level((settings.level() + AUDIO_LEVEL_STEP).min(MAX_AUDIO_LEVEL)),
+            // Level 0 mutes the buzzer
+            KeyCode::AudioVolumeDown => self
+                .settings
+                .set_level(settings.level().saturating_sub(AUDIO_LEVEL_STEP)),
+            _ => warn!("Unsupported audio key: {:?}", key),
+        }
+
+        if self.settings != settings {
+            if self.settings.enable() && !settings.enable() {
+                self.play_song(songs::AUDIO_ON).await;
+            } else if self.settings.level() != settings.level() {
+                // Play a click with the new volume
+                self.play_note(Note::new(self.clicky_freq, self.config.clicky_duration))
+                    .await;
+            }
+            // Save the settings, don't block if the storage is busy
+            if FLASH_CHANNEL
+                .try_send(FlashOperationMessage::AudioConfig(self.settings))
+                .is_err()
+            {
+                warn!("Flash channel is full, audio config isn't saved");
+            }
+        }
+    }
+
+    /// Play a song, do nothing if audio is disabled
+    pub async fn play_song(&mut self, song: Song) {
+        for note in song {
+            self.play_note(*note).await;
+        }
+    }
+
+    async fn play_note(&mut self, note: Note) {
+        if !self.settings.enable() {
+            return;
+        }
+        if note.freq == 0 {
+            self.silence();
+        } else {
+            self.pwm.set_frequency(note.freq as u32);
+            // 50% duty cycle is the loudest
+            let percent = self.settings.level() as u16 * 50 / MAX_AUDIO_LEVEL as u16;
+            if let Err(e) = self.pwm.set_duty_cycle_percent(percent as u8) {
+                error!("Set buzzer duty cycle error: {:?}", e.kind());
+            }
+        }
+        Timer::after_millis(note.duration as u64).await;
+        self.silence();
+    }
+
+    fn silence(&mut self) {
+        if let Err(e) = self.pwm.set_duty_cycle_fully_off() {
+            error!("Turn off buzzer error: {:?}", e.kind());
+        }
+    }
+}
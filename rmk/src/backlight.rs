@@ -0,0 +1,204 @@
+//! Backlight support
+//!
+//! The [`BacklightService`] drives single color backlight LEDs via a PWM output, which implements [`SetDutyCycle`].
+//! The brightness is divided into levels, which can be changed by backlight keycodes.
+//! When breathing is enabled, the brightness fades in and out periodically.
+//! Backlight settings are saved to the storage and restored at startup.
+//!
+//! The backlight service should be run concurrently with RMK:
+//! ```rust
+//! let mut backlight = BacklightService::new(pwm, BacklightConfig::default());
+//! embassy_futures::join::join(backlight.run(), run_rmk(/* .. arguments */)).await;
+//! ```
+
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Ticker};
+use embedded_hal::pwm::{Error, SetDutyCycle};
+
+use crate::{
+    config::BacklightConfig,
+    keycode::KeyCode,
+    storage::{eeconfig::EeBacklightConfig, FlashOperationMessage, FLASH_CHANNEL},
+};
+
+pub(crate) static BACKLIGHT_CHANNEL: Channel<CriticalSectionRawMutex, BacklightEvent, 8> =
+    Channel::new();
+
+/// Maximum number of backlight levels, which is the max value of the 5-bit level in [`EeBacklightConfig`]
+const MAX_BACKLIGHT_LEVELS: u8 = 31;
+
+/// Number of brightness steps in a breathing period
+const BREATHING_STEPS: u16 = 128;
+
+/// Max value of the breathing curve
+const BREATHING_MAX: u16 = 255;
+
+/// Events which trigger the backlight service
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum BacklightEvent {
+    /// A backlight keycode is pressed
+    Key(KeyCode),
+    /// Backlight config read from the storage
+    LoadConfig(EeBacklightConfig),
+}
+
+/// Send an event to the backlight service.
+///
+/// The event is dropped if the backlight service isn't running or is busy, so that the keyboard is never blocked.
+pub(crate) fn send_backlight_event(event: BacklightEvent) {
+    let _ = BACKLIGHT_CHANNEL.try_send(event);
+}
+
+/// Backlight service
+pub struct BacklightService<P: SetDutyCycle> {
+    pwm: P,
+    /// Number of brightness levels, level 0 is always off
+    levels: u8,
+    breathing_period: u8,
+    /// Settings which are saved in the storage
+    settings: EeBacklightConfig,
+    /// Current step in the breathing period
+    breathing_step: u16,
+}
+
+impl<P: SetDutyCycle> BacklightService<P> {
+    pub fn new(pwm: P, config: BacklightConfig) -> Self {
+        let levels = config.levels.clamp(1, MAX_BACKLIGHT_LEVELS);
+        Self {
+            pwm,
+            levels,
+            breathing_period: config.breathing_period.max(1),
+            settings: EeBacklightConfig::new()
+                .with_enable(config.enable)
+                .with_breathing(config.breathing)
+                .with_level(levels),
+            breathing_step: 0,
+        }
+    }
+
+    /// Run the backlight service
+    pub async fn run(&mut self) {
+        self.update();
+        loop {
+            if self.settings.enable() && self.settings.breathing() {
+                if let Either::First(event) =
+                    select(BACKLIGHT_CHANNEL.receive(), self.breathe()).await
+                {
+                    self.process_event(event);
+                }
+            } else {
+                let event = BACKLIGHT_CHANNEL.receive().await;
+                self.process_event(event);
+            }
+        }
+    }
+
+    fn process_event(&mut self, event: BacklightEvent) {
+        match event {
+            BacklightEvent::Key(key) => self.process_key(key),
+            BacklightEvent::LoadConfig(settings) => {
+                debug!("Load backlight config: {:?}", settings);
+                self.settings = settings.with_level(settings.level().min(self.levels));
+                self.update();
+            }
+        }
+    }
+
+    fn process_key(&mut self, key: KeyCode) {
+        let settings = self.settings;
+        let level = settings.level();
+        match key {
+            KeyCode::BacklightOn => self.turn_on(),
+            KeyCode::BacklightOff => self.settings.set_enable(false),
+            KeyCode::BacklightToggle => {
+                if settings.enable() {
+                    self.settings.set_enable(false);
+                } else {
+                    self.turn_on();
+                }
+            }
+            KeyCode::BacklightDown => {
+                let level = level.saturating_sub(1);
+                self.settings.set_level(level);
+                self.settings.set_enable(level != 0);
+            }
+            KeyCode::BacklightUp => {
+                self.settings.set_level((level + 1).min(self.levels));
+                self.settings.set_enable(true);
+            }
+            KeyCode::BacklightStep => {
+                // Cycle through all levels, then turn off the backlight
+                let level = if level >= self.levels { 0 } else { level + 1 };
+                self.settings.set_level(level);
+                self.settings.set_enable(level != 0);
+            }
+            KeyCode::BacklightToggleBreathing => self.settings.set_breathing(!settings.breathing()),
+            _ => warn!("Unsupported backlight key: {:?}", key),
+        }
+
+        if self.settings != settings {
+            self.update();
+            // Save the settings, don't block if the storage is busy
+            if FLASH_CHANNEL
+                .try_send(FlashOperationMessage::BacklightConfig(self.settings))
+                .is_err()
+            {
+                warn!("Flash channel is full, backlight config isn't saved");
+            }
+        }
+    }
+
+    /// Turn on the backlight, the lowest level is used if current level is 0
+    fn turn_on(&mut self) {
+        self.settings.set_enable(true);
+        if self.settings.level() == 0 {
+            self.settings.set_level(1);
+        }
+    }
+
+    /// Breathing animation, runs until it's cancelled
+    async fn breathe(&mut self) {
+        let step_ms = self.breathing_period as u64 * 1000 / BREATHING_STEPS as u64;
+        let mut ticker = Ticker::every(Duration::from_millis(step_ms.max(1)));
+        loop {
+            self.breathing_step = (self.breathing_step + 1) % BREATHING_STEPS;
+            let brightness = breathing_curve(self.breathing_step);
+            self.set_brightness(
+                self.settings.level() as u16 * brightness,
+                self.levels as u16 * BREATHING_MAX,
+            );
+            ticker.next().await;
+        }
+    }
+
+    /// Apply current settings to the backlight
+    fn update(&mut self) {
+        if self.settings.enable() {
+            self.set_brightness(self.settings.level() as u16, self.levels as u16);
+        } else {
+            self.set_brightness(0, 1);
+        }
+    }
+
+    /// Set the brightness as a fraction of the max brightness
+    fn set_brightness(&mut self, num: u16, denom: u16) {
+        if let Err(e) = self.pwm.set_duty_cycle_fraction(num, denom) {
+            error!("Set backlight duty cycle error: {:?}", e.kind());
+        }
+    }
+}
+
+/// Brightness of the given breathing step, 0 ~ `BREATHING_MAX`.
+///
+/// The brightness rises and falls linearly, then is squared to look smoother to human eyes.
+fn breathing_curve(step: u16) -> u16 {
+    let half = BREATHING_STEPS / 2;
+    let linear = if step < half {
+        step
+    } else {
+        BREATHING_STEPS - step
+    };
+    (linear as u32 * linear as u32 * BREATHING_MAX as u32 / (half as u32 * half as u32)) as u16
+}
@@ -1,9 +1,12 @@
+use heapless::Vec;
 use usbd_hid::descriptor::generator_prelude::*;
 
+use crate::usb::descriptor::{composite_report_desc, COMPOSITE_REPORT_DESC_LEN};
+
 ///! HID Descriptor used in BLE keyboard, which might be different from USB HID device
 
 /// Predefined report ids for composite BLE hid report. The report id of BLE should start from 0x01
-/// Should be same with `#[gen_hid_descriptor]`
+/// Should be same with `#[gen_hid_descriptor]` and [`BLE_COMPOSITE_REPORT_DESC`]
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum BleCompositeReportType {
@@ -12,6 +15,34 @@ pub(crate) enum BleCompositeReportType {
     Media = 0x03,
     System = 0x04,
     Vial = 0x05,
+    Joystick = 0x06,
+}
+
+/// Max length of the BLE report map
+const BLE_REPORT_MAP_SIZE: usize = 384;
+
+/// Mouse, consumer control and system control part of the BLE report map
+const BLE_COMPOSITE_REPORT_DESC: [u8; COMPOSITE_REPORT_DESC_LEN] = composite_report_desc(
+    BleCompositeReportType::Mouse as u8,
+    BleCompositeReportType::Media as u8,
+    BleCompositeReportType::System as u8,
+);
+
+/// Report map of the BLE hid service.
+///
+/// The mouse report isn't generated by `#[gen_hid_descriptor]`, so it's appended to the descriptor of [`BleKeyboardReport`].
+/// The gamepad report is appended if `joystick` feature is enabled.
+pub(crate) fn ble_report_map() -> Vec<u8, BLE_REPORT_MAP_SIZE> {
+    let mut report_map = Vec::new();
+    let result = report_map
+        .extend_from_slice(BleKeyboardReport::desc())
+        .and_then(|_| report_map.extend_from_slice(&BLE_COMPOSITE_REPORT_DESC));
+    #[cfg(feature = "joystick")]
+    let result = result.and_then(|_| report_map.extend_from_slice(BleJoystickReport::desc()));
+    if result.is_err() {
+        error!("BLE report map is too long");
+    }
+    report_map
 }
 
 /// KeyboardReport describes a report and its companion descriptor that can be
@@ -33,45 +64,6 @@ pub(crate) enum BleCompositeReportType {
                 #[item_settings data,array,absolute] keycodes=input;
             };
         };
-    },
-    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = MOUSE) = {
-        (collection = PHYSICAL, usage = POINTER) = {
-            (report_id = 0x02,) = {
-                (usage_page = BUTTON, usage_min = BUTTON_1, usage_max = BUTTON_8) = {
-                    #[packed_bits 8] #[item_settings data,variable,absolute] buttons=input;
-                };
-                (usage_page = GENERIC_DESKTOP,) = {
-                    (usage = X,) = {
-                        #[item_settings data,variable,relative] x=input;
-                    };
-                    (usage = Y,) = {
-                        #[item_settings data,variable,relative] y=input;
-                    };
-                    (usage = WHEEL,) = {
-                        #[item_settings data,variable,relative] wheel=input;
-                    };
-                };
-                (usage_page = CONSUMER,) = {
-                    (usage = AC_PAN,) = {
-                        #[item_settings data,variable,relative] pan=input;
-                    };
-                };
-            };
-        };
-    },
-    (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL) = {
-        (report_id = 0x03,) = {
-            (usage_page = CONSUMER, usage_min = 0x00, usage_max = 0x514) = {
-            #[item_settings data,array,absolute,not_null] media_usage_id=input;
-            }
-        };
-    },
-    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = SYSTEM_CONTROL) = {
-        (report_id = 0x04,) = {
-            (usage_min = 0x81, usage_max = 0xB7, logical_min = 1) = {
-                #[item_settings data,array,absolute,not_null] system_usage_id=input;
-            };
-        };
     }
 )]
 #[allow(dead_code)]
@@ -80,11 +72,46 @@ pub(crate) struct BleKeyboardReport {
     pub(crate) reserved: u8,
     pub(crate) leds: u8,
     pub(crate) keycodes: [u8; 6],
-    pub(crate) buttons: u8,
-    pub(crate) x: i8,
-    pub(crate) y: i8,
-    pub(crate) wheel: i8, // Scroll down (negative) or up (positive) this many units
-    pub(crate) pan: i8,   // Scroll left (negative) or right (positive) this many units
-    pub(crate) media_usage_id: u16,
-    pub(crate) system_usage_id: u8,
+}
+
+/// Gamepad report of BLE, it's same with [`crate::usb::descriptor::JoystickReport`] but with a report id
+#[cfg(feature = "joystick")]
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = 0x05) = {
+        (report_id = 0x06,) = {
+            (usage_page = BUTTON, usage_min = 0x01, usage_max = 0x20) = {
+                #[packed_bits 32] #[item_settings data,variable,absolute] joystick_buttons=input;
+            };
+            (usage_page = GENERIC_DESKTOP,) = {
+                (usage = X,) = {
+                    #[item_settings data,variable,absolute] joystick_x=input;
+                };
+                (usage = Y,) = {
+                    #[item_settings data,variable,absolute] joystick_y=input;
+                };
+                (usage = 0x32,) = {
+                    #[item_settings data,variable,absolute] joystick_z=input;
+                };
+                (usage = 0x33,) = {
+                    #[item_settings data,variable,absolute] joystick_rx=input;
+                };
+                (usage = 0x34,) = {
+                    #[item_settings data,variable,absolute] joystick_ry=input;
+                };
+                (usage = 0x35,) = {
+                    #[item_settings data,variable,absolute] joystick_rz=input;
+                };
+            };
+        };
+    }
+)]
+#[allow(dead_code)]
+pub(crate) struct BleJoystickReport {
+    pub(crate) joystick_buttons: u32,
+    pub(crate) joystick_x: i16,
+    pub(crate) joystick_y: i16,
+    pub(crate) joystick_z: i16,
+    pub(crate) joystick_rx: i16,
+    pub(crate) joystick_ry: i16,
+    pub(crate) joystick_rz: i16,
 }
@@ -1,9 +1,13 @@
 pub(crate) mod server;
 
 use self::server::{BleServer, VialReaderWriter};
+use crate::bootloader::set_bootloader;
 use crate::config::StorageConfig;
+use crate::input_device::pointing::set_resolution_multiplier;
 use crate::keyboard::KEYBOARD_REPORT_CHANNEL;
+use crate::light::{led_service_task, update_indicator_state, LedIndicator, LightService};
 use crate::matrix::MatrixTrait;
+use crate::raw_hid::set_raw_hid_handler;
 use crate::storage::nor_flash::esp_partition::{Partition, PartitionType};
 use crate::storage::Storage;
 use crate::via::process::VialService;
@@ -51,6 +55,7 @@ pub async fn initialize_esp_ble_keyboard_with_config_and_run<
     let mut storage = Storage::new(
         f,
         &default_keymap,
+        keyboard_config.default_macros,
         StorageConfig {
             start_addr: 0,
             num_sectors,
@@ -59,7 +64,18 @@ pub async fn initialize_esp_ble_keyboard_with_config_and_run<
     )
     .await;
 
-    let keymap = RefCell::new(KeyMap::new_from_storage(default_keymap, Some(&mut storage)).await);
+    let keymap = RefCell::new(
+        KeyMap::new_from_storage(
+            default_keymap,
+            Some(&mut storage),
+            keyboard_config.default_macros,
+        )
+        .await,
+    );
+
+    // The bootloader is used by both the keyboard and Via
+    set_bootloader(keyboard_config.bootloader);
+    set_raw_hid_handler(keyboard_config.raw_hid_handler);
 
     let keyboard_report_sender = KEYBOARD_REPORT_CHANNEL.sender();
     let keyboard_report_receiver = KEYBOARD_REPORT_CHANNEL.receiver();
@@ -68,12 +84,15 @@ pub async fn initialize_esp_ble_keyboard_with_config_and_run<
         &keymap,
         &keyboard_report_sender,
         keyboard_config.behavior_config,
+        keyboard_config.hooks,
+        keyboard_config.processor,
     );
     // esp32c3 doesn't have USB device, so there is no usb here
     // TODO: add usb service for other chips of esp32 which have USB device
 
     static via_output: Channel<CriticalSectionRawMutex, [u8; 32], 2> = Channel::new();
     let mut vial_service = VialService::new(&keymap, keyboard_config.vial_config);
+    let mut light_service = LightService::from_config(keyboard_config.light_config);
     loop {
         KEYBOARD_STATE.store(false, core::sync::atomic::Ordering::Release);
         CONNECTION_STATE.store(false, core::sync::atomic::Ordering::Release);
@@ -82,6 +101,18 @@ pub async fn initialize_esp_ble_keyboard_with_config_and_run<
         ble_server.output_keyboard.lock().on_write(|args| {
             let data: &[u8] = args.recv_data();
             debug!("output_keyboard {}, {}", data.len(), data[0]);
+            if let Some(&leds) = data.first() {
+                update_indicator_state(|s| s.leds = LedIndicator::from_bits(leds));
+            }
+        });
+        // The host enables high-resolution scrolling and touchpad mode again after connected
+        set_resolution_multiplier(0);
+        #[cfg(feature = "touchpad")]
+        crate::input_device::touchpad::reset_touchpad_mode();
+        ble_server.feature_mouse.lock().on_write(|args| {
+            if let Some(feature) = args.recv_data().first() {
+                set_resolution_multiplier(*feature);
+            }
         });
 
         info!("Waitting for connection..");
@@ -89,12 +120,21 @@ pub async fn initialize_esp_ble_keyboard_with_config_and_run<
 
         info!("BLE connected!");
         CONNECTION_STATE.store(true, core::sync::atomic::Ordering::Release);
+        update_indicator_state(|s| {
+            s.ble_connected = true;
+            // There's only one BLE profile on ESP32
+            s.ble_profile = 0;
+            // The battery level isn't measured on ESP32, the reported level is always 80%
+            s.battery_low = false;
+        });
 
         // Create BLE HID writers
         let mut keyboard_writer = ble_server.input_keyboard;
         let mut media_writer = ble_server.input_media_keys;
         let mut system_writer = ble_server.input_system_keys;
         let mut mouse_writer = ble_server.input_mouse_keys;
+        #[cfg(feature = "joystick")]
+        let mut joystick_writer = ble_server.input_joystick;
 
         let disconnect = BleServer::wait_for_disconnection(ble_server.server);
 
@@ -105,6 +145,8 @@ pub async fn initialize_esp_ble_keyboard_with_config_and_run<
             &mut media_writer,
             &mut system_writer,
             &mut mouse_writer,
+            #[cfg(feature = "joystick")]
+            &mut joystick_writer,
         );
 
         ble_server.output_vial.lock().on_write(|args| {
@@ -119,21 +161,24 @@ pub async fn initialize_esp_ble_keyboard_with_config_and_run<
         let via_fut = vial_task(&mut via_rw, &mut vial_service);
         let matrix_fut = matrix.run();
         let storage_fut = storage.run();
+        let led_fut = led_service_task(&mut light_service);
         pin_mut!(storage_fut);
         pin_mut!(via_fut);
         pin_mut!(keyboard_fut);
         pin_mut!(disconnect);
         pin_mut!(ble_fut);
         pin_mut!(matrix_fut);
+        pin_mut!(led_fut);
 
         select4(
             select(storage_fut, keyboard_fut),
             select(disconnect, matrix_fut),
             ble_fut,
-            via_fut,
+            select(via_fut, led_fut),
         )
         .await;
 
+        update_indicator_state(|s| s.ble_connected = false);
         warn!("BLE disconnected!")
     }
 }
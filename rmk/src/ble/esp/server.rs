@@ -13,7 +13,7 @@ use usbd_hid::descriptor::{AsInputReport, SerializedDescriptor as _};
 use crate::{
     ble::{
         as_bytes,
-        descriptor::{BleCompositeReportType, BleKeyboardReport},
+        descriptor::{ble_report_map, BleCompositeReportType},
         device_info::VidSource,
     },
     config::KeyboardUsbConfig,
@@ -66,6 +66,9 @@ pub(crate) struct BleServer {
     pub(crate) input_media_keys: BleHidWriter,
     pub(crate) input_system_keys: BleHidWriter,
     pub(crate) input_mouse_keys: BleHidWriter,
+    pub(crate) feature_mouse: BleHidReader,
+    #[cfg(feature = "joystick")]
+    pub(crate) input_joystick: BleHidWriter,
     pub(crate) input_vial: BleHidWriter,
     pub(crate) output_vial: BleHidReader,
 }
@@ -102,6 +105,9 @@ impl BleServer {
         let input_media_keys = hid.input_report(BleCompositeReportType::Media as u8);
         let input_system_keys = hid.input_report(BleCompositeReportType::System as u8);
         let input_mouse_keys = hid.input_report(BleCompositeReportType::Mouse as u8);
+        let feature_mouse = hid.feature_report(BleCompositeReportType::Mouse as u8);
+        #[cfg(feature = "joystick")]
+        let input_joystick = hid.input_report(BleCompositeReportType::Joystick as u8);
 
         hid.pnp(
             VidSource::UsbIF as u8,
@@ -111,7 +117,7 @@ impl BleServer {
         );
         hid.set_battery_level(80);
         hid.hid_info(0x00, 0x03);
-        hid.report_map(BleKeyboardReport::desc());
+        hid.report_map(&ble_report_map());
 
         let mut vial_hid = BLEHIDDevice::new(server);
         vial_hid.manufacturer(usb_config.manufacturer);
@@ -155,6 +161,9 @@ impl BleServer {
             input_media_keys,
             input_system_keys,
             input_mouse_keys,
+            feature_mouse,
+            #[cfg(feature = "joystick")]
+            input_joystick,
             input_vial,
             output_vial,
         }
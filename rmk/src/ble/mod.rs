@@ -26,6 +26,7 @@ pub(crate) async fn ble_communication_task<
     W2: HidWriterWrapper,
     W3: HidWriterWrapper,
     W4: HidWriterWrapper,
+    #[cfg(feature = "joystick")] W5: HidWriterWrapper,
 >(
     keyboard_report_receiver: &Receiver<
         'a,
@@ -37,6 +38,7 @@ pub(crate) async fn ble_communication_task<
     ble_media_writer: &mut W2,
     ble_system_control_writer: &mut W3,
     ble_mouse_writer: &mut W4,
+    #[cfg(feature = "joystick")] ble_joystick_writer: &mut W5,
 ) {
     // Wait 1 seconds, ensure that gatt server has been started
     Timer::after_secs(1).await;
@@ -74,6 +76,17 @@ pub(crate) async fn ble_communication_task<
                         CompositeReportType::None => (),
                     };
                 }
+                #[cfg(feature = "joystick")]
+                KeyboardReportMessage::JoystickReport(report) => {
+                    match ble_joystick_writer.write_serialize(&report).await {
+                        Ok(()) => {}
+                        Err(e) => error!("Send joystick report error: {:?}", e),
+                    };
+                }
+                #[cfg(feature = "touchpad")]
+                KeyboardReportMessage::TouchpadReport(_) => {
+                    debug!("Touchpad report isn't supported via BLE")
+                }
             }
         }
     }
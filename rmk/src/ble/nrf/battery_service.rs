@@ -1,4 +1,4 @@
-use crate::config::BleBatteryConfig;
+use crate::{config::BleBatteryConfig, light::update_indicator_state};
 use embassy_time::Timer;
 use nrf_softdevice::ble::Connection;
 
@@ -60,6 +60,7 @@ impl<'a> BatteryService {
                         }
                     },
                 }
+                update_indicator_state(|s| s.battery_low = val < 10);
                 if val < 10 {
                     // The battery is low, blink the led!
                     if let Some(ref mut charge_led) = battery_config.charge_led_pin {
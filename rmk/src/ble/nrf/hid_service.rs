@@ -1,7 +1,10 @@
 use super::spec::{BleCharacteristics, BleDescriptor, BLE_HID_SERVICE_UUID};
 use crate::{
-    ble::descriptor::{BleCompositeReportType, BleKeyboardReport},
-    light::{LedIndicator, LED_CHANNEL},
+    audio::{send_audio_event, AudioEvent},
+    ble::descriptor::{ble_report_map, BleCompositeReportType},
+    input_device::pointing::set_resolution_multiplier,
+    light::{update_indicator_state, LedIndicator},
+    usb::descriptor::{MOUSE_REPORT_SIZE, NUM_CONSUMER_KEYS, NUM_SYSTEM_KEYS},
 };
 use nrf_softdevice::{
     ble::{
@@ -15,7 +18,6 @@ use nrf_softdevice::{
     },
     Softdevice,
 };
-use usbd_hid::descriptor::SerializedDescriptor as _;
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -34,9 +36,17 @@ pub(crate) struct HidService {
     pub(crate) input_mouse_keys: u16,
     input_mouse_keys_cccd: u16,
     input_mouse_keys_descriptor: u16,
+    pub(crate) feature_mouse: u16,
+    feature_mouse_descriptor: u16,
     pub(crate) input_system_keys: u16,
     input_system_keys_cccd: u16,
     input_system_keys_descriptor: u16,
+    #[cfg(feature = "joystick")]
+    pub(crate) input_joystick: u16,
+    #[cfg(feature = "joystick")]
+    input_joystick_cccd: u16,
+    #[cfg(feature = "joystick")]
+    input_joystick_descriptor: u16,
 }
 
 impl HidService {
@@ -59,7 +69,7 @@ impl HidService {
         let report_map_handle = service_builder
             .add_characteristic(
                 BleCharacteristics::ReportMap.uuid(),
-                Attribute::new(BleKeyboardReport::desc()).security(SecurityMode::JustWorks),
+                Attribute::new(ble_report_map()).security(SecurityMode::JustWorks),
                 Metadata::new(Properties::new().read()),
             )?
             .build();
@@ -98,7 +108,7 @@ impl HidService {
 
         let mut input_media_keys = service_builder.add_characteristic(
             BleCharacteristics::HidReport.uuid(),
-            Attribute::new([0u8; 2]).security(SecurityMode::JustWorks),
+            Attribute::new([0u8; 2 * NUM_CONSUMER_KEYS]).security(SecurityMode::JustWorks),
             Metadata::new(Properties::new().read().notify()),
         )?;
         let input_media_keys_desc = input_media_keys.add_descriptor(
@@ -110,7 +120,7 @@ impl HidService {
 
         let mut input_system_keys = service_builder.add_characteristic(
             BleCharacteristics::HidReport.uuid(),
-            Attribute::new([0u8; 1]).security(SecurityMode::JustWorks),
+            Attribute::new([0u8; NUM_SYSTEM_KEYS]).security(SecurityMode::JustWorks),
             Metadata::new(Properties::new().read().notify()),
         )?;
         let input_system_keys_desc = input_system_keys.add_descriptor(
@@ -122,7 +132,7 @@ impl HidService {
 
         let mut input_mouse = service_builder.add_characteristic(
             BleCharacteristics::HidReport.uuid(),
-            Attribute::new([0u8; 5]).security(SecurityMode::JustWorks),
+            Attribute::new([0u8; MOUSE_REPORT_SIZE]).security(SecurityMode::JustWorks),
             Metadata::new(Properties::new().read().notify()),
         )?;
         let input_mouse_desc = input_mouse.add_descriptor(
@@ -132,6 +142,34 @@ impl HidService {
         )?;
         let input_mouse_handle = input_mouse.build();
 
+        // Resolution multiplier of the mouse wheel and pan, it's set by the host
+        let mut feature_mouse = service_builder.add_characteristic(
+            BleCharacteristics::HidReport.uuid(),
+            Attribute::new([0u8; 1]).security(SecurityMode::JustWorks),
+            Metadata::new(Properties::new().read().write()),
+        )?;
+        let feature_mouse_desc = feature_mouse.add_descriptor(
+            BleDescriptor::ReportReference.uuid(),
+            Attribute::new([BleCompositeReportType::Mouse as u8, 3u8])
+                .security(SecurityMode::JustWorks),
+        )?;
+        let feature_mouse_handle = feature_mouse.build();
+
+        #[cfg(feature = "joystick")]
+        let mut input_joystick = service_builder.add_characteristic(
+            BleCharacteristics::HidReport.uuid(),
+            Attribute::new([0u8; 16]).security(SecurityMode::JustWorks),
+            Metadata::new(Properties::new().read().notify()),
+        )?;
+        #[cfg(feature = "joystick")]
+        let input_joystick_desc = input_joystick.add_descriptor(
+            BleDescriptor::ReportReference.uuid(),
+            Attribute::new([BleCompositeReportType::Joystick as u8, 1u8])
+                .security(SecurityMode::JustWorks),
+        )?;
+        #[cfg(feature = "joystick")]
+        let input_joystick_handle = input_joystick.build();
+
         let _service_handle = service_builder.build();
 
         Ok(HidService {
@@ -152,6 +190,14 @@ impl HidService {
             input_mouse_keys: input_mouse_handle.value_handle,
             input_mouse_keys_cccd: input_mouse_handle.cccd_handle,
             input_mouse_keys_descriptor: input_mouse_desc.handle(),
+            feature_mouse: feature_mouse_handle.value_handle,
+            feature_mouse_descriptor: feature_mouse_desc.handle(),
+            #[cfg(feature = "joystick")]
+            input_joystick: input_joystick_handle.value_handle,
+            #[cfg(feature = "joystick")]
+            input_joystick_cccd: input_joystick_handle.cccd_handle,
+            #[cfg(feature = "joystick")]
+            input_joystick_descriptor: input_joystick_desc.handle(),
         })
     }
 
@@ -172,6 +218,10 @@ impl gatt_server::Service for HidService {
     type Event = HidServiceEvent;
 
     fn on_write(&self, handle: u16, data: &[u8]) -> Option<Self::Event> {
+        #[cfg(feature = "joystick")]
+        if handle == self.input_joystick_cccd {
+            return Some(HidServiceEvent::InputJoystickCccdWrite);
+        }
         if handle == self.input_keyboard_cccd {
             Some(HidServiceEvent::InputKeyboardCccdWrite)
         } else if handle == self.input_media_keys_cccd {
@@ -184,14 +234,14 @@ impl gatt_server::Service for HidService {
             // Fires if a keyboard output is changed - e.g. the caps lock LED
             let led_indicator = LedIndicator::from_bits(data[0]);
             info!("HID output keyboard: {:?}", led_indicator);
-            // Retry 3 times in case the channel is full(which is really rare)
-            for _i in 0..3 {
-                match LED_CHANNEL.try_send(led_indicator) {
-                    Ok(_) => break,
-                    Err(e) => warn!("LED channel full, retrying: {:?}", e),
-                }
-            }
+            send_audio_event(AudioEvent::CapsLock(led_indicator.capslock()));
+            update_indicator_state(|s| s.leds = led_indicator);
             Some(HidServiceEvent::OutputKeyboard)
+        } else if handle == self.feature_mouse {
+            if let Some(feature) = data.first() {
+                set_resolution_multiplier(*feature);
+            }
+            Some(HidServiceEvent::FeatureMouse)
         } else {
             None
         }
@@ -206,5 +256,7 @@ pub(crate) enum HidServiceEvent {
     InputMediaKeyCccdWrite,
     InputMouseKeyCccdWrite,
     InputSystemKeyCccdWrite,
+    InputJoystickCccdWrite,
     OutputKeyboard,
+    FeatureMouse,
 }
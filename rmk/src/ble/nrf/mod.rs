@@ -9,9 +9,15 @@ pub(crate) mod spec;
 mod vial_service;
 
 use self::server::BleServer;
+use crate::bootloader::set_bootloader;
 use crate::config::BleBatteryConfig;
 use crate::keyboard::{KEYBOARD_REPORT_CHANNEL, REPORT_CHANNEL_SIZE};
 use crate::matrix::MatrixTrait;
+use crate::raw_hid::set_raw_hid_handler;
+#[cfg(all(feature = "midi", not(feature = "_no_usb")))]
+use crate::sequencer::Sequencer;
+#[cfg(all(feature = "steno", not(feature = "_no_usb")))]
+use crate::steno::Steno;
 use crate::storage::StorageKeys;
 use crate::{
     ble::{
@@ -22,9 +28,11 @@ use crate::{
             server::BleHidWriter,
         },
     },
+    input_device::pointing::set_resolution_multiplier,
     keyboard::{Keyboard, KeyboardReportMessage},
-    light::led_service_task,
+    light::{led_service_task, update_indicator_state},
     storage::{get_bond_info_key, Storage, StorageData},
+    usb::descriptor::{MOUSE_REPORT_SIZE, NUM_CONSUMER_KEYS, NUM_SYSTEM_KEYS},
     vial_task, KeyAction, KeyMap, LightService, RmkConfig, VialService, CONNECTION_TYPE,
 };
 use crate::{CONNECTION_STATE, KEYBOARD_STATE};
@@ -251,8 +259,21 @@ pub async fn initialize_nrf_ble_keyboard_and_run<
 
     // Flash and keymap configuration
     let flash = Flash::take(sd);
-    let mut storage = Storage::new(flash, default_keymap, keyboard_config.storage_config).await;
-    let keymap = RefCell::new(KeyMap::new_from_storage(default_keymap, Some(&mut storage)).await);
+    let mut storage = Storage::new(
+        flash,
+        default_keymap,
+        keyboard_config.default_macros,
+        keyboard_config.storage_config,
+    )
+    .await;
+    let keymap = RefCell::new(
+        KeyMap::new_from_storage(
+            default_keymap,
+            Some(&mut storage),
+            keyboard_config.default_macros,
+        )
+        .await,
+    );
 
     let mut buf: [u8; 128] = [0; 128];
 
@@ -300,6 +321,10 @@ pub async fn initialize_nrf_ble_keyboard_and_run<
     let ble_server =
         BleServer::new(sd, keyboard_config.usb_config, bonder).expect("Failed to start ble server");
 
+    // The bootloader is used by both the keyboard and Via
+    set_bootloader(keyboard_config.bootloader);
+    set_raw_hid_handler(keyboard_config.raw_hid_handler);
+
     let keyboard_report_sender = KEYBOARD_REPORT_CHANNEL.sender();
     let keyboard_report_receiver = KEYBOARD_REPORT_CHANNEL.receiver();
 
@@ -308,11 +333,17 @@ pub async fn initialize_nrf_ble_keyboard_and_run<
         &keymap,
         &keyboard_report_sender,
         keyboard_config.behavior_config,
+        keyboard_config.hooks,
+        keyboard_config.processor,
     );
     #[cfg(not(feature = "_no_usb"))]
     let mut usb_device = KeyboardUsbDevice::new(usb_driver, keyboard_config.usb_config);
     let mut vial_service = VialService::new(&keymap, keyboard_config.vial_config);
     let mut light_service = LightService::from_config(keyboard_config.light_config);
+    #[cfg(all(feature = "midi", not(feature = "_no_usb")))]
+    let mut sequencer = Sequencer::new(keyboard_config.sequencer_config);
+    #[cfg(all(feature = "steno", not(feature = "_no_usb")))]
+    let mut steno = Steno::new(keyboard_config.steno_config);
 
     // Main loop
     loop {
@@ -344,6 +375,10 @@ pub async fn initialize_nrf_ble_keyboard_and_run<
                     &mut storage,
                     &mut light_service,
                     &mut vial_service,
+                    #[cfg(feature = "midi")]
+                    &mut sequencer,
+                    #[cfg(feature = "steno")]
+                    &mut steno,
                     &keyboard_report_receiver,
                 );
                 if CONNECTION_TYPE.load(Ordering::Relaxed) == 0 {
@@ -402,6 +437,7 @@ pub async fn initialize_nrf_ble_keyboard_and_run<
                                 Either3::Third(_) => info!("Switch profile"),
                             }
                             bonder.save_sys_attrs(&conn);
+                            update_indicator_state(|s| s.ble_connected = false);
                         }
                         _ => {
                             // Wait 10ms
@@ -461,6 +497,7 @@ pub async fn initialize_nrf_ble_keyboard_and_run<
                             Either3::Third(_) => info!("Switch profile"),
                         }
                         bonder.save_sys_attrs(&conn);
+                        update_indicator_state(|s| s.ble_connected = false);
                     }
                     _ => {
                         // Wait 10ms for usb resuming/switching profile/advertising error
@@ -496,6 +533,7 @@ pub async fn initialize_nrf_ble_keyboard_and_run<
                 )
                 .await;
                 bonder.save_sys_attrs(&conn);
+                update_indicator_state(|s| s.ble_connected = false);
             }
             Err(e) => error!("Advertise error: {}", e),
         }
@@ -624,13 +662,25 @@ pub(crate) async fn run_ble_keyboard<
     >,
 ) {
     CONNECTION_STATE.store(false, Ordering::Release);
+    update_indicator_state(|s| {
+        s.ble_connected = true;
+        s.ble_profile = ACTIVE_PROFILE.load(Ordering::Acquire);
+    });
+    // The host enables high-resolution scrolling and touchpad mode again after connected
+    set_resolution_multiplier(0);
+    #[cfg(feature = "touchpad")]
+    crate::input_device::touchpad::reset_touchpad_mode();
     info!("Starting GATT server 20 ms later");
     Timer::after_millis(20).await;
     let mut ble_keyboard_writer = BleHidWriter::<'_, 8>::new(&conn, ble_server.hid.input_keyboard);
-    let mut ble_media_writer = BleHidWriter::<'_, 2>::new(&conn, ble_server.hid.input_media_keys);
+    let mut ble_media_writer =
+        BleHidWriter::<'_, { 2 * NUM_CONSUMER_KEYS }>::new(&conn, ble_server.hid.input_media_keys);
     let mut ble_system_control_writer =
-        BleHidWriter::<'_, 1>::new(&conn, ble_server.hid.input_system_keys);
-    let mut ble_mouse_writer = BleHidWriter::<'_, 5>::new(&conn, ble_server.hid.input_mouse_keys);
+        BleHidWriter::<'_, NUM_SYSTEM_KEYS>::new(&conn, ble_server.hid.input_system_keys);
+    let mut ble_mouse_writer =
+        BleHidWriter::<'_, MOUSE_REPORT_SIZE>::new(&conn, ble_server.hid.input_mouse_keys);
+    #[cfg(feature = "joystick")]
+    let mut ble_joystick_writer = BleHidWriter::<'_, 16>::new(&conn, ble_server.hid.input_joystick);
     let mut bas = ble_server.bas;
     let mut vial_rw = VialReaderWriter::new(ble_server.vial, &conn);
     let vial_task = vial_task(&mut vial_rw, vial_service);
@@ -648,6 +698,8 @@ pub(crate) async fn run_ble_keyboard<
         &mut ble_media_writer,
         &mut ble_system_control_writer,
         &mut ble_mouse_writer,
+        #[cfg(feature = "joystick")]
+        &mut ble_joystick_writer,
     );
     let storage_fut = storage.run();
     let set_conn_param = set_conn_params(&conn);
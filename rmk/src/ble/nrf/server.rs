@@ -156,11 +156,12 @@ impl gatt_server::Server for BleServer {
                 HidServiceEvent::InputKeyboardCccdWrite
                 | HidServiceEvent::InputMediaKeyCccdWrite
                 | HidServiceEvent::InputMouseKeyCccdWrite
-                | HidServiceEvent::InputSystemKeyCccdWrite => {
+                | HidServiceEvent::InputSystemKeyCccdWrite
+                | HidServiceEvent::InputJoystickCccdWrite => {
                     info!("{:?}, handle: {}, data: {:?}", event, handle, data);
                     self.bonder.save_sys_attrs(conn)
                 }
-                HidServiceEvent::OutputKeyboard => (),
+                HidServiceEvent::OutputKeyboard | HidServiceEvent::FeatureMouse => (),
             }
         }
         if let Some(event) = self.bas.on_write(handle, data) {
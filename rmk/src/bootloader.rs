@@ -0,0 +1,216 @@
+//! Jump to the bootloader
+//!
+//! Implement [`BootloaderJump`] for your chip, or use one of the built-in implementations,
+//! then pass it to RMK by [`crate::config::RmkConfig::bootloader`].
+//! The jump is triggered by the `Bootloader` keycode or Via's `BootloaderJump` command.
+//!
+//! ```rust
+//! static BOOTLOADER: StaticCell<Rp2040Bootloader> = StaticCell::new();
+//! let keyboard_config = RmkConfig {
+//!     bootloader: Some(BOOTLOADER.init(Rp2040Bootloader)),
+//!     ..Default::default()
+//! };
+//! ```
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+
+/// Jump to the bootloader of the chip
+pub trait BootloaderJump: Send {
+    /// Enter the bootloader, this function should not return.
+    ///
+    /// If it returns, the jump is failed and the keyboard keeps running.
+    fn jump(&mut self);
+}
+
+/// The bootloader set by user, which is used by both the keyboard and Via
+static BOOTLOADER: Mutex<
+    CriticalSectionRawMutex,
+    RefCell<Option<&'static mut dyn BootloaderJump>>,
+> = Mutex::new(RefCell::new(None));
+
+/// Set the bootloader used by `jump_to_bootloader`
+pub(crate) fn set_bootloader(bootloader: Option<&'static mut dyn BootloaderJump>) {
+    BOOTLOADER.lock(|b| *b.borrow_mut() = bootloader);
+}
+
+/// Jump to the bootloader, returns only if the bootloader is not set or the jump is failed
+pub(crate) fn jump_to_bootloader() {
+    BOOTLOADER.lock(|b| match b.borrow_mut().as_mut() {
+        Some(bootloader) => {
+            warn!("Jumping to bootloader!");
+            bootloader.jump();
+            error!("Jump to bootloader failed");
+        }
+        None => warn!("Bootloader jump is not available, set `RmkConfig::bootloader` first"),
+    });
+}
+
+/// Reboot RP2040 to the USB bootloader(BOOTSEL mode) by the ROM function `reset_usb_boot`
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub struct Rp2040Bootloader;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+impl BootloaderJump for Rp2040Bootloader {
+    fn jump(&mut self) {
+        // The ROM stores pointers in 16 bits, see section 2.8.3 of the RP2040 datasheet
+        const FUNC_TABLE: *const u16 = 0x0000_0014 as _;
+        const ROM_TABLE_LOOKUP: *const u16 = 0x0000_0018 as _;
+        unsafe {
+            let lookup: unsafe extern "C" fn(*const u16, u32) -> usize =
+                core::mem::transmute(*ROM_TABLE_LOOKUP as usize);
+            let reset_usb_boot = lookup(
+                *FUNC_TABLE as usize as *const u16,
+                u16::from_le_bytes(*b"UB") as u32,
+            );
+            if reset_usb_boot == 0 {
+                return;
+            }
+            let reset_usb_boot: unsafe extern "C" fn(u32, u32) -> ! =
+                core::mem::transmute(reset_usb_boot);
+            reset_usb_boot(0, 0);
+        }
+    }
+}
+
+/// Reboot RP2350 to the USB bootloader(BOOTSEL mode) by the ROM function `reboot`
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub struct Rp2350Bootloader;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+impl BootloaderJump for Rp2350Bootloader {
+    fn jump(&mut self) {
+        // See section 5.4 of the RP2350 datasheet
+        const ROM_TABLE_LOOKUP: *const u16 = 0x0000_0016 as _;
+        const RT_FLAG_FUNC_ARM_SEC: u32 = 0x0004;
+        const REBOOT_TYPE_BOOTSEL: u32 = 0x0002;
+        unsafe {
+            let lookup: unsafe extern "C" fn(u32, u32) -> usize =
+                core::mem::transmute(*ROM_TABLE_LOOKUP as usize);
+            let reboot = lookup(u16::from_le_bytes(*b"RB") as u32, RT_FLAG_FUNC_ARM_SEC);
+            if reboot == 0 {
+                return;
+            }
+            let reboot: unsafe extern "C" fn(u32, u32, u32, u32) -> i32 =
+                core::mem::transmute(reboot);
+            if reboot(REBOOT_TYPE_BOOTSEL, 10, 0, 0) != 0 {
+                return;
+            }
+        }
+        // The chip reboots after 10ms
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+}
+
+/// Magic number which requests the STM32 system bootloader after reset
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+const STM32_BOOTLOADER_MAGIC: u32 = 0xB00710AD;
+
+/// The flag in RAM which survives the reset
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+#[link_section = ".uninit.rmk_bootloader"]
+static mut STM32_BOOTLOADER_FLAG: core::mem::MaybeUninit<u32> = core::mem::MaybeUninit::uninit();
+
+/// Reboot STM32 to the system DFU bootloader.
+///
+/// The system bootloader can't be entered safely when the peripherals are initialized,
+/// so a magic flag is saved in RAM and the chip is reset.
+/// [`Stm32Bootloader::check`] MUST be called at the very beginning of `main`, before the chip is initialized:
+///
+/// ```rust
+/// // The address of the system memory, check the AN2606 for your chip
+/// const BOOTLOADER: Stm32Bootloader = Stm32Bootloader::new(0x1FFF_0000);
+///
+/// #[embassy_executor::main]
+/// async fn main(_spawner: Spawner) {
+///     unsafe { BOOTLOADER.check() };
+///     let p = embassy_stm32::init(config);
+///     // ...
+/// }
+/// ```
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub struct Stm32Bootloader {
+    system_memory: u32,
+}
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+impl Stm32Bootloader {
+    pub const fn new(system_memory: u32) -> Self {
+        Self { system_memory }
+    }
+
+    /// Enter the system bootloader if it's requested before the reset.
+    ///
+    /// # Safety
+    ///
+    /// This function must be called before any peripheral is initialized.
+    pub unsafe fn check(&self) {
+        let flag = core::ptr::addr_of_mut!(STM32_BOOTLOADER_FLAG) as *mut u32;
+        if core::ptr::read_volatile(flag) == STM32_BOOTLOADER_MAGIC {
+            core::ptr::write_volatile(flag, 0);
+            cortex_m::asm::bootload(self.system_memory as *const u32);
+        }
+    }
+}
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+impl BootloaderJump for Stm32Bootloader {
+    fn jump(&mut self) {
+        unsafe {
+            let flag = core::ptr::addr_of_mut!(STM32_BOOTLOADER_FLAG) as *mut u32;
+            core::ptr::write_volatile(flag, STM32_BOOTLOADER_MAGIC);
+        }
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+}
+
+/// Reboot nRF52 to the Adafruit UF2 bootloader, by writing the magic value to `GPREGRET`
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+pub struct Nrf52Bootloader;
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+impl BootloaderJump for Nrf52Bootloader {
+    fn jump(&mut self) {
+        const DFU_MAGIC_UF2_RESET: u32 = 0x57;
+        // When the softdevice is enabled, `POWER` registers can be accessed only by the softdevice API
+        #[cfg(feature = "_nrf_ble")]
+        unsafe {
+            nrf_softdevice::raw::sd_power_gpregret_clr(0, 0xFFFF_FFFF);
+            nrf_softdevice::raw::sd_power_gpregret_set(0, DFU_MAGIC_UF2_RESET);
+        }
+        #[cfg(not(feature = "_nrf_ble"))]
+        unsafe {
+            const GPREGRET: *mut u32 = 0x4000_051C as _;
+            core::ptr::write_volatile(GPREGRET, DFU_MAGIC_UF2_RESET);
+        }
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+}
+
+/// Reboot ESP32 to the ROM download mode.
+///
+/// It's not set by default, the register definitions are from `soc/rtc_cntl_reg.h`(ESP32-S3/C3)
+/// and `soc/lp_aon_reg.h`(ESP32-C6) of ESP-IDF, check them against your chip before using it.
+#[cfg(feature = "_esp_ble")]
+pub struct Esp32Bootloader;
+
+#[cfg(feature = "_esp_ble")]
+impl BootloaderJump for Esp32Bootloader {
+    fn jump(&mut self) {
+        // `RTC_CNTL_OPTION1_REG`, bit 0 is `RTC_CNTL_FORCE_DOWNLOAD_BOOT`
+        #[cfg(feature = "esp32s3_ble")]
+        let (reg, bit): (*mut u32, u32) = (0x6000_812C as _, 1 << 0);
+        #[cfg(feature = "esp32c3_ble")]
+        let (reg, bit): (*mut u32, u32) = (0x6000_80F4 as _, 1 << 0);
+        // `LP_AON_SYS_CFG_REG`, bit 30 is `LP_AON_FORCE_DOWNLOAD_BOOT`
+        #[cfg(feature = "esp32c6_ble")]
+        let (reg, bit): (*mut u32, u32) = (0x600B_1008 as _, 1 << 30);
+        unsafe {
+            core::ptr::write_volatile(reg, core::ptr::read_volatile(reg) | bit);
+        }
+        esp_idf_svc::hal::reset::restart();
+    }
+}
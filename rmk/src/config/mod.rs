@@ -10,6 +10,16 @@ pub use nrf_config::BleBatteryConfig;
 
 use embassy_time::Duration;
 use embedded_hal::digital::OutputPin;
+use heapless::Vec;
+
+use crate::audio::{songs, Song};
+use crate::bootloader::BootloaderJump;
+use crate::hooks::KeyboardHooks;
+use crate::host_layout::HostLayout;
+use crate::input_device::InputProcessor;
+use crate::keyboard_macro::MacroSequence;
+use crate::raw_hid::RawHidHandler;
+use crate::rgb::{color::Hsv, matrix::RgbMatrixEffect};
 
 /// Internal configurations for RMK keyboard.
 pub struct RmkConfig<'a, O: OutputPin> {
@@ -19,10 +29,24 @@ pub struct RmkConfig<'a, O: OutputPin> {
     pub light_config: LightConfig<O>,
     pub storage_config: StorageConfig,
     pub behavior_config: BehaviorConfig,
+    #[cfg(feature = "midi")]
+    pub sequencer_config: SequencerConfig,
+    #[cfg(feature = "steno")]
+    pub steno_config: StenoConfig,
     #[cfg(feature = "_nrf_ble")]
     pub ble_battery_config: BleBatteryConfig<'a>,
     #[cfg(feature = "_esp_ble")]
     pub ble_battery_config: BleBatteryConfig,
+    /// User hooks of the keyboard, check [`KeyboardHooks`] for details
+    pub hooks: Option<&'a mut dyn KeyboardHooks>,
+    /// Input processors which process events before the keyboard, check [`InputProcessor`] for details
+    pub processor: Option<&'a mut dyn InputProcessor>,
+    /// Jump to the bootloader by the `Bootloader` keycode or Via, check [`BootloaderJump`] for details
+    pub bootloader: Option<&'a mut dyn BootloaderJump>,
+    /// Handler of user commands of the raw HID interface, check [`RawHidHandler`] for details
+    pub raw_hid_handler: Option<&'a mut dyn RawHidHandler>,
+    /// Default macros, which are saved to the storage when it's initialized, check [`MacroSequence`] for details
+    pub default_macros: Option<&'a MacroSequence>,
 }
 
 impl<'a, O: OutputPin> Default for RmkConfig<'a, O> {
@@ -34,18 +58,48 @@ impl<'a, O: OutputPin> Default for RmkConfig<'a, O> {
             light_config: LightConfig::default(),
             storage_config: StorageConfig::default(),
             behavior_config: BehaviorConfig::default(),
+            #[cfg(feature = "midi")]
+            sequencer_config: SequencerConfig::default(),
+            #[cfg(feature = "steno")]
+            steno_config: StenoConfig::default(),
             #[cfg(any(feature = "_nrf_ble", feature = "_esp_ble"))]
             ble_battery_config: BleBatteryConfig::default(),
+            hooks: None,
+            processor: None,
+            bootloader: None,
+            raw_hid_handler: None,
+            default_macros: None,
         }
     }
 }
 
 /// Config for configurable action behavior
-#[derive(Default)]
 pub struct BehaviorConfig {
     pub tri_layer: Option<[u8; 3]>,
     pub tap_hold: TapHoldConfig,
     pub one_shot: OneShotConfig,
+    /// How long the `Bootloader` key should be held before the jump, 0 means jumping immediately when pressed.
+    /// The default is 2s
+    pub bootloader_hold_time: Duration,
+    /// How long the `Reboot` and `ClearStorage` keys should be held before triggered, 0 means triggering immediately when pressed.
+    /// The default is 2s
+    pub reset_hold_time: Duration,
+    /// Default keyboard layout of the host, used to type text in macros.
+    /// It's overridden by the layout selected by `HostLayoutXxx` keycodes, which is saved in storage
+    pub host_layout: HostLayout,
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            tri_layer: None,
+            tap_hold: TapHoldConfig::default(),
+            one_shot: OneShotConfig::default(),
+            bootloader_hold_time: Duration::from_secs(2),
+            reset_hold_time: Duration::from_secs(2),
+            host_layout: HostLayout::default(),
+        }
+    }
 }
 
 /// Configurations for tap hold behavior
@@ -101,12 +155,105 @@ impl Default for StorageConfig {
     }
 }
 
-/// Config for lights
+/// Configurations for audio
+#[derive(Clone, Copy, Debug)]
+pub struct AudioConfig {
+    /// Song played when the keyboard starts
+    pub startup_song: Song,
+    /// Song played when a layer is toggled or the default layer is changed
+    pub layer_change_song: Song,
+    pub caps_lock_on_song: Song,
+    pub caps_lock_off_song: Song,
+    /// Whether clicky mode is enabled when there's no saved audio settings
+    pub clicky_enable: bool,
+    /// Default frequency of key clicks in Hz
+    pub clicky_freq: u16,
+    /// Duration of key clicks in milliseconds
+    pub clicky_duration: u16,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            startup_song: songs::STARTUP,
+            layer_change_song: songs::LAYER_CHANGE,
+            caps_lock_on_song: songs::CAPS_LOCK_ON,
+            caps_lock_off_song: songs::CAPS_LOCK_OFF,
+            clicky_enable: false,
+            clicky_freq: 440,
+            clicky_duration: 15,
+        }
+    }
+}
+
+/// Configurations for backlight
+#[derive(Clone, Copy, Debug)]
+pub struct BacklightConfig {
+    /// Number of brightness levels, 1 ~ 31
+    pub levels: u8,
+    /// Whether the backlight is on when there's no saved backlight settings
+    pub enable: bool,
+    /// Whether breathing is enabled when there's no saved backlight settings
+    pub breathing: bool,
+    /// Duration of a breathing period in seconds
+    pub breathing_period: u8,
+}
+
+impl Default for BacklightConfig {
+    fn default() -> Self {
+        Self {
+            levels: 3,
+            enable: true,
+            breathing: false,
+            breathing_period: 6,
+        }
+    }
+}
+
+/// Configurations for haptic feedback
+///
+/// These are the default haptic settings, which are used when there's no saved haptic settings
 #[derive(Clone, Copy, Debug)]
+pub struct HapticConfig {
+    /// Whether haptic feedback is enabled
+    pub enable: bool,
+    /// Play haptic feedback when a key is pressed
+    pub feedback_on_key_press: bool,
+    /// Play haptic feedback when a layer is toggled or the default layer is changed
+    pub feedback_on_layer_change: bool,
+    /// Play haptic feedback when a combo is triggered, see [`crate::haptic::trigger_combo_feedback`]
+    pub feedback_on_combo: bool,
+    /// Id of the effect in the DRV2605L's effect library, 1 ~ 123
+    pub effect: u8,
+    /// Dwell time of the solenoid in milliseconds, 4 ~ 100
+    pub dwell: u8,
+}
+
+impl Default for HapticConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            feedback_on_key_press: true,
+            feedback_on_layer_change: true,
+            feedback_on_combo: true,
+            // Strong click 100%
+            effect: 1,
+            dwell: 12,
+        }
+    }
+}
+
+/// Max number of indicator LEDs in [`LightConfig::indicators`]
+pub const MAX_INDICATORS: usize = 8;
+
+/// Config for lights
+#[derive(Clone, Debug)]
 pub struct LightConfig<O: OutputPin> {
     pub capslock: Option<LightPinConfig<O>>,
     pub scrolllock: Option<LightPinConfig<O>>,
     pub numslock: Option<LightPinConfig<O>>,
+    /// Indicator LEDs bound to keyboard states
+    pub indicators: Vec<IndicatorConfig<O>, MAX_INDICATORS>,
 }
 
 #[derive(Clone, Copy, Default, Debug)]
@@ -121,10 +268,40 @@ impl<O: OutputPin> Default for LightConfig<O> {
             capslock: None,
             scrolllock: None,
             numslock: None,
+            indicators: Vec::new(),
         }
     }
 }
 
+/// An indicator LED, which is on when the condition is met
+#[derive(Clone, Copy, Debug)]
+pub struct IndicatorConfig<O: OutputPin> {
+    pub pin: LightPinConfig<O>,
+    pub condition: IndicatorCondition,
+}
+
+/// Keyboard states which an indicator LED can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum IndicatorCondition {
+    /// Capslock is on, reported by the host
+    CapsLock,
+    /// Numslock is on, reported by the host
+    NumsLock,
+    /// Scrolllock is on, reported by the host
+    ScrollLock,
+    /// The layer is the highest active layer
+    Layer(u8),
+    /// The keyboard is connected via USB
+    UsbActive,
+    /// The BLE profile is active and connected
+    BleProfileConnected(u8),
+    /// The battery level is low
+    BatteryLow,
+    /// A one-shot modifier is pending
+    OneShotModifier,
+}
+
 /// Config for [vial](https://get.vial.today/).
 ///
 /// You can generate automatically using [`build.rs`](https://github.com/HaoboGu/rmk/blob/main/examples/use_rust/stm32h7/build.rs).
@@ -167,14 +344,136 @@ impl Default for MouseConfig {
     }
 }
 
+/// Number of steps of the step sequencer
+#[cfg(feature = "midi")]
+pub const SEQUENCER_STEPS: usize = 16;
+/// Number of tracks of the step sequencer, each track plays one MIDI note
+#[cfg(feature = "midi")]
+pub const SEQUENCER_TRACKS: usize = 8;
+
+/// Note length of a sequencer step, `T` means triplet
+#[cfg(feature = "midi")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SequencerResolution {
+    Res2,
+    Res2T,
+    Res4,
+    Res4T,
+    Res8,
+    Res8T,
+    Res16,
+    Res16T,
+    Res32,
+}
+
+/// Configurations for the step sequencer
+#[cfg(feature = "midi")]
+#[derive(Clone, Copy, Debug)]
+pub struct SequencerConfig {
+    /// Tempo in BPM
+    pub tempo: u8,
+    /// Note length of each step
+    pub resolution: SequencerResolution,
+    /// MIDI note of each track
+    pub track_notes: [u8; SEQUENCER_TRACKS],
+    /// Active tracks of each step, bit `n` is track `n`
+    pub steps: [u8; SEQUENCER_STEPS],
+    /// Tracks which are affected by `SequencerStepsAll`/`SequencerStepsClear`/`SequencerStepN`, bit `n` is track `n`.
+    /// It can be changed by `SequencerTrackN`
+    pub active_tracks: u8,
+    /// MIDI channel, 0 ~ 15
+    pub channel: u8,
+    /// Note velocity, 0 ~ 127
+    pub velocity: u8,
+}
+
+#[cfg(feature = "midi")]
+impl Default for SequencerConfig {
+    fn default() -> Self {
+        Self {
+            tempo: 120,
+            resolution: SequencerResolution::Res16,
+            // C3 ~ C4 in C major
+            track_notes: [48, 50, 52, 53, 55, 57, 59, 60],
+            steps: [0; SEQUENCER_STEPS],
+            active_tracks: 0b1,
+            channel: 0,
+            velocity: 127,
+        }
+    }
+}
+
+/// Steno protocol used to send steno chords to the host
+#[cfg(feature = "steno")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StenoProtocol {
+    /// 6-byte GeminiPR packets via USB serial
+    #[default]
+    GeminiPR,
+    /// TX Bolt packets via USB serial
+    TxBolt,
+    /// Plover HID protocol, the state of all steno keys is sent via a vendor defined hid interface
+    PloverHid,
+}
+
+/// Configurations for steno
+#[cfg(feature = "steno")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StenoConfig {
+    /// Default steno protocol, `StenoBolt`/`StenoGemini` keycodes switch the protocol at runtime
+    pub protocol: StenoProtocol,
+}
+
 /// Configurations for RGB light
+///
+/// The number of LEDs is the const generic parameter `N` of [`crate::rgb::underglow::RgbLightService`], which replaces `rgb_led_num`
 #[derive(Clone, Copy, Debug)]
 pub struct RGBLightConfig {
+    /// Whether the RGB light is on when there's no saved RGB light settings
     pub enabled: bool,
-    pub rgb_led_num: u32,
-    pub rgb_hue_step: u32,
-    pub rgb_val_step: u32,
-    pub rgb_sat_step: u32,
+    pub rgb_hue_step: u8,
+    pub rgb_val_step: u8,
+    pub rgb_sat_step: u8,
+    /// Max brightness(value of HSV), which limits the current of LEDs
+    pub max_brightness: u8,
+}
+
+impl Default for RGBLightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rgb_hue_step: 8,
+            rgb_val_step: 17,
+            rgb_sat_step: 17,
+            max_brightness: 255,
+        }
+    }
+}
+
+/// Configurations for per-key RGB matrix
+#[derive(Clone, Copy, Debug)]
+pub struct RgbMatrixConfig<'a> {
+    /// Matrix position `(row, col)` of each LED, in the order of the LED strip
+    pub led_map: &'a [(u8, u8)],
+    /// Color of each layer, used by `SolidLayer` and `Reactive` effects
+    pub layer_colors: &'a [Hsv],
+    /// Effect used at startup
+    pub effect: RgbMatrixEffect,
+    /// Max brightness(value of HSV), which limits the current of LEDs
+    pub max_brightness: u8,
+}
+
+impl Default for RgbMatrixConfig<'_> {
+    fn default() -> Self {
+        Self {
+            led_map: &[],
+            layer_colors: &[Hsv::new(0, 0, 255)],
+            effect: RgbMatrixEffect::default(),
+            max_brightness: 255,
+        }
+    }
 }
 
 /// Configurations for usb
@@ -5,8 +5,8 @@ use crate::debounce::default_bouncer::DefaultDebouncer;
 use crate::debounce::fast_debouncer::RapidDebouncer;
 use crate::debounce::DebounceState;
 use crate::debounce::DebouncerTrait;
-use crate::event::KeyEvent;
-use crate::keyboard::KEY_EVENT_CHANNEL;
+use crate::event::{Event, KeyEvent};
+use crate::keyboard::EVENT_CHANNEL;
 use crate::matrix::KeyState;
 use crate::MatrixTrait;
 use crate::RmkConfig;
@@ -330,12 +330,12 @@ impl<
                                 self.key_states[row_idx][col_idx].toggle_pressed();
                                 let key_state = self.key_states[row_idx][col_idx];
 
-                                KEY_EVENT_CHANNEL
-                                    .send(KeyEvent {
+                                EVENT_CHANNEL
+                                    .send(Event::Key(KeyEvent {
                                         row: row_idx as u8,
                                         col: col_idx as u8,
                                         pressed: key_state.pressed,
-                                    })
+                                    }))
                                     .await;
                             }
                             _ => (),
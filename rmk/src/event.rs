@@ -19,6 +19,10 @@ pub enum Event {
     Touchpad(TouchpadEvent),
     /// Joystick, suppose we have x,y,z axes for this joystick
     Joystick([AxisEvent; 3]),
+    /// Pointing device, such as trackball. X/Y axes move the pointer, V/H axes scroll.
+    ///
+    /// Scrolling values are in high-resolution units, 120 units are one detent.
+    Pointing([AxisEvent; 2]),
     /// An AxisEvent in a stream of events. The receiver should keep receiving events until it receives [`Eos`] event.
     AxisEventStream(AxisEvent),
     /// End of the event sequence
@@ -76,6 +80,9 @@ pub enum Axis {
     Z,
     H,
     V,
+    Rx,
+    Ry,
+    Rz,
     // .. More is allowed
 }
 
@@ -0,0 +1,311 @@
+//! Haptic feedback support
+//!
+//! The [`HapticService`] plays haptic feedback via a [`HapticDriver`] when a key is pressed or the layer changes.
+//! Two drivers are provided: a [`Solenoid`] driven by a GPIO, and a DRV2605L haptic motor driver connected via I2C,
+//! which requires `drv2605l` feature.
+//! Haptic settings are saved to the storage and restored at startup, they can be changed by haptic keycodes or Via.
+//!
+//! The haptic service should be run concurrently with RMK:
+//! ```rust
+//! let mut haptic = HapticService::new(Solenoid::new(pin), HapticConfig::default());
+//! embassy_futures::join::join(haptic.run(), run_rmk(/* .. arguments */)).await;
+//! ```
+
+use core::cell::Cell;
+use core::future::Future;
+
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    channel::Channel,
+};
+use embassy_time::Timer;
+use embedded_hal::digital::OutputPin;
+#[cfg(feature = "drv2605l")]
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    config::HapticConfig,
+    keycode::KeyCode,
+    storage::{eeconfig::EeHapticConfig, FlashOperationMessage, FLASH_CHANNEL},
+};
+
+pub(crate) static HAPTIC_CHANNEL: Channel<CriticalSectionRawMutex, HapticEvent, 8> = Channel::new();
+
+/// Current haptic settings, which are read by Via
+static HAPTIC_SETTINGS: Mutex<CriticalSectionRawMutex, Cell<EeHapticConfig>> =
+    Mutex::new(Cell::new(EeHapticConfig::new()));
+
+/// Range of effect ids in the DRV2605L's effect library
+pub(crate) const HAPTIC_EFFECT_MIN: u8 = 1;
+pub(crate) const HAPTIC_EFFECT_MAX: u8 = 123;
+
+/// Range of the solenoid dwell time in milliseconds, same as QMK
+pub(crate) const HAPTIC_DWELL_MIN: u8 = 4;
+pub(crate) const HAPTIC_DWELL_MAX: u8 = 100;
+
+/// Driver of a haptic feedback device
+pub trait HapticDriver {
+    /// Initialize the device, it's called once when the haptic service starts
+    fn init(&mut self) -> impl Future<Output = ()> {
+        async {}
+    }
+
+    /// Play a haptic feedback.
+    ///
+    /// `effect` is the id of the waveform in the DRV2605L's effect library, `dwell` is the pulse length of a solenoid in milliseconds.
+    /// A driver uses the one which is meaningful for the device.
+    fn play(&mut self, effect: u8, dwell: u8) -> impl Future<Output = ()>;
+}
+
+/// Solenoid driven by a GPIO, which is energized for the dwell time
+pub struct Solenoid<P: OutputPin> {
+    pin: P,
+}
+
+impl<P: OutputPin> Solenoid<P> {
+    pub fn new(pin: P) -> Self {
+        Self { pin }
+    }
+}
+
+impl<P: OutputPin> HapticDriver for Solenoid<P> {
+    async fn init(&mut self) {
+        let _ = self.pin.set_low();
+    }
+
+    async fn play(&mut self, _effect: u8, dwell: u8) {
+        let _ = self.pin.set_high();
+        Timer::after_millis(dwell as u64).await;
+        let _ = self.pin.set_low();
+    }
+}
+
+/// Type of the motor which is driven by the DRV2605L
+#[cfg(feature = "drv2605l")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Drv2605lMotor {
+    /// Eccentric rotating mass motor
+    Erm,
+    /// Linear resonant actuator
+    Lra,
+}
+
+/// DRV2605L haptic motor driver, which plays effects from its ROM library
+#[cfg(feature = "drv2605l")]
+pub struct Drv2605l<I: I2c> {
+    i2c: I,
+    motor: Drv2605lMotor,
+}
+
+#[cfg(feature = "drv2605l")]
+impl<I: I2c> Drv2605l<I> {
+    const ADDRESS: u8 = 0x5A;
+    const REG_MODE: u8 = 0x01;
+    const REG_LIBRARY: u8 = 0x03;
+    const REG_WAVEFORM_SEQ: u8 = 0x04;
+    const REG_GO: u8 = 0x0C;
+    const REG_FEEDBACK: u8 = 0x1A;
+
+    pub fn new(i2c: I, motor: Drv2605lMotor) -> Self {
+        Self { i2c, motor }
+    }
+
+    async fn write_reg(&mut self, reg: u8, value: u8) -> Result<(), I::Error> {
+        self.i2c.write(Self::ADDRESS, &[reg, value]).await
+    }
+
+    async fn read_reg(&mut self, reg: u8) -> Result<u8, I::Error> {
+        let mut buf = [0u8; 1];
+        self.i2c
+            .write_read(Self::ADDRESS, &[reg], &mut buf)
+            .await
+            .map(|_| buf[0])
+    }
+
+    async fn setup(&mut self) -> Result<(), I::Error> {
+        // Exit standby, use internal trigger mode
+        self.write_reg(Self::REG_MODE, 0x00).await?;
+        // Bit 7 of the feedback control register selects ERM(0) or LRA(1)
+        let feedback = self.read_reg(Self::REG_FEEDBACK).await?;
+        let (feedback, library) = match self.motor {
+            Drv2605lMotor::Erm => (feedback & 0x7F, 1),
+            Drv2605lMotor::Lra => (feedback | 0x80, 6),
+        };
+        self.write_reg(Self::REG_FEEDBACK, feedback).await?;
+        self.write_reg(Self::REG_LIBRARY, library).await
+    }
+
+    async fn go(&mut self, effect: u8) -> Result<(), I::Error> {
+        // Play the effect, then stop at the next sequence slot
+        self.i2c
+            .write(Self::ADDRESS, &[Self::REG_WAVEFORM_SEQ, effect, 0])
+            .await?;
+        self.write_reg(Self::REG_GO, 1).await
+    }
+}
+
+#[cfg(feature = "drv2605l")]
+impl<I: I2c> HapticDriver for Drv2605l<I> {
+    async fn init(&mut self) {
+        if self.setup().await.is_err() {
+            error!("Initialize DRV2605L error");
+        }
+    }
+
+    async fn play(&mut self, effect: u8, _dwell: u8) {
+        if self.go(effect).await.is_err() {
+            error!("Play DRV2605L effect error");
+        }
+    }
+}
+
+/// Events which trigger the haptic service
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum HapticEvent {
+    /// A key is pressed
+    KeyPress,
+    /// The layer is changed
+    LayerChange,
+    /// A combo is triggered
+    Combo,
+    /// A haptic keycode is pressed
+    Key(KeyCode),
+    /// Haptic config read from the storage
+    LoadConfig(EeHapticConfig),
+}
+
+/// Send an event to the haptic service.
+///
+/// The event is dropped if the haptic service isn't running or is busy, so that the keyboard is never blocked.
+pub(crate) fn send_haptic_event(event: HapticEvent) {
+    let _ = HAPTIC_CHANNEL.try_send(event);
+}
+
+/// Play the haptic feedback of a combo.
+///
+/// RMK doesn't have a combo engine yet, call it when a combo implemented in [`crate::hooks::KeyboardHooks`] is triggered.
+/// The feedback is played only if `feedback_on_combo` is enabled.
+pub fn trigger_combo_feedback() {
+    send_haptic_event(HapticEvent::Combo);
+}
+
+/// Get current haptic settings
+pub(crate) fn get_haptic_settings() -> EeHapticConfig {
+    HAPTIC_SETTINGS.lock(|s| s.get())
+}
+
+/// Update current haptic settings and save them to the storage if they're changed
+pub(crate) fn update_haptic_settings(settings: EeHapticConfig) {
+    if HAPTIC_SETTINGS.lock(|s| s.replace(settings)) == settings {
+        return;
+    }
+    // Don't block if the storage is busy
+    if FLASH_CHANNEL
+        .try_send(FlashOperationMessage::HapticConfig(settings))
+        .is_err()
+    {
+        warn!("Flash channel is full, haptic config isn't saved");
+    }
+}
+
+/// Haptic feedback service
+pub struct HapticService<D: HapticDriver> {
+    driver: D,
+    config: HapticConfig,
+}
+
+impl<D: HapticDriver> HapticService<D> {
+    pub fn new(driver: D, config: HapticConfig) -> Self {
+        HAPTIC_SETTINGS.lock(|s| s.set(default_settings(&config)));
+        Self { driver, config }
+    }
+
+    /// Run the haptic service
+    pub async fn run(&mut self) {
+        self.driver.init().await;
+        loop {
+            let event = HAPTIC_CHANNEL.receive().await;
+            self.process_event(event).await;
+        }
+    }
+
+    async fn process_event(&mut self, event: HapticEvent) {
+        let settings = get_haptic_settings();
+        match event {
+            HapticEvent::KeyPress => {
+                if settings.key_press() {
+                    self.play(settings).await;
+                }
+            }
+            HapticEvent::LayerChange => {
+                if settings.layer_change() {
+                    self.play(settings).await;
+                }
+            }
+            HapticEvent::Combo => {
+                if settings.combo() {
+                    self.play(settings).await;
+                }
+            }
+            HapticEvent::Key(key) => self.process_key(key, settings),
+            HapticEvent::LoadConfig(settings) => {
+                debug!("Load haptic config: {:?}", settings);
+                HAPTIC_SETTINGS.lock(|s| s.set(settings));
+            }
+        }
+    }
+
+    fn process_key(&mut self, key: KeyCode, settings: EeHapticConfig) {
+        let new_settings = match key {
+            KeyCode::HapticOn => settings.with_enable(true),
+            KeyCode::HapticOff => settings.with_enable(false),
+            KeyCode::HapticToggle => settings.with_enable(!settings.enable()),
+            KeyCode::HapticReset => default_settings(&self.config),
+            KeyCode::HapticFeedbackToggle => settings.with_key_press(!settings.key_press()),
+            KeyCode::HapticModeNext => {
+                settings.with_effect(if settings.effect() >= HAPTIC_EFFECT_MAX {
+                    HAPTIC_EFFECT_MIN
+                } else {
+                    settings.effect() + 1
+                })
+            }
+            KeyCode::HapticModePrevious => {
+                settings.with_effect(if settings.effect() <= HAPTIC_EFFECT_MIN {
+                    HAPTIC_EFFECT_MAX
+                } else {
+                    settings.effect() - 1
+                })
+            }
+            KeyCode::HapticDwellUp => {
+                settings.with_dwell((settings.dwell() + 1).min(HAPTIC_DWELL_MAX))
+            }
+            KeyCode::HapticDwellDown => {
+                settings.with_dwell(settings.dwell().saturating_sub(1).max(HAPTIC_DWELL_MIN))
+            }
+            _ => {
+                warn!("Unsupported haptic key: {:?}", key);
+                return;
+            }
+        };
+        update_haptic_settings(new_settings);
+    }
+
+    async fn play(&mut self, settings: EeHapticConfig) {
+        if settings.enable() {
+            self.driver.play(settings.effect(), settings.dwell()).await;
+        }
+    }
+}
+
+/// Haptic settings which are used when there's no saved haptic settings
+fn default_settings(config: &HapticConfig) -> EeHapticConfig {
+    EeHapticConfig::new()
+        .with_enable(config.enable)
+        .with_key_press(config.feedback_on_key_press)
+        .with_layer_change(config.feedback_on_layer_change)
+        .with_combo(config.feedback_on_combo)
+        .with_effect(config.effect.clamp(HAPTIC_EFFECT_MIN, HAPTIC_EFFECT_MAX))
+        .with_dwell(config.dwell.clamp(HAPTIC_DWELL_MIN, HAPTIC_DWELL_MAX))
+}
@@ -0,0 +1,56 @@
+//! User hooks of the keyboard
+//!
+//! Implement [`KeyboardHooks`] to add custom keycodes, or to intercept key events and reports without forking RMK.
+//! All callbacks have a default implementation, so you only need to implement the ones you need.
+//!
+//! Hooks are passed to RMK by [`crate::config::RmkConfig::hooks`]:
+//! ```rust
+//! struct MyHooks;
+//!
+//! impl KeyboardHooks for MyHooks {
+//!     fn process_custom_keycode(&mut self, keycode: KeyCode, event: KeyEvent) -> bool {
+//!         if keycode == KeyCode::Kb0 && event.pressed {
+//!             info!("Kb0 is pressed");
+//!             return true;
+//!         }
+//!         false
+//!     }
+//! }
+//!
+//! static HOOKS: StaticCell<MyHooks> = StaticCell::new();
+//! let keyboard_config = RmkConfig {
+//!     hooks: Some(HOOKS.init(MyHooks)),
+//!     ..Default::default()
+//! };
+//! ```
+//!
+//! The callbacks are called in the keyboard task, so they should return quickly.
+
+use crate::{
+    action::KeyAction, event::KeyEvent, keyboard::KeyboardReportMessage, keycode::KeyCode,
+};
+
+/// Callbacks of the keyboard
+pub trait KeyboardHooks {
+    /// Called when a key event is received, before it's processed.
+    ///
+    /// `action` is the action at the event's position in the keymap, the returned action is processed instead.
+    /// Return [`KeyAction::No`] to drop the event.
+    fn on_key_event(&mut self, _event: KeyEvent, action: KeyAction) -> KeyAction {
+        action
+    }
+
+    /// Process a custom keycode, `Kb0`~`Kb31` or `User0`~`User31`.
+    ///
+    /// Return `true` if the keycode is handled, otherwise it's processed by RMK.
+    /// For example, `User0`~`User11` are used to switch BLE profiles on nRF52 if they're not handled.
+    fn process_custom_keycode(&mut self, _keycode: KeyCode, _event: KeyEvent) -> bool {
+        false
+    }
+
+    /// Called when the highest active layer is changed
+    fn on_layer_change(&mut self, _layer: u8) {}
+
+    /// Called before a report is sent to the host, the report can be modified
+    fn on_report(&mut self, _report: &mut KeyboardReportMessage) {}
+}
@@ -0,0 +1,528 @@
+//! Keyboard layouts of the host.
+//!
+//! The keyboard sends positions of keys rather than characters, the host translates them to characters with its own layout.
+//! To type text in macros, a character is converted to the key strokes which produce it on the host's layout.
+//! The tables follow the Windows variants of the layouts.
+
+use num_enum::FromPrimitive;
+
+use crate::keycode::{KeyCode, ModifierCombination};
+
+/// Keyboard layout used by the host, which decides the key strokes of characters in text macros
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum HostLayout {
+    /// US QWERTY
+    #[default]
+    Us,
+    /// UK QWERTY
+    Uk,
+    /// German QWERTZ
+    German,
+    /// French AZERTY
+    French,
+    /// US Dvorak
+    Dvorak,
+}
+
+impl HostLayout {
+    pub(crate) fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HostLayout::Us),
+            1 => Some(HostLayout::Uk),
+            2 => Some(HostLayout::German),
+            3 => Some(HostLayout::French),
+            4 => Some(HostLayout::Dvorak),
+            _ => None,
+        }
+    }
+
+    /// Get the host layout selected by a `HostLayoutXxx` keycode
+    pub(crate) fn from_keycode(key: KeyCode) -> Option<Self> {
+        match key {
+            KeyCode::HostLayoutUs => Some(HostLayout::Us),
+            KeyCode::HostLayoutUk => Some(HostLayout::Uk),
+            KeyCode::HostLayoutGerman => Some(HostLayout::German),
+            KeyCode::HostLayoutFrench => Some(HostLayout::French),
+            KeyCode::HostLayoutDvorak => Some(HostLayout::Dvorak),
+            _ => None,
+        }
+    }
+
+    /// Get the key strokes which type the character on the host.
+    ///
+    /// A dead key is always followed by a second stroke: a space for the accent itself, or the base letter of an accented character.
+    /// Returns `None` if the character can't be typed on the layout.
+    pub(crate) fn to_key_strokes(self, c: char) -> Option<(KeyStroke, Option<KeyStroke>)> {
+        if let Some(stroke) = self.key_stroke(c) {
+            if self.is_dead_key(c) {
+                return Some((stroke, Some(KeyStroke::key(KeyCode::Space))));
+            }
+            return Some((stroke, None));
+        }
+
+        // Compose the accented character with the dead key of the accent
+        let (accent, base) = decompose(c)?;
+        if !self.is_dead_key(accent) {
+            return None;
+        }
+        Some((self.key_stroke(accent)?, Some(self.key_stroke(base)?)))
+    }
+
+    /// Returns `true` if the character is typed by a dead key, which modifies the next character
+    fn is_dead_key(self, c: char) -> bool {
+        match self {
+            HostLayout::Us | HostLayout::Uk | HostLayout::Dvorak => false,
+            HostLayout::German => matches!(c, '^' | '´' | '`' | '~'),
+            HostLayout::French => matches!(c, '^' | '¨' | '`' | '~'),
+        }
+    }
+
+    /// Get the key stroke which types the character directly
+    fn key_stroke(self, c: char) -> Option<KeyStroke> {
+        if let Some(stroke) = common_key_stroke(c) {
+            return Some(stroke);
+        }
+        if c.is_ascii_alphabetic() {
+            let key = self.letter_key(c.to_ascii_lowercase());
+            return Some(if c.is_ascii_uppercase() {
+                KeyStroke::shift(key)
+            } else {
+                KeyStroke::key(key)
+            });
+        }
+        match self {
+            HostLayout::Us => us_key_stroke(c),
+            HostLayout::Uk => uk_key_stroke(c),
+            HostLayout::German => german_key_stroke(c),
+            HostLayout::French => french_key_stroke(c),
+            HostLayout::Dvorak => dvorak_key_stroke(c),
+        }
+    }
+
+    /// Get the key of a lowercase ascii letter
+    fn letter_key(self, c: char) -> KeyCode {
+        match (self, c) {
+            (HostLayout::German, 'y') => KeyCode::Z,
+            (HostLayout::German, 'z') => KeyCode::Y,
+            (HostLayout::French, 'a') => KeyCode::Q,
+            (HostLayout::French, 'q') => KeyCode::A,
+            (HostLayout::French, 'w') => KeyCode::Z,
+            (HostLayout::French, 'z') => KeyCode::W,
+            (HostLayout::French, 'm') => KeyCode::Semicolon,
+            (HostLayout::Dvorak, _) => match c {
+                'a' => KeyCode::A,
+                'b' => KeyCode::N,
+                'c' => KeyCode::I,
+                'd' => KeyCode::H,
+                'e' => KeyCode::D,
+                'f' => KeyCode::Y,
+                'g' => KeyCode::U,
+                'h' => KeyCode::J,
+                'i' => KeyCode::G,
+                'j' => KeyCode::C,
+                'k' => KeyCode::V,
+                'l' => KeyCode::P,
+                'm' => KeyCode::M,
+                'n' => KeyCode::L,
+                'o' => KeyCode::S,
+                'p' => KeyCode::R,
+                'q' => KeyCode::X,
+                'r' => KeyCode::O,
+                's' => KeyCode::Semicolon,
+                't' => KeyCode::K,
+                'u' => KeyCode::F,
+                'v' => KeyCode::Dot,
+                'w' => KeyCode::Comma,
+                'x' => KeyCode::B,
+                'y' => KeyCode::T,
+                _ => KeyCode::Slash,
+            },
+            _ => KeyCode::from_primitive(KeyCode::A as u16 + (c as u16 - 'a' as u16)),
+        }
+    }
+}
+
+/// A key stroke, which is a key pressed with modifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct KeyStroke {
+    pub(crate) key: KeyCode,
+    pub(crate) modifier: ModifierCombination,
+}
+
+impl KeyStroke {
+    const fn key(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifier: ModifierCombination::new(),
+        }
+    }
+
+    const fn shift(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifier: ModifierCombination::new_from(false, false, false, true, false),
+        }
+    }
+
+    /// AltGr is the right alt key
+    const fn altgr(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifier: ModifierCombination::new_from(true, false, true, false, false),
+        }
+    }
+}
+
+/// Split an accented letter into the accent and the base letter
+fn decompose(c: char) -> Option<(char, char)> {
+    let accent = match c {
+        'à' | 'è' | 'ì' | 'ò' | 'ù' | 'À' | 'È' | 'Ì' | 'Ò' | 'Ù' => '`',
+        'á' | 'é' | 'í' | 'ó' | 'ú' | 'ý' | 'Á' | 'É' | 'Í' | 'Ó' | 'Ú' | 'Ý' => '´',
+        'â' | 'ê' | 'î' | 'ô' | 'û' | 'Â' | 'Ê' | 'Î' | 'Ô' | 'Û' => '^',
+        'ä' | 'ë' | 'ï' | 'ö' | 'ü' | 'ÿ' | 'Ä' | 'Ë' | 'Ï' | 'Ö' | 'Ü' => '¨',
+        'ã' | 'õ' | 'ñ' | 'Ã' | 'Õ' | 'Ñ' => '~',
+        _ => return None,
+    };
+    let base = match c {
+        'à' | 'á' | 'â' | 'ä' | 'ã' => 'a',
+        'À' | 'Á' | 'Â' | 'Ä' | 'Ã' => 'A',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'È' | 'É' | 'Ê' | 'Ë' => 'E',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'Ì' | 'Í' | 'Î' | 'Ï' => 'I',
+        'ò' | 'ó' | 'ô' | 'ö' | 'õ' => 'o',
+        'Ò' | 'Ó' | 'Ô' | 'Ö' | 'Õ' => 'O',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'Ù' | 'Ú' | 'Û' | 'Ü' => 'U',
+        'ý' | 'ÿ' => 'y',
+        'Ý' => 'Y',
+        'ñ' => 'n',
+        _ => 'N',
+    };
+    Some((accent, base))
+}
+
+/// Key strokes which are same on all layouts
+fn common_key_stroke(c: char) -> Option<KeyStroke> {
+    let key = match c {
+        ' ' => KeyCode::Space,
+        '\n' => KeyCode::Enter,
+        '\t' => KeyCode::Tab,
+        '\x08' => KeyCode::Backspace,
+        '\x1B' => KeyCode::Escape,
+        '\x7F' => KeyCode::Delete,
+        _ => return None,
+    };
+    Some(KeyStroke::key(key))
+}
+
+/// Digits and symbols on the number row, which are shared by US, UK and Dvorak layouts
+fn number_row_key_stroke(c: char) -> Option<KeyStroke> {
+    let stroke = match c {
+        '1' => KeyStroke::key(KeyCode::Kc1),
+        '2' => KeyStroke::key(KeyCode::Kc2),
+        '3' => KeyStroke::key(KeyCode::Kc3),
+        '4' => KeyStroke::key(KeyCode::Kc4),
+        '5' => KeyStroke::key(KeyCode::Kc5),
+        '6' => KeyStroke::key(KeyCode::Kc6),
+        '7' => KeyStroke::key(KeyCode::Kc7),
+        '8' => KeyStroke::key(KeyCode::Kc8),
+        '9' => KeyStroke::key(KeyCode::Kc9),
+        '0' => KeyStroke::key(KeyCode::Kc0),
+        '!' => KeyStroke::shift(KeyCode::Kc1),
+        '$' => KeyStroke::shift(KeyCode::Kc4),
+        '%' => KeyStroke::shift(KeyCode::Kc5),
+        '^' => KeyStroke::shift(KeyCode::Kc6),
+        '&' => KeyStroke::shift(KeyCode::Kc7),
+        '*' => KeyStroke::shift(KeyCode::Kc8),
+        '(' => KeyStroke::shift(KeyCode::Kc9),
+        ')' => KeyStroke::shift(KeyCode::Kc0),
+        _ => return None,
+    };
+    Some(stroke)
+}
+
+fn us_key_stroke(c: char) -> Option<KeyStroke> {
+    let stroke = match c {
+        '@' => KeyStroke::shift(KeyCode::Kc2),
+        '#' => KeyStroke::shift(KeyCode::Kc3),
+        '`' => KeyStroke::key(KeyCode::Grave),
+        '~' => KeyStroke::shift(KeyCode::Grave),
+        '-' => KeyStroke::key(KeyCode::Minus),
+        '_' => KeyStroke::shift(KeyCode::Minus),
+        '=' => KeyStroke::key(KeyCode::Equal),
+        '+' => KeyStroke::shift(KeyCode::Equal),
+        '[' => KeyStroke::key(KeyCode::LeftBracket),
+        '{' => KeyStroke::shift(KeyCode::LeftBracket),
+        ']' => KeyStroke::key(KeyCode::RightBracket),
+        '}' => KeyStroke::shift(KeyCode::RightBracket),
+        '\\' => KeyStroke::key(KeyCode::Backslash),
+        '|' => KeyStroke::shift(KeyCode::Backslash),
+        ';' => KeyStroke::key(KeyCode::Semicolon),
+        ':' => KeyStroke::shift(KeyCode::Semicolon),
+        '\'' => KeyStroke::key(KeyCode::Quote),
+        '"' => KeyStroke::shift(KeyCode::Quote),
+        ',' => KeyStroke::key(KeyCode::Comma),
+        '<' => KeyStroke::shift(KeyCode::Comma),
+        '.' => KeyStroke::key(KeyCode::Dot),
+        '>' => KeyStroke::shift(KeyCode::Dot),
+        '/' => KeyStroke::key(KeyCode::Slash),
+        '?' => KeyStroke::shift(KeyCode::Slash),
+        _ => return number_row_key_stroke(c),
+    };
+    Some(stroke)
+}
+
+fn uk_key_stroke(c: char) -> Option<KeyStroke> {
+    let stroke = match c {
+        '"' => KeyStroke::shift(KeyCode::Kc2),
+        '£' => KeyStroke::shift(KeyCode::Kc3),
+        '€' => KeyStroke::altgr(KeyCode::Kc4),
+        '¬' => KeyStroke::shift(KeyCode::Grave),
+        '@' => KeyStroke::shift(KeyCode::Quote),
+        '#' => KeyStroke::key(KeyCode::NonusHash),
+        '~' => KeyStroke::shift(KeyCode::NonusHash),
+        '\\' => KeyStroke::key(KeyCode::NonusBackslash),
+        '|' => KeyStroke::shift(KeyCode::NonusBackslash),
+        _ => return us_key_stroke(c),
+    };
+    Some(stroke)
+}
+
+fn german_key_stroke(c: char) -> Option<KeyStroke> {
+    let stroke = match c {
+        '1' => KeyStroke::key(KeyCode::Kc1),
+        '2' => KeyStroke::key(KeyCode::Kc2),
+        '3' => KeyStroke::key(KeyCode::Kc3),
+        '4' => KeyStroke::key(KeyCode::Kc4),
+        '5' => KeyStroke::key(KeyCode::Kc5),
+        '6' => KeyStroke::key(KeyCode::Kc6),
+        '7' => KeyStroke::key(KeyCode::Kc7),
+        '8' => KeyStroke::key(KeyCode::Kc8),
+        '9' => KeyStroke::key(KeyCode::Kc9),
+        '0' => KeyStroke::key(KeyCode::Kc0),
+        '!' => KeyStroke::shift(KeyCode::Kc1),
+        '"' => KeyStroke::shift(KeyCode::Kc2),
+        '§' => KeyStroke::shift(KeyCode::Kc3),
+        '$' => KeyStroke::shift(KeyCode::Kc4),
+        '%' => KeyStroke::shift(KeyCode::Kc5),
+        '&' => KeyStroke::shift(KeyCode::Kc6),
+        '/' => KeyStroke::shift(KeyCode::Kc7),
+        '(' => KeyStroke::shift(KeyCode::Kc8),
+        ')' => KeyStroke::shift(KeyCode::Kc9),
+        '=' => KeyStroke::shift(KeyCode::Kc0),
+        '²' => KeyStroke::altgr(KeyCode::Kc2),
+        '³' => KeyStroke::altgr(KeyCode::Kc3),
+        '{' => KeyStroke::altgr(KeyCode::Kc7),
+        '[' => KeyStroke::altgr(KeyCode::Kc8),
+        ']' => KeyStroke::altgr(KeyCode::Kc9),
+        '}' => KeyStroke::altgr(KeyCode::Kc0),
+        'ß' => KeyStroke::key(KeyCode::Minus),
+        '?' => KeyStroke::shift(KeyCode::Minus),
+        '\\' => KeyStroke::altgr(KeyCode::Minus),
+        '´' => KeyStroke::key(KeyCode::Equal),
+        '`' => KeyStroke::shift(KeyCode::Equal),
+        '^' => KeyStroke::key(KeyCode::Grave),
+        '°' => KeyStroke::shift(KeyCode::Grave),
+        'ü' => KeyStroke::key(KeyCode::LeftBracket),
+        'Ü' => KeyStroke::shift(KeyCode::LeftBracket),
+        '+' => KeyStroke::key(KeyCode::RightBracket),
+        '*' => KeyStroke::shift(KeyCode::RightBracket),
+        '~' => KeyStroke::altgr(KeyCode::RightBracket),
+        'ö' => KeyStroke::key(KeyCode::Semicolon),
+        'Ö' => KeyStroke::shift(KeyCode::Semicolon),
+        'ä' => KeyStroke::key(KeyCode::Quote),
+        'Ä' => KeyStroke::shift(KeyCode::Quote),
+        '#' => KeyStroke::key(KeyCode::NonusHash),
+        '\'' => KeyStroke::shift(KeyCode::NonusHash),
+        '<' => KeyStroke::key(KeyCode::NonusBackslash),
+        '>' => KeyStroke::shift(KeyCode::NonusBackslash),
+        '|' => KeyStroke::altgr(KeyCode::NonusBackslash),
+        ',' => KeyStroke::key(KeyCode::Comma),
+        ';' => KeyStroke::shift(KeyCode::Comma),
+        '.' => KeyStroke::key(KeyCode::Dot),
+        ':' => KeyStroke::shift(KeyCode::Dot),
+        '-' => KeyStroke::key(KeyCode::Slash),
+        '_' => KeyStroke::shift(KeyCode::Slash),
+        '@' => KeyStroke::altgr(KeyCode::Q),
+        '€' => KeyStroke::altgr(KeyCode::E),
+        'µ' => KeyStroke::altgr(KeyCode::M),
+        _ => return None,
+    };
+    Some(stroke)
+}
+
+fn french_key_stroke(c: char) -> Option<KeyStroke> {
+    let stroke = match c {
+        '&' => KeyStroke::key(KeyCode::Kc1),
+        'é' => KeyStroke::key(KeyCode::Kc2),
+        '"' => KeyStroke::key(KeyCode::Kc3),
+        '\'' => KeyStroke::key(KeyCode::Kc4),
+        '(' => KeyStroke::key(KeyCode::Kc5),
+        '-' => KeyStroke::key(KeyCode::Kc6),
+        'è' => KeyStroke::key(KeyCode::Kc7),
+        '_' => KeyStroke::key(KeyCode::Kc8),
+        'ç' => KeyStroke::key(KeyCode::Kc9),
+        'à' => KeyStroke::key(KeyCode::Kc0),
+        ')' => KeyStroke::key(KeyCode::Minus),
+        '=' => KeyStroke::key(KeyCode::Equal),
+        '1' => KeyStroke::shift(KeyCode::Kc1),
+        '2' => KeyStroke::shift(KeyCode::Kc2),
+        '3' => KeyStroke::shift(KeyCode::Kc3),
+        '4' => KeyStroke::shift(KeyCode::Kc4),
+        '5' => KeyStroke::shift(KeyCode::Kc5),
+        '6' => KeyStroke::shift(KeyCode::Kc6),
+        '7' => KeyStroke::shift(KeyCode::Kc7),
+        '8' => KeyStroke::shift(KeyCode::Kc8),
+        '9' => KeyStroke::shift(KeyCode::Kc9),
+        '0' => KeyStroke::shift(KeyCode::Kc0),
+        '°' => KeyStroke::shift(KeyCode::Minus),
+        '+' => KeyStroke::shift(KeyCode::Equal),
+        '~' => KeyStroke::altgr(KeyCode::Kc2),
+        '#' => KeyStroke::altgr(KeyCode::Kc3),
+        '{' => KeyStroke::altgr(KeyCode::Kc4),
+        '[' => KeyStroke::altgr(KeyCode::Kc5),
+        '|' => KeyStroke::altgr(KeyCode::Kc6),
+        '`' => KeyStroke::altgr(KeyCode::Kc7),
+        '\\' => KeyStroke::altgr(KeyCode::Kc8),
+        '@' => KeyStroke::altgr(KeyCode::Kc0),
+        ']' => KeyStroke::altgr(KeyCode::Minus),
+        '}' => KeyStroke::altgr(KeyCode::Equal),
+        '€' => KeyStroke::altgr(KeyCode::E),
+        '²' => KeyStroke::key(KeyCode::Grave),
+        '^' => KeyStroke::key(KeyCode::LeftBracket),
+        '¨' => KeyStroke::shift(KeyCode::LeftBracket),
+        '$' => KeyStroke::key(KeyCode::RightBracket),
+        '£' => KeyStroke::shift(KeyCode::RightBracket),
+        '¤' => KeyStroke::altgr(KeyCode::RightBracket),
+        'ù' => KeyStroke::key(KeyCode::Quote),
+        '%' => KeyStroke::shift(KeyCode::Quote),
+        '*' => KeyStroke::key(KeyCode::NonusHash),
+        'µ' => KeyStroke::shift(KeyCode::NonusHash),
+        '<' => KeyStroke::key(KeyCode::NonusBackslash),
+        '>' => KeyStroke::shift(KeyCode::NonusBackslash),
+        ',' => KeyStroke::key(KeyCode::M),
+        '?' => KeyStroke::shift(KeyCode::M),
+        ';' => KeyStroke::key(KeyCode::Comma),
+        '.' => KeyStroke::shift(KeyCode::Comma),
+        ':' => KeyStroke::key(KeyCode::Dot),
+        '/' => KeyStroke::shift(KeyCode::Dot),
+        '!' => KeyStroke::key(KeyCode::Slash),
+        '§' => KeyStroke::shift(KeyCode::Slash),
+        _ => return None,
+    };
+    Some(stroke)
+}
+
+fn dvorak_key_stroke(c: char) -> Option<KeyStroke> {
+    let stroke = match c {
+        '@' => KeyStroke::shift(KeyCode::Kc2),
+        '#' => KeyStroke::shift(KeyCode::Kc3),
+        '`' => KeyStroke::key(KeyCode::Grave),
+        '~' => KeyStroke::shift(KeyCode::Grave),
+        '[' => KeyStroke::key(KeyCode::Minus),
+        '{' => KeyStroke::shift(KeyCode::Minus),
+        ']' => KeyStroke::key(KeyCode::Equal),
+        '}' => KeyStroke::shift(KeyCode::Equal),
+        '\'' => KeyStroke::key(KeyCode::Q),
+        '"' => KeyStroke::shift(KeyCode::Q),
+        ',' => KeyStroke::key(KeyCode::W),
+        '<' => KeyStroke::shift(KeyCode::W),
+        '.' => KeyStroke::key(KeyCode::E),
+        '>' => KeyStroke::shift(KeyCode::E),
+        '/' => KeyStroke::key(KeyCode::LeftBracket),
+        '?' => KeyStroke::shift(KeyCode::LeftBracket),
+        '=' => KeyStroke::key(KeyCode::RightBracket),
+        '+' => KeyStroke::shift(KeyCode::RightBracket),
+        '\\' => KeyStroke::key(KeyCode::Backslash),
+        '|' => KeyStroke::shift(KeyCode::Backslash),
+        '-' => KeyStroke::key(KeyCode::Quote),
+        '_' => KeyStroke::shift(KeyCode::Quote),
+        ';' => KeyStroke::key(KeyCode::Z),
+        ':' => KeyStroke::shift(KeyCode::Z),
+        _ => return number_row_key_stroke(c),
+    };
+    Some(stroke)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_host_layout_letters() {
+        assert_eq!(
+            HostLayout::Us.to_key_strokes('z'),
+            Some((KeyStroke::key(KeyCode::Z), None))
+        );
+        assert_eq!(
+            HostLayout::German.to_key_strokes('Z'),
+            Some((KeyStroke::shift(KeyCode::Y), None))
+        );
+        assert_eq!(
+            HostLayout::French.to_key_strokes('m'),
+            Some((KeyStroke::key(KeyCode::Semicolon), None))
+        );
+        assert_eq!(
+            HostLayout::Dvorak.to_key_strokes('s'),
+            Some((KeyStroke::key(KeyCode::Semicolon), None))
+        );
+    }
+
+    #[test]
+    fn test_host_layout_symbols() {
+        assert_eq!(
+            HostLayout::Us.to_key_strokes('|'),
+            Some((KeyStroke::shift(KeyCode::Backslash), None))
+        );
+        assert_eq!(
+            HostLayout::Uk.to_key_strokes('@'),
+            Some((KeyStroke::shift(KeyCode::Quote), None))
+        );
+        assert_eq!(
+            HostLayout::German.to_key_strokes('@'),
+            Some((KeyStroke::altgr(KeyCode::Q), None))
+        );
+        assert_eq!(
+            HostLayout::French.to_key_strokes('1'),
+            Some((KeyStroke::shift(KeyCode::Kc1), None))
+        );
+        assert_eq!(HostLayout::Us.to_key_strokes('€'), None);
+    }
+
+    #[test]
+    fn test_host_layout_dead_keys() {
+        // The accent itself is typed by the dead key followed by a space
+        assert_eq!(
+            HostLayout::German.to_key_strokes('^'),
+            Some((
+                KeyStroke::key(KeyCode::Grave),
+                Some(KeyStroke::key(KeyCode::Space))
+            ))
+        );
+        // Accented letters are composed by the dead key and the base letter
+        assert_eq!(
+            HostLayout::French.to_key_strokes('Ê'),
+            Some((
+                KeyStroke::key(KeyCode::LeftBracket),
+                Some(KeyStroke::shift(KeyCode::E))
+            ))
+        );
+        assert_eq!(
+            HostLayout::German.to_key_strokes('ñ'),
+            Some((
+                KeyStroke::altgr(KeyCode::RightBracket),
+                Some(KeyStroke::key(KeyCode::N))
+            ))
+        );
+        // Letters with their own keys are typed directly
+        assert_eq!(
+            HostLayout::French.to_key_strokes('é'),
+            Some((KeyStroke::key(KeyCode::Kc2), None))
+        );
+        assert_eq!(HostLayout::Us.to_key_strokes('é'), None);
+    }
+}
@@ -0,0 +1,85 @@
+//! Joystick(gamepad) axes processing
+//!
+//! The gamepad report is shared between the buttons, which are set via `JoystickButton*` keycodes,
+//! and the analog axes, which are set by [`JoystickProcessor`] from joystick events.
+//! Both are processed by the keyboard.
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+
+use crate::event::{Axis, AxisEvent, AxisValType, Event};
+use crate::usb::descriptor::JoystickReport;
+
+/// Current state of the gamepad report
+pub(crate) static JOYSTICK_REPORT: Mutex<CriticalSectionRawMutex, RefCell<JoystickReport>> =
+    Mutex::new(RefCell::new(JoystickReport {
+        buttons: 0,
+        x: 0,
+        y: 0,
+        z: 0,
+        rx: 0,
+        ry: 0,
+        rz: 0,
+    }));
+
+/// Update the gamepad report with the given closure, return the updated report
+pub(crate) fn update_joystick_report<F: FnOnce(&mut JoystickReport)>(f: F) -> JoystickReport {
+    JOYSTICK_REPORT.lock(|r| {
+        let mut report = r.borrow_mut();
+        f(&mut report);
+        *report
+    })
+}
+
+/// Converts [`Event::Joystick`] and [`Event::AxisEventStream`] to gamepad axes
+#[derive(Default)]
+pub(crate) struct JoystickProcessor {
+    /// Whether there're axis changes in the current event stream which haven't been sent
+    pending: bool,
+}
+
+impl JoystickProcessor {
+    pub(crate) fn new() -> Self {
+        Self { pending: false }
+    }
+
+    fn update_axis(report: &mut JoystickReport, event: &AxisEvent) {
+        let axis = match event.axis {
+            Axis::X => &mut report.x,
+            Axis::Y => &mut report.y,
+            Axis::Z => &mut report.z,
+            Axis::Rx => &mut report.rx,
+            Axis::Ry => &mut report.ry,
+            Axis::Rz => &mut report.rz,
+            _ => return,
+        };
+        match event.typ {
+            AxisValType::Abs => *axis = event.value,
+            AxisValType::Rel => *axis = axis.saturating_add(event.value),
+        }
+    }
+
+    /// Process a joystick event, return the gamepad report which should be sent
+    pub(crate) fn process(&mut self, event: &Event) -> Option<JoystickReport> {
+        match event {
+            Event::Joystick(axes) => {
+                self.pending = false;
+                Some(update_joystick_report(|r| {
+                    axes.iter().for_each(|e| Self::update_axis(r, e));
+                }))
+            }
+            Event::AxisEventStream(e) => {
+                update_joystick_report(|r| Self::update_axis(r, e));
+                self.pending = true;
+                None
+            }
+            Event::Eos if self.pending => {
+                self.pending = false;
+                Some(update_joystick_report(|_| {}))
+            }
+            _ => None,
+        }
+    }
+}
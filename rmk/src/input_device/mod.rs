@@ -2,19 +2,22 @@
 //!
 //! This module defines the `InputDevice` trait and the `run_devices` macro, enabling the simultaneous execution of multiple input devices.
 //! The `InputDevice` trait provides the interface for individual input devices, and the `run_devices` macro facilitates their concurrent execution.
+//! Events of all input devices are processed by the `InputProcessor` pipeline, and finally by the keyboard.
 //!
 //! Note: The `InputDevice` trait must be used in conjunction with the `run_devices` macro to ensure correct execution of all input devices.
 
 use core::future::Future;
 
-use embassy_sync::{
-    blocking_mutex::raw::CriticalSectionRawMutex,
-    channel::{Receiver, Sender},
-};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Sender};
 
-use crate::keyboard::{EVENT_CHANNEL_SIZE, REPORT_CHANNEL_SIZE};
+use crate::{event::Event, keyboard::EVENT_CHANNEL_SIZE};
 
+#[cfg(feature = "joystick")]
+pub mod joystick;
+pub mod pointing;
 pub mod rotary_encoder;
+#[cfg(feature = "touchpad")]
+pub mod touchpad;
 
 /// The trait for input devices.
 ///
@@ -67,52 +70,93 @@ pub trait InputDevice {
     fn event_sender(&self) -> Sender<CriticalSectionRawMutex, Self::EventType, EVENT_CHANNEL_SIZE>;
 }
 
+/// Result of an [`InputProcessor`]
+pub enum ProcessResult {
+    /// Pass the event to the next processor
+    Continue(Event),
+    /// The event is consumed, it won't be processed by the following processors and the keyboard
+    Stop,
+}
+
 /// The trait for input processors.
 ///
-/// The input processor processes the [`Event`] from the input devices and converts it to the final HID report.
-/// Take the normal keyboard as the example:
+/// Input processors are stages of the event pipeline. All events sent to [`EVENT_CHANNEL`](crate::EVENT_CHANNEL) by input devices
+/// go through the input processors in order, then reach the keyboard, which is the last stage of the pipeline.
+/// The keyboard maps key events to actions in the keymap, and converts other events to HID reports.
+///
+/// Processors can be chained by [`InputProcessor::chain`], and the chain is set in [`RmkConfig::processor`](crate::config::RmkConfig::processor).
+/// A closure `FnMut(Event) -> ProcessResult` is also an input processor.
+///
+/// # Example
+/// ```rust
+/// // Swap key (0, 0) and key (0, 1)
+/// struct Remap;
+///
+/// impl InputProcessor for Remap {
+///     fn process(&mut self, event: Event) -> ProcessResult {
+///         match event {
+///             Event::Key(mut e) if e.row == 0 && e.col < 2 => {
+///                 e.col = 1 - e.col;
+///                 ProcessResult::Continue(Event::Key(e))
+///             }
+///             e => ProcessResult::Continue(e),
+///         }
+///     }
+/// }
+///
+/// // Ignore all rotary encoder events
+/// struct Filter;
 ///
-/// The [`Matrix`] is actually an input device and the [`Keyboard`] is actually an input processor.
+/// impl InputProcessor for Filter {
+///     fn process(&mut self, event: Event) -> ProcessResult {
+///         match event {
+///             Event::RotaryEncoder(_) => ProcessResult::Stop,
+///             e => ProcessResult::Continue(e),
+///         }
+///     }
+/// }
+///
+/// static PROCESSOR: StaticCell<Chain<Remap, Filter>> = StaticCell::new();
+/// let keyboard_config = RmkConfig {
+///     processor: Some(PROCESSOR.init(Remap.chain(Filter))),
+///     ..Default::default()
+/// };
+/// ```
 pub trait InputProcessor {
-    /// The event type that the input processor receives.
-    type EventType;
-
-    /// The report type that the input processor sends.
-    type ReportType;
-
-    /// Process the incoming events, convert them to HID report [`KeyboardReportMessage`],
-    /// then send the report to the USB/BLE.
+    /// Process an event.
     ///
-    /// Note there might be mulitple HID reports are generated for one event,
-    /// so the "sending report" operation should be done in the `process` method.
-    /// The input processor implementor should be aware of this.  
-    fn process(&mut self, event: Self::EventType) -> impl Future<Output = ()>;
+    /// This is called in the keyboard task, so it should return quickly.
+    fn process(&mut self, event: Event) -> ProcessResult;
+
+    /// Chain another input processor after this one
+    fn chain<P: InputProcessor>(self, next: P) -> Chain<Self, P>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
 
-    /// Get the input event channel  receiver for the input processor.
-    ///
-    /// The input processor receives events from this channel, processes the event,
-    /// then sends to the report channel.
-    fn event_receiver(
-        &self,
-    ) -> Receiver<CriticalSectionRawMutex, Self::EventType, EVENT_CHANNEL_SIZE>;
+impl<F: FnMut(Event) -> ProcessResult> InputProcessor for F {
+    fn process(&mut self, event: Event) -> ProcessResult {
+        self(event)
+    }
+}
 
-    /// Get the output report sender for the input processor.
-    ///
-    /// The input processor sends keyboard reports to this channel.
-    fn report_sender(
-        &self,
-    ) -> Sender<CriticalSectionRawMutex, Self::ReportType, REPORT_CHANNEL_SIZE>;
+/// Two chained input processors, the event is processed by `first`, then `second`
+pub struct Chain<A: InputProcessor, B: InputProcessor> {
+    first: A,
+    second: B,
+}
 
-    /// Default implementation of the input processor. It wait for a new event from the event channel,
-    /// then process the event.
-    ///
-    /// The report is sent to the USB/BLE in the `process` method.
-    fn run(&mut self) -> impl Future<Output = ()> {
-        async {
-            loop {
-                let event = self.event_receiver().receive().await;
-                self.process(event).await;
-            }
+impl<A: InputProcessor, B: InputProcessor> InputProcessor for Chain<A, B> {
+    fn process(&mut self, event: Event) -> ProcessResult {
+        match self.first.process(event) {
+            ProcessResult::Continue(event) => self.second.process(event),
+            ProcessResult::Stop => ProcessResult::Stop,
         }
     }
 }
@@ -146,33 +190,3 @@ macro_rules! run_devices {
         ::embassy_futures::join::join($first.run(), run_devices!($second $(, $rest)*))
     };
 }
-
-/// Macro to run multiple input processors concurrently.
-///
-/// The `run_processors` macro is specifically designed to work with the `InputProcessor` trait. It takes one or more instances of
-/// input processors and combines their `run` methods into a single future. All futures are executed concurrently, enabling
-/// efficient multitasking for multiple input processors.
-///
-/// # Note
-/// This macro must be used with input processors that implement the `InputProcessor` trait.
-///
-/// # Example
-/// ```rust
-/// // `RotaryEncoderProcessor` and `TouchpadProcessor` should implement `InputProcessor` trait
-/// let d1 = RotaryEncoderProcessor{};
-/// let d2 = TouchpadProcessor{};
-///
-/// // Run all input devices concurrently
-/// run_processors!(d1, d2).await;
-/// ```
-#[macro_export]
-macro_rules! run_processors {
-    // Single device case
-    ($single:expr) => {
-        $single.run()
-    };
-    // Multiple devices case
-    ($first:expr, $second:expr $(, $rest:expr)*) => {
-        ::embassy_futures::join::join($first.run(), run_processors!($second $(, $rest)*))
-    };
-}
@@ -0,0 +1,102 @@
+//! Pointing device(trackball, trackpad, etc.) processing
+//!
+//! Motion of pointing devices is sent to the host with the mouse report, which has 16-bit axes.
+//! The wheel and pan axes are in high-resolution units, which are 1/[`RESOLUTION_MULTIPLIER`] of a detent.
+//! If the host doesn't enable the resolution multiplier in the feature report,
+//! the high-resolution units are accumulated and converted to detents before sending.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::event::{Axis, AxisEvent, AxisValType, Event};
+use crate::usb::descriptor::{CompositeReport, RESOLUTION_MULTIPLIER};
+
+/// Resolution multiplier feature report set by the host.
+/// Bits 0-1 are the multiplier of the wheel, bits 2-3 are the multiplier of the pan.
+static RESOLUTION_MULTIPLIER_FEATURE: AtomicU8 = AtomicU8::new(0);
+
+const WHEEL_MULTIPLIER_MASK: u8 = 0b0011;
+const PAN_MULTIPLIER_MASK: u8 = 0b1100;
+
+/// Get the resolution multiplier feature report
+pub(crate) fn resolution_multiplier() -> u8 {
+    RESOLUTION_MULTIPLIER_FEATURE.load(Ordering::Acquire)
+}
+
+/// Update the resolution multiplier feature report, it's set by the host
+pub(crate) fn set_resolution_multiplier(feature: u8) {
+    debug!("Set resolution multiplier: {:#x}", feature);
+    RESOLUTION_MULTIPLIER_FEATURE.store(
+        feature & (WHEEL_MULTIPLIER_MASK | PAN_MULTIPLIER_MASK),
+        Ordering::Release,
+    );
+}
+
+/// Converts [`Event::Pointing`] to mouse reports, and scales the scrolling of mouse reports
+#[derive(Default)]
+pub(crate) struct PointingProcessor {
+    /// High-resolution wheel units which haven't been sent as a detent
+    wheel_remainder: i16,
+    /// High-resolution pan units which haven't been sent as a detent
+    pan_remainder: i16,
+}
+
+impl PointingProcessor {
+    pub(crate) fn new() -> Self {
+        Self {
+            wheel_remainder: 0,
+            pan_remainder: 0,
+        }
+    }
+
+    fn update_axis(report: &mut CompositeReport, event: &AxisEvent) {
+        let axis = match event.axis {
+            Axis::X => &mut report.x,
+            Axis::Y => &mut report.y,
+            Axis::V => &mut report.wheel,
+            Axis::H => &mut report.pan,
+            _ => return,
+        };
+        // Mouse axes are always relative
+        match event.typ {
+            AxisValType::Rel => *axis = axis.saturating_add(event.value),
+            AxisValType::Abs => warn!("Absolute axis of pointing device is ignored"),
+        }
+    }
+
+    /// Process a pointing event, return the mouse report which should be sent.
+    ///
+    /// The motion is added to a copy of the current mouse report, which has the button states and motions of mouse keys.
+    pub(crate) fn process(
+        &self,
+        event: &Event,
+        report: &CompositeReport,
+    ) -> Option<CompositeReport> {
+        match event {
+            Event::Pointing(axes) => {
+                let mut report = *report;
+                axes.iter().for_each(|e| Self::update_axis(&mut report, e));
+                Some(report)
+            }
+            _ => None,
+        }
+    }
+
+    /// Convert high-resolution scrolling of the mouse report to detents,
+    /// if the resolution multiplier isn't enabled by the host
+    pub(crate) fn scale_scroll(&mut self, report: &mut CompositeReport) {
+        let multiplier = resolution_multiplier();
+        if multiplier & WHEEL_MULTIPLIER_MASK == 0 {
+            report.wheel = Self::to_detents(&mut self.wheel_remainder, report.wheel);
+        }
+        if multiplier & PAN_MULTIPLIER_MASK == 0 {
+            report.pan = Self::to_detents(&mut self.pan_remainder, report.pan);
+        }
+    }
+
+    fn to_detents(remainder: &mut i16, value: i16) -> i16 {
+        let total = *remainder as i32 + value as i32;
+        let detents = total / RESOLUTION_MULTIPLIER as i32;
+        *remainder = (total % RESOLUTION_MULTIPLIER as i32) as i16;
+        detents.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+    }
+}
@@ -1,7 +1,7 @@
 //! The rotary encoder implementation is adapted from: https://github.com/leshow/rotary-encoder-hal/blob/master/src/lib.rs
 
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::channel::{Receiver, Sender};
+use embassy_sync::channel::Sender;
 use embedded_hal::digital::InputPin;
 #[cfg(feature = "async_matrix")]
 use embedded_hal_async::digital::Wait;
@@ -9,10 +9,9 @@ use postcard::experimental::max_size::MaxSize;
 use serde::{Deserialize, Serialize};
 
 use crate::event::{Event, RotaryEncoderEvent};
-use crate::keyboard::{KeyboardReportMessage, EVENT_CHANNEL, KEYBOARD_REPORT_CHANNEL};
-use crate::REPORT_CHANNEL_SIZE;
+use crate::keyboard::EVENT_CHANNEL;
 
-use super::{InputDevice, InputProcessor, EVENT_CHANNEL_SIZE};
+use super::{InputDevice, EVENT_CHANNEL_SIZE};
 
 /// Holds current/old state and both [`InputPin`](https://docs.rs/embedded-hal/latest/embedded_hal/digital/trait.InputPin.html)
 #[derive(Clone, Debug)]
@@ -162,6 +161,10 @@ impl<
             }
 
             let direction = self.update();
+            // Only changes are sent to the keyboard
+            if let Direction::None = direction {
+                continue;
+            }
 
             self.event_sender()
                 .send(Event::RotaryEncoder(RotaryEncoderEvent {
@@ -176,38 +179,3 @@ impl<
         EVENT_CHANNEL.sender()
     }
 }
-
-pub struct RotaryEncoderProcessor {}
-
-impl InputProcessor for RotaryEncoderProcessor {
-    type EventType = Event;
-
-    type ReportType = KeyboardReportMessage;
-
-    async fn process(&mut self, event: Self::EventType) {
-        match event {
-            Event::RotaryEncoder(RotaryEncoderEvent { id, direction }) => match direction {
-                Direction::Clockwise => {
-                    debug!("Encoder {} - Clockwise", id);
-                }
-                Direction::CounterClockwise => {
-                    debug!("Encoder {} - CounterClockwise", id);
-                }
-                Direction::None => (),
-            },
-            _ => {}
-        }
-    }
-
-    fn event_receiver(
-        &self,
-    ) -> Receiver<CriticalSectionRawMutex, Self::EventType, EVENT_CHANNEL_SIZE> {
-        EVENT_CHANNEL.receiver()
-    }
-
-    fn report_sender(
-        &self,
-    ) -> Sender<CriticalSectionRawMutex, Self::ReportType, REPORT_CHANNEL_SIZE> {
-        KEYBOARD_REPORT_CHANNEL.sender()
-    }
-}
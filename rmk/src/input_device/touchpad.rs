@@ -0,0 +1,278 @@
+//! Precision touchpad(Windows PTP) processing
+//!
+//! [`TouchpadProcessor`] converts [`Event::Touchpad`] events to touchpad reports, which are sent via a separate USB hid interface.
+//! The host switches the touchpad to touchpad mode by the input mode feature report.
+//! Before that, for example when the host doesn't support precision touchpads, the motion of the first contact is sent as mouse motion.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_time::Instant;
+
+use crate::event::{Axis, AxisEvent, AxisValType, Event, TouchpadEvent};
+use crate::input_device::{InputProcessor, ProcessResult};
+use crate::keyboard::{KeyboardReportMessage, KEYBOARD_REPORT_CHANNEL};
+use crate::usb::descriptor::{
+    TouchpadContact, TouchpadReport, TouchpadReportType, NUM_TOUCHPAD_CONTACTS,
+    TOUCHPAD_LOGICAL_MAX,
+};
+
+/// Input mode of mouse
+const INPUT_MODE_MOUSE: u8 = 0;
+/// Input mode of touchpad
+const INPUT_MODE_TOUCHPAD: u8 = 3;
+/// The touchpad doesn't have a button, the buttons are external(mouse keys)
+const PAD_TYPE_EXTERNAL_BUTTONS: u8 = 2;
+
+const SURFACE_SWITCH: u8 = 0b01;
+const BUTTON_SWITCH: u8 = 0b10;
+
+const CONTACT_CONFIDENCE: u8 = 0b01;
+const CONTACT_TIP_SWITCH: u8 = 0b10;
+
+/// Size of the certification blob of precision touchpads
+pub const TOUCHPAD_CERTIFICATION_SIZE: usize = 256;
+
+/// Certification blob set by [`TouchpadProcessor::with_certification`]
+static CERTIFICATION: Mutex<
+    CriticalSectionRawMutex,
+    Cell<Option<&'static [u8; TOUCHPAD_CERTIFICATION_SIZE]>>,
+> = Mutex::new(Cell::new(None));
+
+/// Input mode set by the host
+static INPUT_MODE: AtomicU8 = AtomicU8::new(INPUT_MODE_MOUSE);
+/// Surface switch(bit 0) and button switch(bit 1) set by the host
+static FUNCTION_SWITCH: AtomicU8 = AtomicU8::new(SURFACE_SWITCH | BUTTON_SWITCH);
+
+/// Reset the touchpad to mouse mode, it should be called when the host is changed
+pub(crate) fn reset_touchpad_mode() {
+    INPUT_MODE.store(INPUT_MODE_MOUSE, Ordering::Release);
+    FUNCTION_SWITCH.store(SURFACE_SWITCH | BUTTON_SWITCH, Ordering::Release);
+}
+
+/// Fill the feature report with the given report id in `buf`, the report id is prepended.
+/// Returns the length of the report.
+pub(crate) fn get_touchpad_feature_report(report_id: u8, buf: &mut [u8]) -> Option<usize> {
+    let len = match TouchpadReportType::from_u8(report_id)? {
+        TouchpadReportType::Capabilities => {
+            // Contact count maximum(bits 0-3) and pad type(bits 4-7)
+            *buf.get_mut(1)? = NUM_TOUCHPAD_CONTACTS as u8 | (PAD_TYPE_EXTERNAL_BUTTONS << 4);
+            2
+        }
+        TouchpadReportType::Certification => {
+            // Windows doesn't enable the touchpad mode without a valid blob, in that case the touchpad works as a mouse
+            let data = buf.get_mut(1..TOUCHPAD_CERTIFICATION_SIZE + 1)?;
+            match CERTIFICATION.lock(|c| c.get()) {
+                Some(blob) => data.copy_from_slice(blob),
+                None => data.fill(0),
+            }
+            TOUCHPAD_CERTIFICATION_SIZE + 1
+        }
+        TouchpadReportType::InputMode => {
+            *buf.get_mut(1)? = INPUT_MODE.load(Ordering::Acquire);
+            2
+        }
+        TouchpadReportType::FunctionSwitch => {
+            *buf.get_mut(1)? = FUNCTION_SWITCH.load(Ordering::Acquire);
+            2
+        }
+        TouchpadReportType::Touchpad => return None,
+    };
+    buf[0] = report_id;
+    Some(len)
+}
+
+/// Save the feature report set by the host, the data might start with the report id.
+/// Returns `false` if the report isn't writable.
+pub(crate) fn set_touchpad_feature_report(report_id: u8, data: &[u8]) -> bool {
+    let Some(&value) = data.last() else {
+        return false;
+    };
+    match TouchpadReportType::from_u8(report_id) {
+        Some(TouchpadReportType::InputMode) => {
+            info!("Touchpad input mode: {}", value);
+            INPUT_MODE.store(value, Ordering::Release);
+            true
+        }
+        Some(TouchpadReportType::FunctionSwitch) => {
+            info!("Touchpad function switch: {:#x}", value);
+            FUNCTION_SWITCH.store(value & (SURFACE_SWITCH | BUTTON_SWITCH), Ordering::Release);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// State of a finger slot
+#[derive(Default, Clone, Copy)]
+struct Finger {
+    x: u16,
+    y: u16,
+    touching: bool,
+    /// The finger is lifted in the current frame, which should be reported once more
+    lifted: bool,
+}
+
+/// Converts [`Event::Touchpad`] to precision touchpad reports.
+///
+/// A [`TouchpadEvent`] updates a finger slot, the slot should be less than 5.
+/// X/Y axes are the position of the finger, the finger touches the touchpad if Z axis is greater than 0.
+/// Touchpad events of a frame should be followed by an [`Event::Eos`], then the report of the frame is sent.
+///
+/// Windows enables the touchpad mode only if the touchpad provides a certification blob,
+/// set it by [`TouchpadProcessor::with_certification`].
+///
+/// # Example
+/// ```rust
+/// // The max X/Y reported by the touchpad device
+/// static TOUCHPAD: StaticCell<TouchpadProcessor> = StaticCell::new();
+/// let keyboard_config = RmkConfig {
+///     processor: Some(TOUCHPAD.init(
+///         TouchpadProcessor::new(1920, 1080).with_certification(&CERTIFICATION_BLOB),
+///     )),
+///     ..Default::default()
+/// };
+/// ```
+pub struct TouchpadProcessor {
+    /// Max X of the touchpad device
+    max_x: u16,
+    /// Max Y of the touchpad device
+    max_y: u16,
+    fingers: [Finger; NUM_TOUCHPAD_CONTACTS],
+    /// Whether there're finger changes which haven't been sent
+    pending: bool,
+    /// Position of the first finger in the last frame, used in mouse mode
+    last_position: Option<(u16, u16)>,
+}
+
+impl TouchpadProcessor {
+    /// Create a touchpad processor, `max_x` and `max_y` are the max coordinates of the touchpad device
+    pub fn new(max_x: u16, max_y: u16) -> Self {
+        Self {
+            max_x: max_x.max(1),
+            max_y: max_y.max(1),
+            fingers: [Finger::default(); NUM_TOUCHPAD_CONTACTS],
+            pending: false,
+            last_position: None,
+        }
+    }
+
+    /// Set the certification blob which is sent to the host in the certification feature report.
+    ///
+    /// The blob is issued by Microsoft for certified touchpads.
+    /// For development, the sample blob in the Windows precision touchpad documentation can be used.
+    pub fn with_certification(self, blob: &'static [u8; TOUCHPAD_CERTIFICATION_SIZE]) -> Self {
+        CERTIFICATION.lock(|c| c.set(Some(blob)));
+        self
+    }
+
+    fn update_axis(value: &mut u16, event: &AxisEvent) {
+        match event.typ {
+            AxisValType::Abs => *value = event.value.max(0) as u16,
+            AxisValType::Rel => *value = value.saturating_add_signed(event.value),
+        }
+    }
+
+    fn update_finger(&mut self, event: &TouchpadEvent) {
+        let Some(finger) = self.fingers.get_mut(event.finger as usize) else {
+            warn!("Touchpad finger slot {} is out of range", event.finger);
+            return;
+        };
+        for axis in event.axis.iter() {
+            match axis.axis {
+                Axis::X => Self::update_axis(&mut finger.x, axis),
+                Axis::Y => Self::update_axis(&mut finger.y, axis),
+                Axis::Z => {
+                    let touching = axis.value > 0;
+                    finger.lifted |= finger.touching && !touching;
+                    finger.touching = touching;
+                }
+                _ => (),
+            }
+        }
+        self.pending = true;
+    }
+
+    /// Scale the coordinate of the touchpad device to the report
+    fn scale(value: u16, max: u16) -> u16 {
+        (value.min(max) as u32 * TOUCHPAD_LOGICAL_MAX as u32 / max as u32) as u16
+    }
+
+    fn touchpad_report(&mut self) -> TouchpadReport {
+        let mut report = TouchpadReport {
+            // Scan time is in 100us units, it's fine to wrap around
+            scan_time: (Instant::now().as_micros() / 100) as u16,
+            ..Default::default()
+        };
+        let mut count = 0;
+        for (id, finger) in self.fingers.iter_mut().enumerate() {
+            if finger.touching || finger.lifted {
+                let tip_switch = if finger.touching {
+                    CONTACT_TIP_SWITCH
+                } else {
+                    0
+                };
+                report.contacts[count] = TouchpadContact {
+                    flags: CONTACT_CONFIDENCE | tip_switch,
+                    contact_id: id as u8,
+                    x: Self::scale(finger.x, self.max_x),
+                    y: Self::scale(finger.y, self.max_y),
+                };
+                count += 1;
+                finger.lifted = false;
+            }
+        }
+        report.contact_count = count as u8;
+        report
+    }
+
+    /// Convert the motion of the first finger to mouse motion
+    fn pointing_event(&mut self) -> Option<Event> {
+        let finger = self.fingers[0];
+        let position = finger.touching.then_some((finger.x, finger.y));
+        let last_position = core::mem::replace(&mut self.last_position, position);
+        let ((x, y), (last_x, last_y)) = (position?, last_position?);
+        let axis = |axis, value: i32| AxisEvent {
+            typ: AxisValType::Rel,
+            axis,
+            value: value.clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+        };
+        Some(Event::Pointing([
+            axis(Axis::X, x as i32 - last_x as i32),
+            axis(Axis::Y, y as i32 - last_y as i32),
+        ]))
+    }
+}
+
+impl InputProcessor for TouchpadProcessor {
+    fn process(&mut self, event: Event) -> ProcessResult {
+        match event {
+            Event::Touchpad(e) => {
+                self.update_finger(&e);
+                ProcessResult::Stop
+            }
+            Event::Eos if self.pending => {
+                self.pending = false;
+                if INPUT_MODE.load(Ordering::Acquire) != INPUT_MODE_TOUCHPAD {
+                    self.fingers.iter_mut().for_each(|f| f.lifted = false);
+                    return match self.pointing_event() {
+                        Some(event) => ProcessResult::Continue(event),
+                        None => ProcessResult::Stop,
+                    };
+                }
+                self.last_position = None;
+                let report = self.touchpad_report();
+                if FUNCTION_SWITCH.load(Ordering::Acquire) & SURFACE_SWITCH != 0
+                    && KEYBOARD_REPORT_CHANNEL
+                        .try_send(KeyboardReportMessage::TouchpadReport(report))
+                        .is_err()
+                {
+                    warn!("Report channel is full, touchpad report is dropped");
+                }
+                ProcessResult::Stop
+            }
+            e => ProcessResult::Continue(e),
+        }
+    }
+}
@@ -1,32 +1,64 @@
+use crate::audio::{send_audio_event, AudioEvent};
+use crate::backlight::{send_backlight_event, BacklightEvent};
+use crate::bootloader::jump_to_bootloader;
 use crate::config::BehaviorConfig;
-use crate::event::{Event, KeyEvent};
+use crate::event::{Event, KeyEvent, RotaryEncoderEvent};
+use crate::haptic::{send_haptic_event, HapticEvent};
+use crate::hooks::KeyboardHooks;
+use crate::host_layout::{HostLayout, KeyStroke};
+#[cfg(feature = "joystick")]
+use crate::input_device::joystick::{update_joystick_report, JoystickProcessor};
+use crate::light::update_indicator_state;
+#[cfg(feature = "midi")]
+use crate::midi::MidiProcessor;
+#[cfg(feature = "midi")]
+use crate::sequencer::{SequencerAction, SEQUENCER_CHANNEL};
+#[cfg(feature = "steno")]
+use crate::steno::STENO_CHANNEL;
+#[cfg(feature = "joystick")]
+use crate::usb::descriptor::JoystickReport;
+#[cfg(feature = "touchpad")]
+use crate::usb::descriptor::{TouchpadReport, TouchpadReportType, TOUCHPAD_REPORT_SIZE};
 use crate::CONNECTION_STATE;
 use crate::{
     action::{Action, KeyAction},
     hid::{ConnectionType, HidWriterWrapper},
-    keyboard_macro::{MacroOperation, NUM_MACRO},
+    input_device::{pointing::PointingProcessor, InputProcessor, ProcessResult},
+    keyboard_macro::{MacroOperation, MacroPlayer, NUM_MACRO},
     keycode::{KeyCode, ModifierCombination},
     keymap::KeyMap,
-    usb::descriptor::{CompositeReport, CompositeReportType, ViaReport},
+    reboot_keyboard,
+    rgb::{
+        matrix::{send_rgb_matrix_key_event, update_rgb_matrix_layer},
+        underglow::{send_rgb_light_event, RgbLightEvent},
+    },
+    storage::{FlashOperationMessage, FLASH_CHANNEL},
+    usb::descriptor::{
+        CompositeReport, CompositeReportType, ViaReport, MOUSE_REPORT_SIZE, RESOLUTION_MULTIPLIER,
+    },
     KEYBOARD_STATE,
 };
 use core::cell::RefCell;
-use embassy_futures::{select::select, yield_now};
+use embassy_futures::{
+    select::{select, select3, Either, Either3},
+    yield_now,
+};
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
     channel::{Channel, Receiver, Sender},
 };
-use embassy_time::{Instant, Timer};
+use embassy_time::{Duration, Instant, Timer};
 use heapless::{FnvIndexMap, Vec};
 use usbd_hid::descriptor::KeyboardReport;
 
 pub const EVENT_CHANNEL_SIZE: usize = 32;
-pub static KEY_EVENT_CHANNEL: Channel<CriticalSectionRawMutex, KeyEvent, EVENT_CHANNEL_SIZE> =
-    Channel::new();
-
+/// Events of all input devices, which are processed by the input processors and the keyboard in order
 pub static EVENT_CHANNEL: Channel<CriticalSectionRawMutex, Event, EVENT_CHANNEL_SIZE> =
     Channel::new();
 
+/// Key events re-sent by the keyboard itself, which skip the input processors
+static KEY_REPEAT_CHANNEL: Channel<CriticalSectionRawMutex, KeyEvent, 8> = Channel::new();
+
 pub const REPORT_CHANNEL_SIZE: usize = 32;
 pub(crate) static KEYBOARD_REPORT_CHANNEL: Channel<
     CriticalSectionRawMutex,
@@ -34,6 +66,16 @@ pub(crate) static KEYBOARD_REPORT_CHANNEL: Channel<
     REPORT_CHANNEL_SIZE,
 > = Channel::new();
 
+/// Inputs which are waited by the keyboard
+enum KeyboardInput {
+    /// An event from [`EVENT_CHANNEL`]
+    Event(Event),
+    /// A key event re-sent by the keyboard itself
+    KeyRepeat(KeyEvent),
+    /// The next step of the running macro is due
+    MacroStep,
+}
+
 /// State machine for one shot keys
 #[derive(Default)]
 enum OneShotState<T> {
@@ -64,13 +106,27 @@ pub enum KeyboardReportMessage {
     KeyboardReport(KeyboardReport),
     /// Other types of keyboard reports: mouse + media(consumer) + system control
     CompositeReport(CompositeReport, CompositeReportType),
+    /// Gamepad report: buttons + axes
+    #[cfg(feature = "joystick")]
+    JoystickReport(JoystickReport),
+    /// Precision touchpad report, it's only sent via USB
+    #[cfg(feature = "touchpad")]
+    TouchpadReport(TouchpadReport),
 }
 
 /// This task processes all keyboard reports and send them to the host
-pub(crate) async fn communication_task<'a, W: HidWriterWrapper, W2: HidWriterWrapper>(
+pub(crate) async fn communication_task<
+    'a,
+    W: HidWriterWrapper,
+    W2: HidWriterWrapper,
+    #[cfg(feature = "joystick")] W3: HidWriterWrapper,
+    #[cfg(feature = "touchpad")] W4: HidWriterWrapper,
+>(
     receiver: &Receiver<'a, CriticalSectionRawMutex, KeyboardReportMessage, REPORT_CHANNEL_SIZE>,
     keybooard_hid_writer: &mut W,
     other_hid_writer: &mut W2,
+    #[cfg(feature = "joystick")] joystick_hid_writer: &mut W3,
+    #[cfg(feature = "touchpad")] touchpad_hid_writer: &mut W4,
 ) {
     // This delay is necessary otherwise this task will stuck at the first send when the USB is suspended
     Timer::after_secs(2).await;
@@ -88,8 +144,37 @@ pub(crate) async fn communication_task<'a, W: HidWriterWrapper, W2: HidWriterWra
                 KeyboardReportMessage::CompositeReport(report, report_type) => {
                     write_other_report_to_host(report, report_type, other_hid_writer).await;
                 }
+                #[cfg(feature = "joystick")]
+                KeyboardReportMessage::JoystickReport(report) => {
+                    match joystick_hid_writer.write_serialize(&report).await {
+                        Ok(()) => {}
+                        Err(e) => error!("Send joystick report error: {:?}", e),
+                    };
+                }
+                #[cfg(feature = "touchpad")]
+                KeyboardReportMessage::TouchpadReport(report) => {
+                    write_touchpad_report_to_host(report, touchpad_hid_writer).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "touchpad")]
+async fn write_touchpad_report_to_host<W: HidWriterWrapper>(
+    report: TouchpadReport,
+    touchpad_hid_writer: &mut W,
+) {
+    let mut buf: [u8; TOUCHPAD_REPORT_SIZE + 1] = [0; TOUCHPAD_REPORT_SIZE + 1];
+    // Prepend report id
+    buf[0] = TouchpadReportType::Touchpad as u8;
+    match ssmarshal::serialize(&mut buf[1..], &report) {
+        Ok(s) => {
+            if let Err(e) = touchpad_hid_writer.write(&buf[0..s + 1]).await {
+                error!("Send touchpad report error: {:?}", e);
             }
         }
+        Err(_) => error!("Serialize touchpad report error"),
     }
 }
 
@@ -98,7 +183,7 @@ pub(crate) async fn write_other_report_to_host<W: HidWriterWrapper>(
     report_type: CompositeReportType,
     other_hid_writer: &mut W,
 ) {
-    let mut buf: [u8; 9] = [0; 9];
+    let mut buf: [u8; MOUSE_REPORT_SIZE + 1] = [0; MOUSE_REPORT_SIZE + 1];
     // Prepend report id
     buf[0] = report_type as u8;
     match report.serialize(&mut buf[1..], report_type) {
@@ -116,6 +201,28 @@ pub(crate) async fn write_other_report_to_host<W: HidWriterWrapper>(
     }
 }
 
+/// Add a pressed usage to the usage array of a report, or remove a released one.
+///
+/// Empty slots of the array are 0. Returns `false` if there's no empty slot for the pressed usage.
+fn update_usage_ids<T: Copy + Default + PartialEq>(
+    usage_ids: &mut [T],
+    usage_id: T,
+    pressed: bool,
+) -> bool {
+    if pressed {
+        if usage_ids.contains(&usage_id) {
+            return true;
+        }
+        match usage_ids.iter_mut().find(|u| **u == T::default()) {
+            Some(u) => *u = usage_id,
+            None => return false,
+        }
+    } else if let Some(u) = usage_ids.iter_mut().find(|u| **u == usage_id) {
+        *u = T::default();
+    }
+    true
+}
+
 pub(crate) struct Keyboard<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize> {
     /// Keymap
     pub(crate) keymap: &'a RefCell<KeyMap<'a, ROW, COL, NUM_LAYER>>,
@@ -163,9 +270,35 @@ pub(crate) struct Keyboard<'a, const ROW: usize, const COL: usize, const NUM_LAY
     /// The key is mouse keycode, the value is the last action and its timestamp.
     last_mouse_tick: FnvIndexMap<KeyCode, (bool, Instant), 4>,
 
-    /// The current distance of mouse key moving
-    mouse_key_move_delta: i8,
-    mouse_wheel_move_delta: i8,
+    /// The current distance of mouse key moving, wheel distance is in high-resolution units
+    mouse_key_move_delta: i16,
+    mouse_wheel_move_delta: i16,
+
+    /// MIDI keycodes processor
+    #[cfg(feature = "midi")]
+    midi: MidiProcessor,
+
+    /// User hooks
+    hooks: Option<&'static mut dyn KeyboardHooks>,
+
+    /// Input processors which process events before the keyboard
+    processor: Option<&'static mut dyn InputProcessor>,
+
+    /// Processor of joystick axes
+    #[cfg(feature = "joystick")]
+    joystick: JoystickProcessor,
+
+    /// Processor of pointing devices and high-resolution scrolling
+    pointing: PointingProcessor,
+
+    /// Highest active layer, used to detect layer changes
+    active_layer: u8,
+
+    /// The pressed key which should be held to confirm, such as `Bootloader`, and the timestamp of the press
+    hold_to_confirm: Option<(KeyCode, Instant)>,
+
+    /// Player of keyboard macros
+    macro_player: MacroPlayer,
 }
 
 impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
@@ -175,6 +308,8 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
         keymap: &'a RefCell<KeyMap<'a, ROW, COL, NUM_LAYER>>,
         sender: &'a Sender<'a, CriticalSectionRawMutex, KeyboardReportMessage, REPORT_CHANNEL_SIZE>,
         behavior: BehaviorConfig,
+        hooks: Option<&'static mut dyn KeyboardHooks>,
+        processor: Option<&'static mut dyn InputProcessor>,
     ) -> Self {
         Keyboard {
             keymap,
@@ -208,13 +343,33 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
             },
             last_mouse_tick: FnvIndexMap::new(),
             mouse_key_move_delta: 8,
-            mouse_wheel_move_delta: 1,
+            mouse_wheel_move_delta: RESOLUTION_MULTIPLIER,
+            #[cfg(feature = "midi")]
+            midi: MidiProcessor::new(),
+            hooks,
+            processor,
+            #[cfg(feature = "joystick")]
+            joystick: JoystickProcessor::new(),
+            pointing: PointingProcessor::new(),
+            active_layer: 0,
+            hold_to_confirm: None,
+            macro_player: MacroPlayer::new(),
+        }
+    }
+
+    /// Send a report to the communication task, user hooks can modify the report before it's sent
+    async fn send_report(&mut self, mut report: KeyboardReportMessage) {
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_report(&mut report);
         }
+        self.sender.send(report).await;
     }
 
     pub(crate) async fn send_keyboard_report(&mut self) {
-        self.sender
-            .send(KeyboardReportMessage::KeyboardReport(self.report))
+        // Keys pressed by the running macro are sent together with physical keys
+        let mut report = self.report;
+        self.macro_player.merge_report(&mut report);
+        self.send_report(KeyboardReportMessage::KeyboardReport(report))
             .await;
         // Yield once after sending the report to channel
         yield_now().await;
@@ -222,47 +377,55 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
 
     /// Send system control report if needed
     pub(crate) async fn send_system_control_report(&mut self) {
-        self.sender
-            .send(KeyboardReportMessage::CompositeReport(
-                self.other_report,
-                CompositeReportType::System,
-            ))
-            .await;
-        self.other_report.system_usage_id = 0;
+        self.send_report(KeyboardReportMessage::CompositeReport(
+            self.other_report,
+            CompositeReportType::System,
+        ))
+        .await;
         yield_now().await;
     }
 
     /// Send media report if needed
     pub(crate) async fn send_media_report(&mut self) {
-        self.sender
-            .send(KeyboardReportMessage::CompositeReport(
-                self.other_report,
-                CompositeReportType::Media,
-            ))
-            .await;
-        self.other_report.media_usage_id = 0;
+        self.send_report(KeyboardReportMessage::CompositeReport(
+            self.other_report,
+            CompositeReportType::Media,
+        ))
+        .await;
         yield_now().await;
     }
 
     /// Send mouse report if needed
     pub(crate) async fn send_mouse_report(&mut self) {
-        // Prevent mouse report flooding, set maximum mouse report rate to 50 HZ
-        self.sender
-            .send(KeyboardReportMessage::CompositeReport(
-                self.other_report,
-                CompositeReportType::Mouse,
-            ))
+        self.send_pointing_report(self.other_report).await;
+    }
+
+    /// Send a mouse report which might have motions of pointing devices
+    async fn send_pointing_report(&mut self, mut report: CompositeReport) {
+        self.pointing.scale_scroll(&mut report);
+        self.send_report(KeyboardReportMessage::CompositeReport(
+            report,
+            CompositeReportType::Mouse,
+        ))
+        .await;
+        yield_now().await;
+    }
+
+    /// Send joystick report
+    #[cfg(feature = "joystick")]
+    pub(crate) async fn send_joystick_report(&mut self, report: JoystickReport) {
+        self.send_report(KeyboardReportMessage::JoystickReport(report))
             .await;
         yield_now().await;
     }
 
-    /// Main keyboard task, it receives input devices result, processes keys.
+    /// Main keyboard task, it receives events of input devices, processes keys.
     /// The report is sent to communication task via `KEYBOARD_REPORT_CHANNEL`, and finally sent to the host
-    /// TODO: make keyboard an `InputProcessor`
     pub(crate) async fn run(&mut self) {
         KEYBOARD_STATE.store(true, core::sync::atomic::Ordering::Release);
+        send_audio_event(AudioEvent::Startup);
         loop {
-            let key_event = KEY_EVENT_CHANNEL.receive().await;
+            let key_event = self.next_key_event().await;
 
             // Process the key change
             self.process_key_change(key_event).await;
@@ -277,6 +440,122 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 let e = self.unprocessed_events.remove(0);
                 self.process_key_change(e).await;
             }
+
+            // Publish the active layer and one-shot state after all events are processed
+            self.publish_state();
+        }
+    }
+
+    /// Publish the active layer and one-shot state to hooks and lights
+    fn publish_state(&mut self) {
+        let layer = self.keymap.borrow().get_activated_layer();
+        if layer != self.active_layer {
+            self.active_layer = layer;
+            if let Some(hooks) = self.hooks.as_mut() {
+                hooks.on_layer_change(layer);
+            }
+        }
+        update_rgb_matrix_layer(layer);
+        let one_shot_modifier = self.osm_state.value().is_some();
+        update_indicator_state(|s| {
+            s.layer = layer;
+            s.one_shot_modifier = one_shot_modifier;
+        });
+    }
+
+    /// Receive the next key event from the event pipeline.
+    ///
+    /// Events from [`EVENT_CHANNEL`] are processed by the input processors first.
+    /// Other events which reach the keyboard are processed immediately.
+    /// The running macro is played while waiting for events.
+    async fn next_key_event(&mut self) -> KeyEvent {
+        loop {
+            let input = self.next_input().await;
+            if let Some(key_event) = self.process_input(input).await {
+                return key_event;
+            }
+        }
+    }
+
+    /// Receive the next key event before `deadline`, returns `None` if the deadline is reached.
+    ///
+    /// Only receiving is cancelled by the deadline, so a macro step or an event which has been received
+    /// is always processed completely.
+    async fn next_key_event_before(&mut self, deadline: Instant) -> Option<KeyEvent> {
+        loop {
+            let input = match select(Timer::at(deadline), self.next_input()).await {
+                Either::First(_) => return None,
+                Either::Second(input) => input,
+            };
+            if let Some(key_event) = self.process_input(input).await {
+                return Some(key_event);
+            }
+        }
+    }
+
+    /// Wait for the next input of the keyboard, it's cancel-safe
+    async fn next_input(&self) -> KeyboardInput {
+        match select3(
+            EVENT_CHANNEL.receive(),
+            KEY_REPEAT_CHANNEL.receive(),
+            self.macro_player.wait_next_step(),
+        )
+        .await
+        {
+            Either3::First(event) => KeyboardInput::Event(event),
+            Either3::Second(key_event) => KeyboardInput::KeyRepeat(key_event),
+            Either3::Third(_) => KeyboardInput::MacroStep,
+        }
+    }
+
+    /// Process an input of the keyboard, returns the key event which should be processed by the keymap
+    async fn process_input(&mut self, input: KeyboardInput) -> Option<KeyEvent> {
+        let event = match input {
+            KeyboardInput::Event(event) => event,
+            KeyboardInput::KeyRepeat(key_event) => return Some(key_event),
+            KeyboardInput::MacroStep => {
+                self.step_macro().await;
+                self.publish_state();
+                return None;
+            }
+        };
+        let event = match self.processor.as_mut() {
+            Some(processor) => match processor.process(event) {
+                ProcessResult::Continue(event) => event,
+                ProcessResult::Stop => return None,
+            },
+            None => event,
+        };
+        match event {
+            Event::Key(key_event) => {
+                send_rgb_matrix_key_event(key_event);
+                Some(key_event)
+            }
+            event => {
+                self.process_event(event).await;
+                None
+            }
+        }
+    }
+
+    /// Process events other than key events
+    async fn process_event(&mut self, event: Event) {
+        match event {
+            #[cfg(feature = "joystick")]
+            Event::Joystick(_) | Event::AxisEventStream(_) | Event::Eos => {
+                if let Some(report) = self.joystick.process(&event) {
+                    self.send_joystick_report(report).await;
+                }
+            }
+            Event::Pointing(_) => {
+                if let Some(report) = self.pointing.process(&event, &self.other_report) {
+                    self.send_pointing_report(report).await;
+                }
+            }
+            Event::RotaryEncoder(RotaryEncoderEvent { id, direction }) => {
+                debug!("Encoder {} - {:?}", id, direction);
+            }
+            _ => debug!("Unsupported event is ignored"),
         }
     }
 
@@ -285,13 +564,18 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
         // Matrix should process key pressed event first, record the timestamp of key changes
         if key_event.pressed {
             self.timer[key_event.col as usize][key_event.row as usize] = Some(Instant::now());
+            send_audio_event(AudioEvent::KeyPress);
+            send_haptic_event(HapticEvent::KeyPress);
         }
 
         // Process key
-        let action = self
+        let mut action = self
             .keymap
             .borrow_mut()
             .get_action_with_layer_cache(key_event);
+        if let Some(hooks) = self.hooks.as_mut() {
+            action = hooks.on_key_event(key_event, action);
+        }
         match action {
             KeyAction::No | KeyAction::Transparent => (),
             KeyAction::Single(a) => self.process_key_action_normal(a, key_event).await,
@@ -370,10 +654,27 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
     async fn process_key_action_normal(&mut self, action: Action, key_event: KeyEvent) {
         match action {
             Action::Key(key) => {
-                self.process_action_keycode(key, key_event).await;
+                // Macros are processed here rather than in `process_action_keycode`,
+                // because actions in a macro are processed by `process_action_keycode` as well
+                if key.is_macro() || key == KeyCode::MacroCancel {
+                    self.process_action_macro(key, key_event).await;
+                } else {
+                    self.process_action_keycode(key, key_event).await;
+                }
                 self.update_osm(key_event).await;
                 self.update_osl(key_event);
             }
+            Action::Modifier(modifier) => {
+                self.process_action_modifier(modifier, key_event).await;
+                self.update_osl(key_event);
+            }
+            _ => self.process_action_layer(action, key_event),
+        }
+    }
+
+    /// Process layer actions: `LayerOn`, `LayerOff`, `LayerToggle`, `LayerToggleOnly` and `DefaultLayer`.
+    fn process_action_layer(&mut self, action: Action, key_event: KeyEvent) {
+        match action {
             Action::LayerOn(layer_num) => self.process_action_layer_switch(layer_num, key_event),
             Action::LayerOff(layer_num) => {
                 // Turn off a layer temporarily when the key is pressed
@@ -386,6 +687,8 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 // Toggle a layer when the key is release
                 if !key_event.pressed {
                     self.keymap.borrow_mut().toggle_layer(layer_num);
+                    send_audio_event(AudioEvent::LayerChange);
+                    send_haptic_event(HapticEvent::LayerChange);
                 }
             }
             Action::LayerToggleOnly(layer_num) => {
@@ -400,20 +703,40 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                     }
                     // Activate the target layer
                     self.keymap.borrow_mut().activate_layer(layer_num);
+                    send_audio_event(AudioEvent::LayerChange);
+                    send_haptic_event(HapticEvent::LayerChange);
                 }
             }
             Action::DefaultLayer(layer_num) => {
-                // Set the default layer
-                self.keymap.borrow_mut().set_default_layer(layer_num);
-            }
-            Action::Modifier(modifier) => {
-                let (keycodes, n) = modifier.to_modifier_keycodes();
-                for kc in keycodes.iter().take(n) {
-                    self.process_action_keycode(*kc, key_event).await;
+                // Set the default layer when the key is pressed, so that it's saved and notified only once
+                if key_event.pressed {
+                    if self.keymap.borrow().get_default_layer() != layer_num {
+                        // Save the default layer, so that it's kept after reboot
+                        if FLASH_CHANNEL
+                            .try_send(FlashOperationMessage::DefaultLayer(layer_num))
+                            .is_err()
+                        {
+                            warn!("Flash channel is full, default layer isn't saved");
+                        }
+                    }
+                    self.keymap.borrow_mut().set_default_layer(layer_num);
+                    send_audio_event(AudioEvent::LayerChange);
+                    send_haptic_event(HapticEvent::LayerChange);
                 }
-
-                self.update_osl(key_event);
             }
+            Action::Key(_) | Action::Modifier(_) => (),
+        }
+    }
+
+    /// Process modifier combination action, by processing the keycode of each modifier.
+    async fn process_action_modifier(
+        &mut self,
+        modifier: ModifierCombination,
+        key_event: KeyEvent,
+    ) {
+        let (keycodes, n) = modifier.to_modifier_keycodes();
+        for kc in keycodes.iter().take(n) {
+            self.process_action_keycode(*kc, key_event).await;
         }
     }
 
@@ -524,15 +847,14 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
             // Press
             self.timer[col][row] = Some(Instant::now());
 
-            let hold_timeout =
-                embassy_time::Timer::after_millis(self.behavior.tap_hold.hold_timeout.as_millis());
-            match select(hold_timeout, KEY_EVENT_CHANNEL.receive()).await {
-                embassy_futures::select::Either::First(_) => {
+            let hold_deadline = Instant::now() + self.behavior.tap_hold.hold_timeout;
+            match self.next_key_event_before(hold_deadline).await {
+                None => {
                     // Timeout, trigger hold
                     debug!("Hold timeout, got HOLD: {:?}, {:?}", hold_action, key_event);
                     self.process_key_action_normal(hold_action, key_event).await;
                 }
-                embassy_futures::select::Either::Second(e) => {
+                Some(e) => {
                     if e.row == key_event.row && e.col == key_event.col {
                         // If it's same key event and releasing within `hold_timeout`, trigger tap
                         if !e.pressed {
@@ -555,7 +877,7 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
 
                         // Wait for key release, record all pressed keys during this
                         loop {
-                            let next_key_event = KEY_EVENT_CHANNEL.receive().await;
+                            let next_key_event = self.next_key_event().await;
                             self.unprocessed_events.push(next_key_event).ok();
                             if !next_key_event.pressed {
                                 break;
@@ -591,31 +913,25 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                     "HOLD releasing: {:?}, {}, wait for `post_wait_time` for new releases",
                     hold_action, key_event.pressed
                 );
-                let wait_release = async {
-                    loop {
-                        let next_key_event = KEY_EVENT_CHANNEL.receive().await;
-                        if !next_key_event.pressed {
-                            self.unprocessed_events.push(next_key_event).ok();
-                        } else {
-                            break next_key_event;
+                let wait_deadline = Instant::now() + self.behavior.tap_hold.post_wait_time;
+                loop {
+                    match self.next_key_event_before(wait_deadline).await {
+                        None => {
+                            // Wait timeout, release the hold key finally
+                            self.process_key_action_normal(hold_action, key_event).await;
+                            break;
+                        }
+                        Some(next_release) if !next_release.pressed => {
+                            self.unprocessed_events.push(next_release).ok();
+                        }
+                        Some(next_press) => {
+                            // Next press event comes, add hold release to unprocessed list first, then add next press
+                            self.unprocessed_events.push(key_event).ok();
+                            self.unprocessed_events.push(next_press).ok();
+                            break;
                         }
                     }
-                };
-
-                let wait_timeout = embassy_time::Timer::after_millis(
-                    self.behavior.tap_hold.post_wait_time.as_millis(),
-                );
-                match select(wait_timeout, wait_release).await {
-                    embassy_futures::select::Either::First(_) => {
-                        // Wait timeout, release the hold key finally
-                        self.process_key_action_normal(hold_action, key_event).await;
-                    }
-                    embassy_futures::select::Either::Second(next_press) => {
-                        // Next press event comes, add hold release to unprocessed list first, then add next press
-                        self.unprocessed_events.push(key_event).ok();
-                        self.unprocessed_events.push(next_press).ok();
-                    }
-                };
+                }
                 // Clear timer
                 self.timer[col][row] = None;
             } else {
@@ -657,15 +973,15 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 OneShotState::Initial(m) | OneShotState::Single(m) => {
                     self.osm_state = OneShotState::Single(m);
 
-                    let timeout = embassy_time::Timer::after(self.behavior.one_shot.timeout);
-                    match select(timeout, KEY_EVENT_CHANNEL.receive()).await {
-                        embassy_futures::select::Either::First(_) => {
+                    let deadline = Instant::now() + self.behavior.one_shot.timeout;
+                    match self.next_key_event_before(deadline).await {
+                        None => {
                             // Timeout, release modifier
                             self.process_key_action_normal(Action::Modifier(modifier), key_event)
                                 .await;
                             self.osm_state = OneShotState::None;
                         }
-                        embassy_futures::select::Either::Second(e) => {
+                        Some(e) => {
                             // New event, send it to queue
                             if self.unprocessed_events.push(e).is_err() {
                                 warn!("unprocessed event queue is full, dropping event");
@@ -708,14 +1024,14 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 OneShotState::Initial(l) | OneShotState::Single(l) => {
                     self.osl_state = OneShotState::Single(l);
 
-                    let timeout = embassy_time::Timer::after(self.behavior.one_shot.timeout);
-                    match select(timeout, KEY_EVENT_CHANNEL.receive()).await {
-                        embassy_futures::select::Either::First(_) => {
+                    let deadline = Instant::now() + self.behavior.one_shot.timeout;
+                    match self.next_key_event_before(deadline).await {
+                        None => {
                             // Timeout, deactivate layer
                             self.keymap.borrow_mut().deactivate_layer(layer_num);
                             self.osl_state = OneShotState::None;
                         }
-                        embassy_futures::select::Either::Second(e) => {
+                        Some(e) => {
                             // New event, send it to queue
                             if self.unprocessed_events.push(e).is_err() {
                                 warn!("unprocessed event queue is full, dropping event");
@@ -734,12 +1050,87 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
 
     // Process a single keycode, typically a basic key or a modifier key.
     async fn process_action_keycode(&mut self, key: KeyCode, key_event: KeyEvent) {
-        if key.is_consumer() {
+        // Custom keycodes are processed by user hooks first
+        if key.is_kb() || key.is_user() {
+            if let Some(hooks) = self.hooks.as_mut() {
+                if hooks.process_custom_keycode(key, key_event) {
+                    return;
+                }
+            }
+        }
+
+        if key == KeyCode::Bootloader {
+            if self.check_hold_to_confirm(key, key_event, self.behavior.bootloader_hold_time) {
+                jump_to_bootloader();
+            }
+        } else if key == KeyCode::Reboot {
+            if self.check_hold_to_confirm(key, key_event, self.behavior.reset_hold_time) {
+                reboot_keyboard();
+            }
+        } else if key == KeyCode::ClearStorage {
+            if self.check_hold_to_confirm(key, key_event, self.behavior.reset_hold_time) {
+                // The keyboard is rebooted after the storage is cleared
+                warn!("Clearing storage..");
+                FLASH_CHANNEL.send(FlashOperationMessage::Reset).await;
+            }
+        } else if key.is_host_layout() {
+            if key_event.pressed {
+                if let Some(host_layout) = HostLayout::from_keycode(key) {
+                    self.set_host_layout(host_layout);
+                }
+            }
+        } else if key.is_consumer() {
             self.process_action_consumer_control(key, key_event).await;
         } else if key.is_system() {
             self.process_action_system_control(key, key_event).await;
         } else if key.is_mouse_key() {
             self.process_action_mouse(key, key_event).await;
+        } else if key.is_joystick() {
+            #[cfg(feature = "joystick")]
+            self.process_action_joystick(key, key_event).await;
+            #[cfg(not(feature = "joystick"))]
+            warn!("Joystick keycode {:?} requires `joystick` feature", key);
+        } else if key.is_midi() {
+            #[cfg(feature = "midi")]
+            self.midi.process(key, key_event);
+            #[cfg(not(feature = "midi"))]
+            warn!("MIDI keycode {:?} requires `midi` feature", key);
+        } else if key.is_sequencer() {
+            #[cfg(feature = "midi")]
+            if key_event.pressed {
+                if let Some(action) = SequencerAction::from_keycode(key) {
+                    // Don't block the keyboard if the sequencer isn't running
+                    if SEQUENCER_CHANNEL.try_send(action).is_err() {
+                        warn!("Sequencer channel is full, drop action: {:?}", action);
+                    }
+                }
+            }
+            #[cfg(not(feature = "midi"))]
+            warn!("Sequencer keycode {:?} requires `midi` feature", key);
+        } else if key.is_audio() {
+            if key_event.pressed {
+                send_audio_event(AudioEvent::Key(key));
+            }
+        } else if key.is_backlight() {
+            if key_event.pressed {
+                send_backlight_event(BacklightEvent::Key(key));
+            }
+        } else if key.is_rgb() {
+            if key_event.pressed {
+                send_rgb_light_event(RgbLightEvent::Key(key));
+            }
+        } else if key.is_haptic() {
+            if key_event.pressed {
+                send_haptic_event(HapticEvent::Key(key));
+            }
+        } else if key.is_steno() {
+            // Don't block the keyboard if the steno task isn't running
+            #[cfg(feature = "steno")]
+            if STENO_CHANNEL.try_send((key, key_event.pressed)).is_err() {
+                warn!("Steno channel is full, drop key: {:?}", key);
+            }
+            #[cfg(not(feature = "steno"))]
+            warn!("Steno keycode {:?} requires `steno` feature", key);
         } else if key.is_user() {
             #[cfg(feature = "_nrf_ble")]
             use crate::ble::nrf::profile::{BleProfileAction, BLE_PROFILE_CHANNEL};
@@ -783,13 +1174,59 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
             }
             self.send_keyboard_report().await;
         } else if key.is_macro() {
-            // Process macro
-            self.process_action_macro(key, key_event).await;
+            // Macros are processed in `process_key_action_normal`, reaching here means it's a macro in a macro
+            warn!("Nested macro is not supported: {:?}", key);
         } else {
             warn!("Unsupported key: {:?}", key);
         }
     }
 
+    /// Select the keyboard layout of the host, which is saved so that it's kept after reboot
+    fn set_host_layout(&mut self, host_layout: HostLayout) {
+        if self.keymap.borrow().get_host_layout() == Some(host_layout) {
+            return;
+        }
+        info!("Switch to host layout: {:?}", host_layout);
+        self.keymap.borrow_mut().set_host_layout(host_layout);
+        if FLASH_CHANNEL
+            .try_send(FlashOperationMessage::HostLayout(host_layout))
+            .is_err()
+        {
+            warn!("Flash channel is full, host layout isn't saved");
+        }
+    }
+
+    /// Check whether a key which needs hold-to-confirm is triggered, such as `Bootloader` and `Reboot`.
+    ///
+    /// If `hold_time` is 0, the key is triggered when pressed.
+    /// Otherwise it's triggered when the key is released after held for `hold_time`.
+    fn check_hold_to_confirm(
+        &mut self,
+        key: KeyCode,
+        key_event: KeyEvent,
+        hold_time: Duration,
+    ) -> bool {
+        if key_event.pressed {
+            if hold_time.as_ticks() == 0 {
+                return true;
+            }
+            self.hold_to_confirm = Some((key, Instant::now()));
+        } else if let Some((pressed_key, pressed)) = self.hold_to_confirm {
+            if pressed_key == key {
+                self.hold_to_confirm = None;
+                if pressed.elapsed() >= hold_time {
+                    return true;
+                }
+                info!(
+                    "{:?} is released too early, hold it for {}ms",
+                    key,
+                    hold_time.as_millis()
+                );
+            }
+        }
+        false
+    }
+
     /// Process layer switch action.
     fn process_action_layer_switch(&mut self, layer_num: u8, key_event: KeyEvent) {
         // Change layer state only when the key's state is changed
@@ -803,11 +1240,18 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
     /// Process consumer control action. Consumer control keys are keys in hid consumer page, such as media keys.
     async fn process_action_consumer_control(&mut self, key: KeyCode, key_event: KeyEvent) {
         if key.is_consumer() {
-            self.other_report.media_usage_id = if key_event.pressed {
-                key.as_consumer_control_usage_id() as u16
-            } else {
-                0
-            };
+            let usage_id = key.as_consumer_control_usage_id() as u16;
+            if usage_id == 0 {
+                return;
+            }
+            // Pressed consumer keys are kept in the report until they're released
+            if !update_usage_ids(
+                &mut self.other_report.media_usage_ids,
+                usage_id,
+                key_event.pressed,
+            ) {
+                warn!("Too many consumer keys are pressed, ignore {:?}", key);
+            }
 
             self.send_media_report().await;
         }
@@ -816,15 +1260,20 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
     /// Process system control action. System control keys are keys in system page, such as power key.
     async fn process_action_system_control(&mut self, key: KeyCode, key_event: KeyEvent) {
         if key.is_system() {
-            if key_event.pressed {
-                if let Some(system_key) = key.as_system_control_usage_id() {
-                    self.other_report.system_usage_id = system_key as u8;
-                    self.send_system_control_report().await;
-                }
-            } else {
-                self.other_report.system_usage_id = 0;
-                self.send_system_control_report().await;
+            let usage_id = match key.as_system_control_usage_id() {
+                Some(system_key) => system_key as u8,
+                None => return,
+            };
+            // Pressed system keys are kept in the report until they're released
+            if !update_usage_ids(
+                &mut self.other_report.system_usage_ids,
+                usage_id,
+                key_event.pressed,
+            ) {
+                warn!("Too many system keys are pressed, ignore {:?}", key);
             }
+
+            self.send_system_control_report().await;
         }
     }
 
@@ -916,21 +1365,45 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 error!("The buffer for last moust tick is full");
             }
 
-            // Send the key event back to channel again, to keep processing the mouse key until release
+            // Send the key event back to channel again, to keep processing the mouse key until release.
+            // Mouse keys are repeated every 20ms, which prevents mouse report flooding: the maximum mouse report rate is 50 HZ
             if key_event.pressed {
                 // FIXME: The ideal approach is to spawn another task and send the event after 20ms.
                 // But it requires embassy-executor, which is not available for esp-idf-svc.
                 // So now we just block for 20ms for mouse keys.
                 // In the future, we're going to use esp-hal once it have good support for BLE
                 embassy_time::Timer::after_millis(20).await;
-                KEY_EVENT_CHANNEL.try_send(key_event).ok();
+                KEY_REPEAT_CHANNEL.try_send(key_event).ok();
             }
         }
     }
 
+    /// Process joystick button action. `JoystickButton0`~`JoystickButton31` are mapped to gamepad buttons 1~32.
+    #[cfg(feature = "joystick")]
+    async fn process_action_joystick(&mut self, key: KeyCode, key_event: KeyEvent) {
+        if key.is_joystick() {
+            let button = 1 << (key as u16 - KeyCode::JoystickButton0 as u16);
+            let report = update_joystick_report(|r| {
+                if key_event.pressed {
+                    r.buttons |= button;
+                } else {
+                    r.buttons &= !button;
+                }
+            });
+            self.send_joystick_report(report).await;
+        }
+    }
+
     async fn process_action_macro(&mut self, key: KeyCode, key_event: KeyEvent) {
-        // Execute the macro only when releasing the key
-        if !!key_event.pressed {
+        if key == KeyCode::MacroCancel {
+            if key_event.pressed {
+                self.stop_macro().await;
+            }
+            return;
+        }
+
+        // Start the macro only when releasing the key
+        if key_event.pressed {
             return;
         }
 
@@ -940,64 +1413,197 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 error!("Macro idx invalid: {}", macro_idx);
                 return;
             }
-            // Read macro operations untill the end of the macro
-            let macro_idx = self.keymap.borrow().get_macro_start(macro_idx);
-            if let Some(macro_start_idx) = macro_idx {
-                let mut offset = 0;
-                loop {
-                    // First, get the next macro operation
-                    let (operation, new_offset) = self
-                        .keymap
-                        .borrow()
-                        .get_next_macro_operation(macro_start_idx, offset);
-                    // Execute the operation
-                    match operation {
-                        MacroOperation::Press(k) => {
-                            self.register_key(k, key_event);
-                        }
-                        MacroOperation::Release(k) => {
-                            self.unregister_key(k, key_event);
-                        }
-                        MacroOperation::Tap(k) => {
-                            self.register_key(k, key_event);
-                            self.send_keyboard_report().await;
-                            embassy_time::Timer::after_millis(2).await;
-                            self.unregister_key(k, key_event);
-                        }
-                        MacroOperation::Text(k, is_cap) => {
-                            if is_cap {
-                                // If it's a capital letter, send shift first
-                                self.register_modifier(KeyCode::LShift.as_modifier_bit());
-                                self.send_keyboard_report().await;
-                            }
-                            self.register_keycode(k, key_event);
-                            self.send_keyboard_report().await;
+            // Only one macro is played at a time, stop the running one first
+            let running = self.macro_player.running_macro();
+            self.stop_macro().await;
+            // Triggering the running macro again cancels it
+            if running == Some(macro_idx) {
+                info!("Macro {} is cancelled", macro_idx);
+                return;
+            }
+            let macro_start_idx = self.keymap.borrow().get_macro_start(macro_idx);
+            if let Some(macro_start_idx) = macro_start_idx {
+                // The macro is played in `next_key_event`
+                self.macro_player
+                    .start(macro_idx, macro_start_idx, key_event);
+            } else {
+                error!("Macro not found");
+            }
+        }
+    }
 
-                            self.unregister_keycode(k, key_event);
-                            if is_cap {
-                                self.send_keyboard_report().await;
-                                self.unregister_modifier(KeyCode::LShift.as_modifier_bit());
-                            }
-                        }
-                        MacroOperation::Delay(t) => {
-                            embassy_time::Timer::after_millis(t as u64).await;
-                        }
-                        MacroOperation::End => {
+    /// Stop the running macro, release all keys pressed by it and deactivate layers held by it
+    async fn stop_macro(&mut self) {
+        if let Some((tapped, held, key_event)) = self.macro_player.stop() {
+            let release = KeyEvent {
+                pressed: false,
+                ..key_event
+            };
+            let tapped_action = match tapped {
+                Some(KeyAction::Single(a)) | Some(KeyAction::WithModifier(a, _)) => Some(a),
+                _ => None,
+            };
+            if let Some(action) = tapped {
+                self.process_macro_key_action(action, release).await;
+            }
+            // Media keys, mouse keys and layer actions which are pressed but not released by the macro,
+            // the tapped action is already released
+            for action in held.into_iter().filter(|a| Some(*a) != tapped_action) {
+                self.process_macro_action(action, release).await;
+            }
+            self.send_keyboard_report().await;
+        }
+    }
+
+    /// Execute the next operation of the running macro
+    async fn step_macro(&mut self) {
+        let (macro_start_idx, offset, key_event) = match self.macro_player.position() {
+            Some(p) => p,
+            None => return,
+        };
+        // Actions in the macro are pressed and released at the position of the macro key
+        let press = KeyEvent {
+            pressed: true,
+            ..key_event
+        };
+        let release = KeyEvent {
+            pressed: false,
+            ..key_event
+        };
+
+        // Release the action tapped in the last step first
+        if let Some(action) = self.macro_player.take_tapped() {
+            self.macro_player.advance(offset, 0);
+            self.process_macro_key_action(action, release).await;
+            self.send_keyboard_report().await;
+            return;
+        }
+
+        let (operation, new_offset) = self
+            .keymap
+            .borrow()
+            .get_next_macro_operation(macro_start_idx, offset);
+        // Move to the next operation before executing the current one,
+        // so that the operation isn't executed twice if the step is interrupted
+        let delay_ms = match operation {
+            // The tapped action is released in the next step
+            MacroOperation::Tap(a) => {
+                self.macro_player.set_tapped(a);
+                2
+            }
+            MacroOperation::Delay(t) => t as u64,
+            _ => 0,
+        };
+        self.macro_player.advance(new_offset, delay_ms);
+
+        match operation {
+            MacroOperation::Press(a) => {
+                self.process_macro_key_action(a, press).await;
+            }
+            MacroOperation::Release(a) => {
+                self.process_macro_key_action(a, release).await;
+            }
+            MacroOperation::Tap(a) => {
+                self.process_macro_key_action(a, press).await;
+            }
+            MacroOperation::Text(c) => {
+                // The layout selected by the user is preferred over the default one
+                let host_layout = self
+                    .keymap
+                    .borrow()
+                    .get_host_layout()
+                    .unwrap_or(self.behavior.host_layout);
+                match host_layout.to_key_strokes(c) {
+                    Some((stroke, next)) => {
+                        self.type_key_stroke(stroke).await;
+                        // The key following a dead key
+                        if let Some(next) = next {
                             self.send_keyboard_report().await;
-                            break;
+                            self.type_key_stroke(next).await;
                         }
-                    };
+                    }
+                    None => warn!("Character {} isn't in host layout {:?}", c, host_layout),
+                }
+            }
+            MacroOperation::Delay(_) => (),
+            MacroOperation::End => {
+                // Keys and layers which are still held by the macro are released at the end of the macro
+                self.stop_macro().await;
+                return;
+            }
+        };
+
+        // Send the item in the macro sequence
+        self.send_keyboard_report().await;
+    }
+
+    /// Type a key stroke of a character in the macro.
+    ///
+    /// The key and modifiers are released in the next report, which should be sent by the caller.
+    async fn type_key_stroke(&mut self, stroke: KeyStroke) {
+        let has_modifier = stroke.modifier.into_bits() != 0;
+        if has_modifier {
+            // Send modifiers first
+            self.macro_player.register_modifier(stroke.modifier);
+            self.send_keyboard_report().await;
+        }
+        self.macro_player.register_key(stroke.key);
+        self.send_keyboard_report().await;
 
-                    // Send the item in the macro sequence
+        self.macro_player.unregister_key(stroke.key);
+        if has_modifier {
+            self.send_keyboard_report().await;
+            self.macro_player.unregister_modifier(stroke.modifier);
+        }
+    }
+
+    /// Process a key action in the macro sequence.
+    ///
+    /// Only `Single` and `WithModifier` are supported, other actions such as tap-hold make no sense in a macro.
+    async fn process_macro_key_action(&mut self, key_action: KeyAction, key_event: KeyEvent) {
+        match key_action {
+            KeyAction::Single(action) => self.process_macro_action(action, key_event).await,
+            KeyAction::WithModifier(action, modifier) => {
+                if key_event.pressed {
+                    self.macro_player.register_modifier(modifier);
+                    self.send_keyboard_report().await;
+                    self.process_macro_action(action, key_event).await;
+                } else {
+                    self.process_macro_action(action, key_event).await;
                     self.send_keyboard_report().await;
+                    self.macro_player.unregister_modifier(modifier);
+                }
+            }
+            _ => warn!("Unsupported action in macro: {:?}", key_action),
+        }
+    }
 
-                    offset = new_offset;
-                    if offset > self.keymap.borrow().macro_cache.len() {
-                        break;
+    /// Process an action in the macro sequence.
+    async fn process_macro_action(&mut self, action: Action, key_event: KeyEvent) {
+        match action {
+            Action::Key(key) => {
+                if key.is_basic() && !key.is_consumer() && !key.is_system() && !key.is_mouse_key() {
+                    // Basic keys and modifiers are sent in the macro's own report, without triggering one-shot keys
+                    if key_event.pressed {
+                        self.macro_player.register_key(key);
+                    } else {
+                        self.macro_player.unregister_key(key);
                     }
+                } else {
+                    self.macro_player.hold_action(action, key_event.pressed);
+                    self.process_action_keycode(key, key_event).await;
                 }
-            } else {
-                error!("Macro not found");
+            }
+            Action::Modifier(modifier) => {
+                if key_event.pressed {
+                    self.macro_player.register_modifier(modifier);
+                } else {
+                    self.macro_player.unregister_modifier(modifier);
+                }
+            }
+            _ => {
+                self.macro_player.hold_action(action, key_event.pressed);
+                self.process_action_layer(action, key_event)
             }
         }
     }
@@ -1080,3 +1686,42 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
         self.report.modifier &= !modifier_bit;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use embassy_futures::block_on;
+
+    use super::*;
+
+    #[test]
+    fn test_default_layer_is_set_on_press() {
+        block_on(async {
+            let mut action_map = [[[KeyAction::No; 1]; 1]; 2];
+            let keymap = RefCell::new(KeyMap::new(&mut action_map, None).await);
+            let sender = KEYBOARD_REPORT_CHANNEL.sender();
+            let mut keyboard =
+                Keyboard::new(&keymap, &sender, BehaviorConfig::default(), None, None);
+            let press = KeyEvent {
+                row: 0,
+                col: 0,
+                pressed: true,
+            };
+            let release = KeyEvent {
+                pressed: false,
+                ..press
+            };
+
+            keyboard
+                .process_key_action_normal(Action::DefaultLayer(1), press)
+                .await;
+            assert_eq!(1, keymap.borrow().get_default_layer());
+
+            // Releasing the key doesn't set the default layer again
+            keymap.borrow_mut().set_default_layer(0);
+            keyboard
+                .process_key_action_normal(Action::DefaultLayer(1), release)
+                .await;
+            assert_eq!(0, keymap.borrow().get_default_layer());
+        });
+    }
+}
@@ -1,16 +1,406 @@
-use crate::keycode::KeyCode;
+use embassy_time::{Duration, Instant, Timer};
+use heapless::Vec;
+use usbd_hid::descriptor::KeyboardReport;
 
-// Default macro space size
-pub(crate) const MACRO_SPACE_SIZE: usize = 256;
+use crate::{
+    action::{Action, KeyAction},
+    event::KeyEvent,
+    keycode::{KeyCode, ModifierCombination},
+    via::keycode_convert::to_via_key,
+};
 
-// Default number of keyboard macros
-pub(crate) const NUM_MACRO: usize = 8;
+// Macro space size `MACRO_SPACE_SIZE` and number of keyboard macros `NUM_MACRO`,
+// which are configured by `RMK_MACRO_SPACE_SIZE` and `RMK_NUM_MACRO` environment variables, default to 256 and 8
+include!(concat!(env!("OUT_DIR"), "/constants.rs"));
 
+/// Default macros of the keyboard, which are saved to the storage when the storage is initialized.
+///
+/// Macros are encoded in the same format as Vial, so they can be edited in Vial later.
+/// Use [`macro_seq!`](crate::macro_seq) to define macros in a `static`:
+///
+/// ```rust
+/// static MACROS: MacroSequence = macro_seq!([
+///     // Macro0: type "Hello" then press Enter
+///     [text("Hello"), tap(k!(Enter))],
+///     // Macro1: copy, wait for 100ms, then paste
+///     [press(k!(LCtrl)), tap(k!(C)), delay(100), tap(k!(V)), release(k!(LCtrl))],
+/// ]);
+/// ```
+pub struct MacroSequence {
+    buffer: [u8; MACRO_SPACE_SIZE],
+    /// Length of the encoded macros
+    len: usize,
+    /// Number of finished macros
+    count: usize,
+}
+
+impl Default for MacroSequence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MacroSequence {
+    /// Create an empty macro sequence
+    pub const fn new() -> Self {
+        Self {
+            buffer: [0; MACRO_SPACE_SIZE],
+            len: 0,
+            count: 0,
+        }
+    }
+
+    /// Type the text, characters are converted to key strokes with the host layout when the macro is played
+    pub const fn text(mut self, text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            // 0 and 1 are reserved for the end of macro and the prefix of operations
+            if bytes[i] < 2 {
+                panic!("Characters `\\0` and `\\x01` are not supported in macro text");
+            }
+            self = self.push(&[bytes[i]]);
+            i += 1;
+        }
+        self
+    }
+
+    /// Press and release the action
+    pub const fn tap(self, action: KeyAction) -> Self {
+        self.push_action(1, 5, action)
+    }
+
+    /// Press the action
+    pub const fn press(self, action: KeyAction) -> Self {
+        self.push_action(2, 6, action)
+    }
+
+    /// Release the action
+    pub const fn release(self, action: KeyAction) -> Self {
+        self.push_action(3, 7, action)
+    }
+
+    /// Wait for `ms` milliseconds, max: 65024
+    pub const fn delay(self, ms: u16) -> Self {
+        if ms > 65024 {
+            panic!("Macro delay should be less than 65024ms");
+        }
+        // Each byte is increased by 1 to avoid 0 in the macro sequence
+        self.push(&[1, 4, (ms % 255) as u8 + 1, (ms / 255) as u8 + 1])
+    }
+
+    /// Finish the current macro, following steps belong to the next macro
+    pub const fn end(mut self) -> Self {
+        if self.count >= NUM_MACRO {
+            panic!("Too many macros, increase `RMK_NUM_MACRO`");
+        }
+        self = self.push(&[0]);
+        self.count += 1;
+        self
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8; MACRO_SPACE_SIZE] {
+        &self.buffer
+    }
+
+    /// Encode the action as a basic keycode if possible, otherwise as a 16-bit Via keycode of Vial's macro extension
+    const fn push_action(self, basic_code: u8, ext_code: u8, action: KeyAction) -> Self {
+        let keycode = match action {
+            KeyAction::Single(Action::Key(k))
+                if k as u16 >= KeyCode::Macro0 as u16 && k as u16 <= KeyCode::Macro31 as u16 =>
+            {
+                panic!("Nested macro is not supported")
+            }
+            // The keycode is decoded by `from_via_keycode` when the macro is played
+            KeyAction::Single(Action::Key(k)) => match to_via_key(k) {
+                Some(keycode) => keycode,
+                None => panic!("The keycode can't be used in macro"),
+            },
+            KeyAction::Single(Action::LayerToggleOnly(l)) => 0x5200 | l as u16,
+            KeyAction::Single(Action::LayerOn(l)) => 0x5220 | l as u16,
+            KeyAction::Single(Action::DefaultLayer(l)) => 0x5240 | l as u16,
+            KeyAction::Single(Action::LayerToggle(l)) => 0x5260 | l as u16,
+            KeyAction::WithModifier(Action::Key(k), m) if (k as u16) < 0x100 => {
+                ((m.into_bits() as u16) << 8) | k as u16
+            }
+            _ => panic!("Unsupported action in macro"),
+        };
+        if keycode == 0 {
+            panic!("`No` can't be used in macro");
+        }
+        if keycode <= 0xFF {
+            self.push(&[1, basic_code, keycode as u8])
+        } else if keycode & 0xFF == 0 {
+            // Avoid 0 in the macro sequence, `0xXX00` is saved as `0xFFXX`
+            self.push(&[1, ext_code, (keycode >> 8) as u8, 0xFF])
+        } else {
+            self.push(&[1, ext_code, keycode as u8, (keycode >> 8) as u8])
+        }
+    }
+
+    const fn push(mut self, bytes: &[u8]) -> Self {
+        if self.len + bytes.len() > MACRO_SPACE_SIZE {
+            panic!("Macros are too long, increase `RMK_MACRO_SPACE_SIZE`");
+        }
+        let mut i = 0;
+        while i < bytes.len() {
+            self.buffer[self.len] = bytes[i];
+            self.len += 1;
+            i += 1;
+        }
+        self
+    }
+}
+
+/// Define default macros as a [`MacroSequence`].
+///
+/// Each macro is a list of steps: `text("...")`, `tap(action)`, `press(action)`, `release(action)` and `delay(ms)`.
+/// Actions are created by action macros, such as `k!(A)` or `mo!(1)`.
+/// Macros are numbered in order, the first macro is triggered by `Macro0`.
+#[macro_export]
+macro_rules! macro_seq {
+    ([$([$($step: ident ($($arg: expr),*)),* $(,)?]),* $(,)?]) => {
+        $crate::keyboard_macro::MacroSequence::new()
+            $($(.$step($($arg),*))*.end())*
+    };
+}
+
+/// Operations in a macro sequence.
+///
+/// Press, release and tap accept any `KeyAction` which can be converted from a Via keycode,
+/// so macros can send media keys, mouse keys, switch layers, etc.
 pub(crate) enum MacroOperation {
-    Press(KeyCode),
-    Release(KeyCode),
-    Tap(KeyCode),
-    Text(KeyCode, bool),
+    Press(KeyAction),
+    Release(KeyAction),
+    Tap(KeyAction),
+    /// Type a character with the host layout
+    Text(char),
     Delay(u16),
     End,
 }
+
+/// Max number of non-basic actions which can be held by a macro at the same time
+const MAX_MACRO_HELD_ACTIONS: usize = 8;
+
+/// The running macro
+struct RunningMacro {
+    /// Index of the macro
+    index: u8,
+    /// Start of the macro in the macro buffer
+    start: usize,
+    /// Offset of the next operation from the start of the macro
+    offset: usize,
+    /// The key event which triggers the macro, actions in the macro are processed at its position
+    key_event: KeyEvent,
+    /// The tapped action which should be released in the next step
+    tapped: Option<KeyAction>,
+    /// Actions which are pressed but not released by the macro, except basic keys and modifiers in its report,
+    /// such as media keys, mouse keys and layer actions
+    held: Vec<Action, MAX_MACRO_HELD_ACTIONS>,
+    /// Time of the next step
+    next_step: Instant,
+}
+
+/// Macro player, which plays a macro step by step between key events.
+///
+/// So that physical keys keep working during the playback, and the macro can be cancelled at any time.
+/// Keys and modifiers pressed by the macro are saved in its own report,
+/// which is merged with the keyboard's report when sending.
+pub(crate) struct MacroPlayer {
+    running: Option<RunningMacro>,
+    /// Keys and modifiers pressed by the macro
+    report: KeyboardReport,
+}
+
+impl MacroPlayer {
+    pub(crate) fn new() -> Self {
+        Self {
+            running: None,
+            report: KeyboardReport {
+                modifier: 0,
+                reserved: 0,
+                leds: 0,
+                keycodes: [0; 6],
+            },
+        }
+    }
+
+    /// Index of the running macro
+    pub(crate) fn running_macro(&self) -> Option<u8> {
+        self.running.as_ref().map(|m| m.index)
+    }
+
+    /// Start to play the macro from the start, the running macro is replaced
+    pub(crate) fn start(&mut self, index: u8, start: usize, key_event: KeyEvent) {
+        self.running = Some(RunningMacro {
+            index,
+            start,
+            offset: 0,
+            key_event,
+            tapped: None,
+            held: Vec::new(),
+            next_step: Instant::now(),
+        });
+    }
+
+    /// Stop the running macro and release all keys in its report.
+    ///
+    /// Returns the tapped action which isn't released yet, other actions which are still held by the macro,
+    /// and the key event of the macro key. The caller should release them.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn stop(
+        &mut self,
+    ) -> Option<(
+        Option<KeyAction>,
+        Vec<Action, MAX_MACRO_HELD_ACTIONS>,
+        KeyEvent,
+    )> {
+        self.report.modifier = 0;
+        self.report.keycodes = [0; 6];
+        self.running.take().map(|m| (m.tapped, m.held, m.key_event))
+    }
+
+    /// Wait for the next step of the running macro, pending forever if there's no running macro
+    pub(crate) async fn wait_next_step(&self) {
+        match self.running.as_ref() {
+            Some(m) => Timer::at(m.next_step).await,
+            None => core::future::pending().await,
+        }
+    }
+
+    /// Get the start of the running macro, the offset of the next operation and the key event of the macro key
+    pub(crate) fn position(&self) -> Option<(usize, usize, KeyEvent)> {
+        self.running
+            .as_ref()
+            .map(|m| (m.start, m.offset, m.key_event))
+    }
+
+    /// Move to the next operation at `offset`, which is executed after `delay_ms`
+    pub(crate) fn advance(&mut self, offset: usize, delay_ms: u64) {
+        if let Some(m) = self.running.as_mut() {
+            m.offset = offset;
+            m.next_step = Instant::now() + Duration::from_millis(delay_ms);
+        }
+    }
+
+    /// Set the tapped action, which is released in the next step
+    pub(crate) fn set_tapped(&mut self, action: KeyAction) {
+        if let Some(m) = self.running.as_mut() {
+            m.tapped = Some(action);
+        }
+    }
+
+    /// Take the tapped action which should be released now
+    pub(crate) fn take_tapped(&mut self) -> Option<KeyAction> {
+        self.running.as_mut().and_then(|m| m.tapped.take())
+    }
+
+    /// Record an action which is pressed or released by the running macro, so that it's released when the macro stops
+    pub(crate) fn hold_action(&mut self, action: Action, pressed: bool) {
+        let Some(m) = self.running.as_mut() else {
+            return;
+        };
+        if pressed {
+            if !m.held.contains(&action) && m.held.push(action).is_err() {
+                warn!("Too many actions are held by the macro: {:?}", action);
+            }
+        } else {
+            m.held.retain(|a| *a != action);
+        }
+    }
+
+    /// Press a basic key or modifier in the macro's report
+    pub(crate) fn register_key(&mut self, key: KeyCode) {
+        if key.is_modifier() {
+            self.report.modifier |= key.as_modifier_bit();
+        } else if !self.report.keycodes.contains(&(key as u8)) {
+            if let Some(k) = self.report.keycodes.iter_mut().find(|k| **k == 0) {
+                *k = key as u8;
+            }
+        }
+    }
+
+    /// Release a basic key or modifier in the macro's report
+    pub(crate) fn unregister_key(&mut self, key: KeyCode) {
+        if key.is_modifier() {
+            self.report.modifier &= !key.as_modifier_bit();
+        } else if let Some(k) = self.report.keycodes.iter_mut().find(|k| **k == key as u8) {
+            *k = 0;
+        }
+    }
+
+    /// Press modifiers in the macro's report
+    pub(crate) fn register_modifier(&mut self, modifier: ModifierCombination) {
+        self.report.modifier |= modifier.to_hid_modifier_bits();
+    }
+
+    /// Release modifiers in the macro's report
+    pub(crate) fn unregister_modifier(&mut self, modifier: ModifierCombination) {
+        self.report.modifier &= !modifier.to_hid_modifier_bits();
+    }
+
+    /// Merge keys and modifiers pressed by the macro into the keyboard's report
+    pub(crate) fn merge_report(&self, report: &mut KeyboardReport) {
+        report.modifier |= self.report.modifier;
+        for key in self.report.keycodes.iter().filter(|k| **k != 0) {
+            if report.keycodes.contains(key) {
+                continue;
+            }
+            if let Some(k) = report.keycodes.iter_mut().find(|k| **k == 0) {
+                *k = *key;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{k, mo, to, wm};
+
+    #[test]
+    fn test_macro_sequence_encoding() {
+        let macros = macro_seq!([
+            [text("Hi"), tap(k!(Enter))],
+            [
+                tap(wm!(
+                    C,
+                    ModifierCombination::new_from(false, false, false, false, true)
+                )),
+                delay(300),
+                press(k!(AudioMute)),
+                release(mo!(1)),
+            ],
+        ]);
+        assert_eq!(
+            &macros.as_bytes()[..28],
+            &[
+                // Macro0
+                b'H', b'i', 1, 1, 0x28, 0, //
+                // Macro1: LCtrl + C as 0x0106
+                1, 5, 0x06, 0x01, //
+                // 300ms = (46 - 1) + (2 - 1) * 255
+                1, 4, 46, 2, //
+                1, 2, 0xA8, //
+                // `MO(1)` is 0x5221
+                1, 7, 0x21, 0x52, 0, //
+                0, 0, 0, 0, 0, 0
+            ]
+        );
+
+        // `TO(0)` is 0x5200, keycode `0xXX00` is saved as `0xFFXX`
+        let macros = macro_seq!([[tap(to!(0))]]);
+        assert_eq!(&macros.as_bytes()[..5], &[1, 5, 0x52, 0xFF, 0]);
+
+        // `User1` is `QK_KB_1` in via
+        let macros = macro_seq!([[tap(k!(User1))]]);
+        assert_eq!(&macros.as_bytes()[..5], &[1, 5, 0x01, 0x7E, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The keycode can't be used in macro")]
+    fn test_macro_sequence_unsupported_keycode() {
+        // `BacklightOn` has no via keycode, it would be decoded as a modifier combination
+        let _ = macro_seq!([[tap(k!(BacklightOn))]]);
+    }
+}
@@ -599,7 +599,7 @@ pub enum KeyCode {
     MidiModulationSpeedUp = 0x28D,
     MidiPitchBendDown = 0x28E,
     MidiPitchBendUp = 0x28F,
-    // Sequencer keycodes, use 0x300 ~ 0x30F
+    // Sequencer keycodes, use 0x300 ~ 0x327
     SequencerOn = 0x300,
     SequencerOff = 0x301,
     SequencerToggle = 0x302,
@@ -609,6 +609,32 @@ pub enum KeyCode {
     SequencerResolutionUp = 0x306,
     SequencerStepsAll = 0x307,
     SequencerStepsClear = 0x308,
+    // Toggle a step of the sequencer for active tracks
+    SequencerStep0 = 0x310,
+    SequencerStep1 = 0x311,
+    SequencerStep2 = 0x312,
+    SequencerStep3 = 0x313,
+    SequencerStep4 = 0x314,
+    SequencerStep5 = 0x315,
+    SequencerStep6 = 0x316,
+    SequencerStep7 = 0x317,
+    SequencerStep8 = 0x318,
+    SequencerStep9 = 0x319,
+    SequencerStep10 = 0x31A,
+    SequencerStep11 = 0x31B,
+    SequencerStep12 = 0x31C,
+    SequencerStep13 = 0x31D,
+    SequencerStep14 = 0x31E,
+    SequencerStep15 = 0x31F,
+    // Select a track of the sequencer as the only active track, or deactivate all tracks if it's active
+    SequencerTrack0 = 0x320,
+    SequencerTrack1 = 0x321,
+    SequencerTrack2 = 0x322,
+    SequencerTrack3 = 0x323,
+    SequencerTrack4 = 0x324,
+    SequencerTrack5 = 0x325,
+    SequencerTrack6 = 0x326,
+    SequencerTrack7 = 0x327,
     // Joystick button keycodes, use 0x400 ~ 0x41F
     JoystickButton0 = 0x400,
     JoystickButton1 = 0x401,
@@ -691,7 +717,51 @@ pub enum KeyCode {
     MusicModeNext = 0x473,
     AudioVoiceNext = 0x474,
     AudioVoicePrevious = 0x475,
-    // Steno keycodes, use 0x4F0 ~ 0x4FF
+    AudioVolumeUp = 0x476,
+    AudioVolumeDown = 0x477,
+    // Steno keycodes, use 0x4C0 ~ 0x4FF
+    StenoFn = 0x4C0,
+    StenoN1 = 0x4C1,
+    StenoN2 = 0x4C2,
+    StenoN3 = 0x4C3,
+    StenoN4 = 0x4C4,
+    StenoN5 = 0x4C5,
+    StenoN6 = 0x4C6,
+    StenoS1 = 0x4C7,
+    StenoS2 = 0x4C8,
+    StenoTL = 0x4C9,
+    StenoKL = 0x4CA,
+    StenoPL = 0x4CB,
+    StenoWL = 0x4CC,
+    StenoHL = 0x4CD,
+    StenoRL = 0x4CE,
+    StenoA = 0x4CF,
+    StenoO = 0x4D0,
+    StenoStar1 = 0x4D1,
+    StenoStar2 = 0x4D2,
+    StenoRes1 = 0x4D3,
+    StenoRes2 = 0x4D4,
+    StenoPwr = 0x4D5,
+    StenoStar3 = 0x4D6,
+    StenoStar4 = 0x4D7,
+    StenoE = 0x4D8,
+    StenoU = 0x4D9,
+    StenoFR = 0x4DA,
+    StenoRR = 0x4DB,
+    StenoPR = 0x4DC,
+    StenoBR = 0x4DD,
+    StenoLR = 0x4DE,
+    StenoGR = 0x4DF,
+    StenoTR = 0x4E0,
+    StenoSR = 0x4E1,
+    StenoDR = 0x4E2,
+    StenoN7 = 0x4E3,
+    StenoN8 = 0x4E4,
+    StenoN9 = 0x4E5,
+    StenoNA = 0x4E6,
+    StenoNB = 0x4E7,
+    StenoNC = 0x4E8,
+    StenoZR = 0x4E9,
     StenoBolt = 0x4F0,
     StenoGemini = 0x4F1,
     StenoComb = 0x4F2,
@@ -729,6 +799,8 @@ pub enum KeyCode {
     Macro29 = 0x51D,
     Macro30 = 0x51E,
     Macro31 = 0x51F,
+    /// Stop the running macro
+    MacroCancel = 0x520,
     // Backlight and RGB keycodes, uses 0x600 ~ 0x6FF
     BacklightOn = 0x600,
     BacklightOff = 0x601,
@@ -763,7 +835,8 @@ pub enum KeyCode {
     Bootloader = 0x700,
     Reboot = 0x701,
     DebugToggle = 0x702,
-    ClearEeprom = 0x703,
+    /// Clear the storage and reboot, aka `QK_CLEAR_EEPROM` in Vial
+    ClearStorage = 0x703,
     Make = 0x704,
     AutoShiftDown = 0x710,
     AutoShiftUp = 0x711,
@@ -835,6 +908,12 @@ pub enum KeyCode {
     TriLayerUpper = 0x778,
     RepeatKey = 0x779,
     AltRepeatKey = 0x77A,
+    /// Select the keyboard layout of the host, which is used to type text in macros
+    HostLayoutUs = 0x780,
+    HostLayoutUk = 0x781,
+    HostLayoutGerman = 0x782,
+    HostLayoutFrench = 0x783,
+    HostLayoutDvorak = 0x784,
     // Kb keycodes, use 0x800 ~ 0x81F
     Kb0 = 0x800,
     Kb1 = 0x801,
@@ -904,6 +983,11 @@ pub enum KeyCode {
 }
 
 impl KeyCode {
+    /// Old name of [`KeyCode::ClearStorage`]
+    #[deprecated(note = "use `KeyCode::ClearStorage` instead")]
+    #[allow(non_upper_case_globals)]
+    pub const ClearEeprom: KeyCode = KeyCode::ClearStorage;
+
     /// Returns `true` if the keycode is basic keycode
     pub(crate) fn is_basic(self) -> bool {
         KeyCode::No <= self && self <= KeyCode::RGui
@@ -951,7 +1035,7 @@ impl KeyCode {
 
     /// Returns `true` if the keycode is a sequencer keycode
     pub(crate) fn is_sequencer(self) -> bool {
-        KeyCode::SequencerOn <= self && self <= KeyCode::SequencerStepsClear
+        KeyCode::SequencerOn <= self && self <= KeyCode::SequencerTrack7
     }
 
     /// Returns `true` if the keycode is a joystick keycode
@@ -967,12 +1051,22 @@ impl KeyCode {
     /// Returns `true` if the keycode is a audio keycode
     /// Note: Basic audio keycodes are not included
     pub(crate) fn is_audio(self) -> bool {
-        KeyCode::AudioOn <= self && self <= KeyCode::AudioVoicePrevious
+        KeyCode::AudioOn <= self && self <= KeyCode::AudioVolumeDown
+    }
+
+    /// Returns `true` if the keycode is a haptic keycode
+    pub(crate) fn is_haptic(self) -> bool {
+        KeyCode::HapticOn <= self && self <= KeyCode::HapticDwellDown
     }
 
     /// Returns `true` if the keycode is a steno keycode
     pub(crate) fn is_steno(self) -> bool {
-        KeyCode::StenoBolt <= self && self <= KeyCode::StenoCombMax
+        KeyCode::StenoFn <= self && self <= KeyCode::StenoCombMax
+    }
+
+    /// Returns `true` if the keycode is a steno key, which is a part of the steno chord
+    pub(crate) fn is_steno_key(self) -> bool {
+        KeyCode::StenoFn <= self && self <= KeyCode::StenoZR
     }
 
     /// Returns `true` if the keycode is a macro keycode
@@ -992,7 +1086,12 @@ impl KeyCode {
 
     /// Returns `true` if the keycode is defined by rmk to achieve special functionalities, such as reboot keyboard, goto bootloader, etc.
     pub(crate) fn is_rmk(self) -> bool {
-        KeyCode::Bootloader <= self && self <= KeyCode::AltRepeatKey
+        KeyCode::Bootloader <= self && self <= KeyCode::HostLayoutDvorak
+    }
+
+    /// Returns `true` if the keycode selects the keyboard layout of the host
+    pub(crate) fn is_host_layout(self) -> bool {
+        KeyCode::HostLayoutUs <= self && self <= KeyCode::HostLayoutDvorak
     }
 
     /// Returns `true` if the keycode is a kb keycode
@@ -1057,111 +1156,4 @@ impl KeyCode {
             _ => None,
         }
     }
-
-    /// Convert a ascii chat to keycode
-    pub(crate) fn from_ascii(ascii: u8) -> (Self, bool) {
-        match ascii {
-            b'0' => (KeyCode::Kc0, false),
-            b'1' => (KeyCode::Kc1, false),
-            b'2' => (KeyCode::Kc2, false),
-            b'3' => (KeyCode::Kc3, false),
-            b'4' => (KeyCode::Kc4, false),
-            b'5' => (KeyCode::Kc5, false),
-            b'6' => (KeyCode::Kc6, false),
-            b'7' => (KeyCode::Kc7, false),
-            b'8' => (KeyCode::Kc8, false),
-            b'9' => (KeyCode::Kc9, false),
-            b'a' => (KeyCode::A, false),
-            b'b' => (KeyCode::B, false),
-            b'c' => (KeyCode::C, false),
-            b'd' => (KeyCode::D, false),
-            b'e' => (KeyCode::E, false),
-            b'f' => (KeyCode::F, false),
-            b'g' => (KeyCode::G, false),
-            b'h' => (KeyCode::H, false),
-            b'i' => (KeyCode::I, false),
-            b'j' => (KeyCode::J, false),
-            b'k' => (KeyCode::K, false),
-            b'l' => (KeyCode::L, false),
-            b'm' => (KeyCode::M, false),
-            b'n' => (KeyCode::N, false),
-            b'o' => (KeyCode::O, false),
-            b'p' => (KeyCode::P, false),
-            b'q' => (KeyCode::Q, false),
-            b'r' => (KeyCode::R, false),
-            b's' => (KeyCode::S, false),
-            b't' => (KeyCode::T, false),
-            b'u' => (KeyCode::U, false),
-            b'v' => (KeyCode::V, false),
-            b'w' => (KeyCode::W, false),
-            b'x' => (KeyCode::X, false),
-            b'y' => (KeyCode::Y, false),
-            b'z' => (KeyCode::Z, false),
-            b'A' => (KeyCode::A, true),
-            b'B' => (KeyCode::B, true),
-            b'C' => (KeyCode::C, true),
-            b'D' => (KeyCode::D, true),
-            b'E' => (KeyCode::E, true),
-            b'F' => (KeyCode::F, true),
-            b'G' => (KeyCode::G, true),
-            b'H' => (KeyCode::H, true),
-            b'I' => (KeyCode::I, true),
-            b'J' => (KeyCode::J, true),
-            b'K' => (KeyCode::K, true),
-            b'L' => (KeyCode::L, true),
-            b'M' => (KeyCode::M, true),
-            b'N' => (KeyCode::N, true),
-            b'O' => (KeyCode::O, true),
-            b'P' => (KeyCode::P, true),
-            b'Q' => (KeyCode::Q, true),
-            b'R' => (KeyCode::R, true),
-            b'S' => (KeyCode::S, true),
-            b'T' => (KeyCode::T, true),
-            b'U' => (KeyCode::U, true),
-            b'V' => (KeyCode::V, true),
-            b'W' => (KeyCode::W, true),
-            b'X' => (KeyCode::X, true),
-            b'Y' => (KeyCode::Y, true),
-            b'Z' => (KeyCode::Z, true),
-            b'!' => (KeyCode::Kc1, true),
-            b'@' => (KeyCode::Kc2, true),
-            b'#' => (KeyCode::Kc3, true),
-            b'$' => (KeyCode::Kc4, true),
-            b'%' => (KeyCode::Kc5, true),
-            b'^' => (KeyCode::Kc6, true),
-            b'&' => (KeyCode::Kc7, true),
-            b'*' => (KeyCode::Kc8, true),
-            b'(' => (KeyCode::Kc9, true),
-            b')' => (KeyCode::Kc0, true),
-            b'-' => (KeyCode::Minus, false),
-            b'_' => (KeyCode::Minus, true),
-            b'=' => (KeyCode::Equal, false),
-            b'+' => (KeyCode::Equal, true),
-            b'[' => (KeyCode::LeftBracket, false),
-            b']' => (KeyCode::RightBracket, false),
-            b'{' => (KeyCode::LeftBracket, true),
-            b'}' => (KeyCode::RightBracket, true),
-            b';' => (KeyCode::Semicolon, false),
-            b':' => (KeyCode::Semicolon, true),
-            b'\'' => (KeyCode::Quote, false),
-            b'"' => (KeyCode::Quote, true),
-            b'`' => (KeyCode::Grave, false),
-            b'~' => (KeyCode::Grave, true),
-            b'\\' => (KeyCode::Backslash, true),
-            b'|' => (KeyCode::Backslash, false),
-            b',' => (KeyCode::Comma, false),
-            b'<' => (KeyCode::Comma, true),
-            b'.' => (KeyCode::Dot, false),
-            b'>' => (KeyCode::Dot, true),
-            b'/' => (KeyCode::Slash, false),
-            b'?' => (KeyCode::Slash, false),
-            b' ' => (KeyCode::Space, false),
-            b'\n' => (KeyCode::Enter, false),
-            b'\t' => (KeyCode::Tab, false),
-            b'\x08' => (KeyCode::Backspace, false),
-            b'\x1B' => (KeyCode::Escape, false),
-            b'\x7F' => (KeyCode::Delete, false),
-            _ => (KeyCode::No, false),
-        }
-    }
 }
@@ -1,11 +1,14 @@
 use crate::{
-    action::KeyAction,
+    action::{Action, KeyAction},
     event::KeyEvent,
-    keyboard_macro::{MacroOperation, MACRO_SPACE_SIZE},
+    host_layout::HostLayout,
+    keyboard_macro::{MacroOperation, MacroSequence, MACRO_SPACE_SIZE},
     keycode::KeyCode,
     reboot_keyboard,
     storage::Storage,
+    via::keycode_convert::from_via_keycode,
 };
+use byteorder::{ByteOrder, LittleEndian};
 use embedded_storage_async::nor_flash::NorFlash;
 use num_enum::FromPrimitive;
 
@@ -24,6 +27,10 @@ pub(crate) struct KeyMap<'a, const ROW: usize, const COL: usize, const NUM_LAYER
     layer_state: [bool; NUM_LAYER],
     /// Default layer number, max: 32
     default_layer: u8,
+    /// Layout option of Via, saved in storage
+    layout_option: u32,
+    /// Host layout saved in storage, `None` if it's never changed by the user
+    host_layout: Option<HostLayout>,
     /// Layer cache
     layer_cache: [[u8; COL]; ROW],
     /// Macro cache
@@ -33,23 +40,44 @@ pub(crate) struct KeyMap<'a, const ROW: usize, const COL: usize, const NUM_LAYER
 impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
     KeyMap<'a, ROW, COL, NUM_LAYER>
 {
-    pub(crate) async fn new(action_map: &'a mut [[[KeyAction; COL]; ROW]; NUM_LAYER]) -> Self {
+    pub(crate) async fn new(
+        action_map: &'a mut [[[KeyAction; COL]; ROW]; NUM_LAYER],
+        default_macros: Option<&MacroSequence>,
+    ) -> Self {
         KeyMap {
             layers: action_map,
             layer_state: [false; NUM_LAYER],
             default_layer: 0,
+            layout_option: 0,
+            host_layout: None,
             layer_cache: [[0; COL]; ROW],
-            macro_cache: [0; MACRO_SPACE_SIZE],
+            macro_cache: default_macros.map_or([0; MACRO_SPACE_SIZE], |m| *m.as_bytes()),
         }
     }
 
     pub(crate) async fn new_from_storage<F: NorFlash>(
         action_map: &'a mut [[[KeyAction; COL]; ROW]; NUM_LAYER],
         storage: Option<&mut Storage<F, ROW, COL, NUM_LAYER>>,
+        default_macros: Option<&MacroSequence>,
     ) -> Self {
         // If the storage is initialized, read keymap from storage
-        let mut macro_cache = [0; MACRO_SPACE_SIZE];
+        // Default macros are used if there's no macro in the storage
+        let mut macro_cache = default_macros.map_or([0; MACRO_SPACE_SIZE], |m| *m.as_bytes());
+        let mut default_layer = 0;
+        let mut layout_option = 0;
+        let mut host_layout = None;
         if let Some(storage) = storage {
+            // Read default layer and layout option
+            if let Some((layer, option)) = storage.read_layout_config().await {
+                if (layer as usize) < NUM_LAYER {
+                    default_layer = layer;
+                } else {
+                    warn!("Saved default layer {} is out of range, use layer 0", layer);
+                }
+                layout_option = option;
+            }
+            host_layout = storage.read_host_layout().await;
+
             // Read keymap to `action_map`
             if storage.read_keymap(action_map).await.is_err() {
                 error!("Keymap reading aborted by an error, clearing the storage...");
@@ -78,7 +106,9 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
         KeyMap {
             layers: action_map,
             layer_state: [false; NUM_LAYER],
-            default_layer: 0,
+            default_layer,
+            layout_option,
+            host_layout,
             layer_cache: [[0; COL]; ROW],
             macro_cache,
         }
@@ -98,6 +128,26 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
         self.default_layer = layer_num;
     }
 
+    /// Get the layout option of Via
+    pub(crate) fn get_layout_option(&self) -> u32 {
+        self.layout_option
+    }
+
+    /// Set the layout option of Via
+    pub(crate) fn set_layout_option(&mut self, layout_option: u32) {
+        self.layout_option = layout_option;
+    }
+
+    /// Get the host layout saved in storage
+    pub(crate) fn get_host_layout(&self) -> Option<HostLayout> {
+        self.host_layout
+    }
+
+    /// Set the host layout selected by the user
+    pub(crate) fn set_host_layout(&mut self, host_layout: HostLayout) {
+        self.host_layout = Some(host_layout);
+    }
+
     /// Get the next macro operation starting from given index and offset
     /// Return current macro operation and the next operations's offset
     pub(crate) fn get_next_macro_operation(
@@ -115,7 +165,10 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 // SS_QMK_PREFIX + SS_TAP_CODE
                 if idx + 2 < self.macro_cache.len() {
                     let keycode = KeyCode::from_primitive(self.macro_cache[idx + 2] as u16);
-                    (MacroOperation::Tap(keycode), offset + 3)
+                    (
+                        MacroOperation::Tap(KeyAction::Single(Action::Key(keycode))),
+                        offset + 3,
+                    )
                 } else {
                     (MacroOperation::End, offset + 3)
                 }
@@ -124,7 +177,10 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 // SS_QMK_PREFIX + SS_DOWN_CODE
                 if idx + 2 < self.macro_cache.len() {
                     let keycode = KeyCode::from_primitive(self.macro_cache[idx + 2] as u16);
-                    (MacroOperation::Press(keycode), offset + 3)
+                    (
+                        MacroOperation::Press(KeyAction::Single(Action::Key(keycode))),
+                        offset + 3,
+                    )
                 } else {
                     (MacroOperation::End, offset + 3)
                 }
@@ -133,7 +189,10 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 // SS_QMK_PREFIX + SS_UP_CODE
                 if idx + 2 < self.macro_cache.len() {
                     let keycode = KeyCode::from_primitive(self.macro_cache[idx + 2] as u16);
-                    (MacroOperation::Release(keycode), offset + 3)
+                    (
+                        MacroOperation::Release(KeyAction::Single(Action::Key(keycode))),
+                        offset + 3,
+                    )
                 } else {
                     (MacroOperation::End, offset + 3)
                 }
@@ -148,14 +207,41 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                     (MacroOperation::End, offset + 4)
                 }
             }
-            (1, 5) | (1, 6) | (1, 7) => {
-                warn!("VIAL_MACRO_EXT is not supported");
-                (MacroOperation::Delay(0), offset + 4)
+            (1, code @ (5 | 6 | 7)) => {
+                // VIAL_MACRO_EXT_TAP, VIAL_MACRO_EXT_DOWN and VIAL_MACRO_EXT_UP, followed by a 16-bit keycode.
+                // It's used for all non-basic keycodes, such as media keys, mouse keys, layer keys, etc.
+                if idx + 3 < self.macro_cache.len() {
+                    let mut via_keycode =
+                        LittleEndian::read_u16(&self.macro_cache[idx + 2..idx + 4]);
+                    // Vial avoids 0 in the macro sequence, keycode `0xXX00` is saved as `0xFFXX`
+                    if via_keycode > 0xFF00 {
+                        via_keycode = (via_keycode & 0xFF) << 8;
+                    }
+                    let key_action = from_via_keycode(via_keycode);
+                    match code {
+                        5 => (MacroOperation::Tap(key_action), offset + 4),
+                        6 => (MacroOperation::Press(key_action), offset + 4),
+                        _ => (MacroOperation::Release(key_action), offset + 4),
+                    }
+                } else {
+                    (MacroOperation::End, offset + 4)
+                }
             }
             _ => {
-                // Current byte is the ascii code, convert it to keyboard keycode(with caps state)
-                let (keycode, is_caps) = KeyCode::from_ascii(self.macro_cache[idx]);
-                (MacroOperation::Text(keycode, is_caps), offset + 1)
+                // Current byte starts an UTF-8 encoded character, get its length from the first byte
+                let len = match self.macro_cache[idx] {
+                    0xF0..=0xFF => 4,
+                    0xE0..=0xEF => 3,
+                    0xC0..=0xDF => 2,
+                    _ => 1,
+                };
+                let end = (idx + len).min(self.macro_cache.len());
+                // Invalid characters are skipped when typing, since they can't be found in any host layout
+                let c = core::str::from_utf8(&self.macro_cache[idx..end])
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .unwrap_or(char::REPLACEMENT_CHARACTER);
+                (MacroOperation::Text(c), offset + end - idx)
             }
         }
     }
@@ -254,6 +340,16 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
         self.layer_cache[row][col] = layer_num;
     }
 
+    /// Get the highest activated layer, the default layer is always activated
+    pub(crate) fn get_activated_layer(&self) -> u8 {
+        for (layer_idx, &activated) in self.layer_state.iter().enumerate().rev() {
+            if activated || layer_idx as u8 == self.default_layer {
+                return layer_idx as u8;
+            }
+        }
+        self.default_layer
+    }
+
     /// Update given Tri Layer state
     pub(crate) fn update_tri_layer(&mut self, tri_layer: &[u8; 3]) {
         self.layer_state[tri_layer[2] as usize] =
@@ -51,6 +51,10 @@ use keyboard::{communication_task, Keyboard, KeyboardReportMessage, KEYBOARD_REP
 pub use keyboard::{EVENT_CHANNEL, EVENT_CHANNEL_SIZE, REPORT_CHANNEL_SIZE};
 use keymap::KeyMap;
 use matrix::{Matrix, MatrixTrait};
+#[cfg(feature = "midi")]
+use {embassy_futures::join::join, sequencer::Sequencer};
+#[cfg(feature = "steno")]
+use steno::Steno;
 pub use rmk_macro as macros;
 use usb::KeyboardUsbDevice;
 use via::process::VialService;
@@ -58,6 +62,9 @@ use via::process::VialService;
 use {embedded_storage_async::nor_flash::NorFlash as AsyncNorFlash, storage::Storage};
 
 pub mod action;
+pub mod audio;
+pub mod backlight;
+pub mod bootloader;
 #[cfg(feature = "_ble")]
 pub mod ble;
 pub mod config;
@@ -65,17 +72,28 @@ pub mod debounce;
 pub mod direct_pin;
 pub mod event;
 mod flash;
+pub mod haptic;
 mod hid;
+pub mod hooks;
+pub mod host_layout;
 pub mod input_device;
 pub mod keyboard;
-mod keyboard_macro;
+pub mod keyboard_macro;
 pub mod keycode;
 mod keymap;
 mod layout_macro;
 mod light;
 pub mod matrix;
+#[cfg(feature = "midi")]
+mod midi;
+pub mod raw_hid;
+pub mod rgb;
 #[cfg(feature = "split")]
 pub mod split;
+#[cfg(feature = "midi")]
+mod sequencer;
+#[cfg(feature = "steno")]
+mod steno;
 mod storage;
 mod usb;
 mod via;
@@ -290,12 +308,27 @@ pub async fn initialize_usb_keyboard_and_run<
     // For USB keyboard, the "external" storage means the storage initialized by the user.
     #[cfg(any(feature = "_nrf_ble", not(feature = "_no_external_storage")))]
     let (mut storage, keymap) = {
-        let mut s = Storage::new(flash, default_keymap, keyboard_config.storage_config).await;
-        let keymap = RefCell::new(KeyMap::new_from_storage(default_keymap, Some(&mut s)).await);
+        let mut s = Storage::new(
+            flash,
+            default_keymap,
+            keyboard_config.default_macros,
+            keyboard_config.storage_config,
+        )
+        .await;
+        let keymap = RefCell::new(
+            KeyMap::new_from_storage(default_keymap, Some(&mut s), keyboard_config.default_macros)
+                .await,
+        );
         (s, keymap)
     };
     #[cfg(all(not(feature = "_nrf_ble"), feature = "_no_external_storage"))]
-    let keymap = RefCell::new(KeyMap::<ROW, COL, NUM_LAYER>::new(default_keymap).await);
+    let keymap = RefCell::new(
+        KeyMap::<ROW, COL, NUM_LAYER>::new(default_keymap, keyboard_config.default_macros).await,
+    );
+
+    // The bootloader is used by both the keyboard and Via
+    bootloader::set_bootloader(keyboard_config.bootloader);
+    raw_hid::set_raw_hid_handler(keyboard_config.raw_hid_handler);
 
     let keyboard_report_sender = KEYBOARD_REPORT_CHANNEL.sender();
     let keyboard_report_receiver = KEYBOARD_REPORT_CHANNEL.receiver();
@@ -306,11 +339,17 @@ pub async fn initialize_usb_keyboard_and_run<
             &keymap,
             &keyboard_report_sender,
             keyboard_config.behavior_config,
+            keyboard_config.hooks,
+            keyboard_config.processor,
         ),
         KeyboardUsbDevice::new(usb_driver, keyboard_config.usb_config),
         VialService::new(&keymap, keyboard_config.vial_config),
         LightService::from_config(keyboard_config.light_config),
     );
+    #[cfg(feature = "midi")]
+    let mut sequencer = Sequencer::new(keyboard_config.sequencer_config);
+    #[cfg(feature = "steno")]
+    let mut steno = Steno::new(keyboard_config.steno_config);
 
     KEYBOARD_STATE.store(false, core::sync::atomic::Ordering::Release);
     // Run all tasks, if one of them fails, wait 1 second and then restart
@@ -322,6 +361,10 @@ pub async fn initialize_usb_keyboard_and_run<
         &mut storage,
         &mut light_service,
         &mut vial_service,
+        #[cfg(feature = "midi")]
+        &mut sequencer,
+        #[cfg(feature = "steno")]
+        &mut steno,
         &keyboard_report_receiver,
     )
     .await
@@ -350,6 +393,8 @@ pub(crate) async fn run_usb_keyboard<
     >,
     light_service: &mut LightService<Out>,
     vial_service: &mut VialService<'b, ROW, COL, NUM_LAYER>,
+    #[cfg(feature = "midi")] sequencer: &mut Sequencer,
+    #[cfg(feature = "steno")] steno: &mut Steno,
     keyboard_report_receiver: &Receiver<
         'b,
         CriticalSectionRawMutex,
@@ -366,6 +411,23 @@ pub(crate) async fn run_usb_keyboard<
             keyboard_report_receiver,
             &mut usb_device.keyboard_hid_writer,
             &mut usb_device.other_hid_writer,
+            #[cfg(feature = "joystick")]
+            &mut usb_device.joystick_hid_writer,
+            #[cfg(feature = "touchpad")]
+            &mut usb_device.touchpad_hid_writer,
+        );
+        #[cfg(feature = "midi")]
+        let communication_fut = select(
+            communication_fut,
+            join(midi::midi_task(&mut usb_device.midi), sequencer.run()),
+        );
+        #[cfg(feature = "steno")]
+        let communication_fut = select(
+            communication_fut,
+            steno.run(
+                &mut usb_device.steno_serial,
+                &mut usb_device.steno_hid_writer,
+            ),
         );
         let led_fut = led_hid_task(&mut usb_device.keyboard_hid_reader, light_service);
         let via_fut = vial_task(&mut usb_device.via_hid, vial_service);
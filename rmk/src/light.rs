@@ -1,29 +1,60 @@
-use crate::config::{LightConfig, LightPinConfig};
+use core::cell::Cell;
+
+use crate::audio::{send_audio_event, AudioEvent};
+use crate::config::{IndicatorCondition, LightConfig, LightPinConfig, MAX_INDICATORS};
 use crate::hid::HidReaderWrapper;
 use bitfield_struct::bitfield;
 use embassy_futures::select::select;
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+};
 use embedded_hal::digital::{Error, OutputPin, PinState};
+use heapless::Vec;
+
+/// Current keyboard state which indicator LEDs are bound to
+static INDICATOR_STATE: Mutex<CriticalSectionRawMutex, Cell<IndicatorState>> =
+    Mutex::new(Cell::new(IndicatorState::new()));
+
+/// Signaled with the new state when the indicator state is changed
+static INDICATOR_SIGNAL: Signal<CriticalSectionRawMutex, IndicatorState> = Signal::new();
 
-pub(crate) static LED_CHANNEL: Channel<CriticalSectionRawMutex, LedIndicator, 8> = Channel::new();
+/// Update the indicator state, indicator LEDs are refreshed if the state is changed
+pub(crate) fn update_indicator_state(f: impl FnOnce(&mut IndicatorState)) {
+    let (old, new) = INDICATOR_STATE.lock(|s| {
+        let old = s.get();
+        let mut new = old;
+        f(&mut new);
+        s.set(new);
+        (old, new)
+    });
+    if new != old {
+        INDICATOR_SIGNAL.signal(new);
+    }
+}
 
 /// LED control task
 pub(crate) async fn led_service_task<P: OutputPin>(light_service: &mut LightService<P>) {
+    if !light_service.enabled {
+        warn!(
+            "Led service is not enabled but led_service_task is started, this should not happen!"
+        );
+    }
+    // Apply the state which is changed before the task starts
+    let mut state = INDICATOR_STATE.lock(|s| s.get());
     loop {
-        let led_indicator = LED_CHANNEL.receive().await;
         if light_service.enabled {
-            if let Err(e) = light_service.set_leds(led_indicator) {
+            if let Err(e) = light_service.set_leds(&state) {
                 error!("Set led error {:?}", e.kind());
                 // If there's an error, wait for a while
                 embassy_time::Timer::after_millis(500).await;
             }
-        } else {
-            warn!("Led service is not enabled but led_service_task is started, this should not happen!");
         }
+        state = INDICATOR_SIGNAL.wait().await;
     }
 }
 
-/// Check led indicator and send the led status to LED channel
+/// Check led indicator and update the indicator state
 ///
 /// If there's an error, print a message and ignore error types
 pub(crate) async fn hid_read_led<R: HidReaderWrapper>(keyboard_hid_reader: &mut R) -> ! {
@@ -31,10 +62,11 @@ pub(crate) async fn hid_read_led<R: HidReaderWrapper>(keyboard_hid_reader: &mut
         let mut led_indicator_data = [0; 1];
         match keyboard_hid_reader.read(&mut led_indicator_data).await {
             Ok(_) => {
-                // Read led indicator data and send to LED channel
+                // Read led indicator data and update the indicator state
                 let indicator = LedIndicator::from_bits(led_indicator_data[0]);
                 debug!("Read keyboard state: {:?}", indicator);
-                LED_CHANNEL.send(indicator).await;
+                send_audio_event(AudioEvent::CapsLock(indicator.capslock()));
+                update_indicator_state(|s| s.leds = indicator);
             }
             Err(e) => {
                 error!("Read keyboard state error: {:?}", e);
@@ -49,9 +81,8 @@ pub(crate) async fn led_hid_task<R: HidReaderWrapper, Out: OutputPin>(
     light_service: &mut LightService<Out>,
 ) {
     if !light_service.enabled {
-        loop {
-            embassy_time::Timer::after_secs(u32::MAX as u64).await;
-        }
+        // The lock state is still read without lights, it's used by indicators of other services, such as the capslock sound
+        hid_read_led(keyboard_hid_reader).await;
     } else {
         select(
             hid_read_led(keyboard_hid_reader),
@@ -66,24 +97,75 @@ pub(crate) async fn led_hid_task<R: HidReaderWrapper, Out: OutputPin>(
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub(crate) struct LedIndicator {
     #[bits(1)]
-    numslock: bool,
+    pub(crate) numslock: bool,
     #[bits(1)]
-    capslock: bool,
+    pub(crate) capslock: bool,
     #[bits(1)]
-    scrolllock: bool,
+    pub(crate) scrolllock: bool,
     #[bits(1)]
-    compose: bool,
+    pub(crate) compose: bool,
     #[bits(1)]
-    kana: bool,
+    pub(crate) kana: bool,
     #[bits(1)]
-    shift: bool,
+    pub(crate) shift: bool,
     #[bits(2)]
     _reserved: u8,
 }
 
+/// Keyboard state which indicator LEDs can be bound to
+///
+/// The state is updated by the keyboard, connection and battery tasks via [`update_indicator_state`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct IndicatorState {
+    /// LED state reported by the host
+    pub(crate) leds: LedIndicator,
+    /// Highest active layer
+    pub(crate) layer: u8,
+    /// USB is configured by the host
+    pub(crate) usb_active: bool,
+    /// BLE is connected
+    pub(crate) ble_connected: bool,
+    /// Active BLE profile
+    pub(crate) ble_profile: u8,
+    pub(crate) battery_low: bool,
+    pub(crate) one_shot_modifier: bool,
+}
+
+impl IndicatorState {
+    const fn new() -> Self {
+        Self {
+            leds: LedIndicator::new(),
+            layer: 0,
+            usb_active: false,
+            ble_connected: false,
+            ble_profile: 0,
+            battery_low: false,
+            one_shot_modifier: false,
+        }
+    }
+
+    /// Check whether the condition is met in current state
+    fn is_met(&self, condition: IndicatorCondition) -> bool {
+        match condition {
+            IndicatorCondition::CapsLock => self.leds.capslock(),
+            IndicatorCondition::NumsLock => self.leds.numslock(),
+            IndicatorCondition::ScrollLock => self.leds.scrolllock(),
+            IndicatorCondition::Layer(layer) => self.layer == layer,
+            IndicatorCondition::UsbActive => self.usb_active,
+            IndicatorCondition::BleProfileConnected(profile) => {
+                self.ble_connected && self.ble_profile == profile
+            }
+            IndicatorCondition::BatteryLow => self.battery_low,
+            IndicatorCondition::OneShotModifier => self.one_shot_modifier,
+        }
+    }
+}
+
 /// A single LED
 ///
-/// In general, a single LED can be used for capslock/numslock, or in a LED matrix.
+/// In general, a single LED can be used for capslock/numslock.
+/// It's driven by a GPIO, so it can only be turned on or off, use [`crate::backlight::BacklightService`] for PWM dimmed LEDs.
 struct SingleLED<P: OutputPin> {
     /// On/Off state
     state: bool,
@@ -93,12 +175,6 @@ struct SingleLED<P: OutputPin> {
 
     /// GPIO for controlling the LED
     pin: P,
-
-    /// Brightness level, range: 0 ~ 255
-    brightness: u8,
-
-    // The duration in seconds of a LED breathing period
-    period: u8,
 }
 
 impl<P: OutputPin> SingleLED<P> {
@@ -112,8 +188,6 @@ impl<P: OutputPin> SingleLED<P> {
             state: false,
             on_state,
             pin: p.pin,
-            brightness: 255,
-            period: 0,
         }
     }
 
@@ -127,22 +201,25 @@ impl<P: OutputPin> SingleLED<P> {
         self.pin.set_state(!self.on_state)
     }
 
-    /// Set LED's brightness
-    fn set_brightness(&mut self, brightness: u8) -> Result<(), P::Error> {
-        self.brightness = brightness;
-
-        // TODO: Write brightness to LED
-
+    /// Turn LED on or off
+    fn set(&mut self, on: bool) -> Result<(), P::Error> {
+        if on {
+            self.on()?
+        } else {
+            self.off()?
+        }
+        self.state = on;
         Ok(())
     }
 }
 
 pub(crate) struct LightService<P: OutputPin> {
     pub(crate) enabled: bool,
-    led_indicator_data: [u8; 1],
     capslock: Option<SingleLED<P>>,
     scrolllock: Option<SingleLED<P>>,
     numslock: Option<SingleLED<P>>,
+    /// Indicator LEDs and their conditions
+    indicators: Vec<(SingleLED<P>, IndicatorCondition), MAX_INDICATORS>,
 }
 
 // Implement on/off function for LightService
@@ -150,11 +227,7 @@ macro_rules! impl_led_on_off {
     ($n:ident, $fn_name:ident) => {
         pub(crate) fn $fn_name(&mut self, state: bool) -> Result<(), P::Error> {
             if let Some(led) = &mut self.$n {
-                if state {
-                    led.on()?
-                } else {
-                    led.off()?
-                }
+                led.set(state)?
             }
             Ok(())
         }
@@ -162,38 +235,21 @@ macro_rules! impl_led_on_off {
 }
 
 impl<P: OutputPin> LightService<P> {
-    pub(crate) fn new(
-        capslock_pin: Option<LightPinConfig<P>>,
-        scrolllock_pin: Option<LightPinConfig<P>>,
-        numslock_pin: Option<LightPinConfig<P>>,
-    ) -> Self {
-        let mut enabled = true;
-        if capslock_pin.is_none() && scrolllock_pin.is_none() && numslock_pin.is_none() {
-            enabled = false;
-        }
-        Self {
-            enabled,
-            led_indicator_data: [0; 1],
-            capslock: capslock_pin.map(|p| SingleLED::new(p)),
-            scrolllock: scrolllock_pin.map(|p| SingleLED::new(p)),
-            numslock: numslock_pin.map(|p| SingleLED::new(p)),
-        }
-    }
-
     pub(crate) fn from_config(light_config: LightConfig<P>) -> Self {
-        let mut enabled = true;
-        if light_config.capslock.is_none()
-            && light_config.numslock.is_none()
-            && light_config.scrolllock.is_none()
-        {
-            enabled = false;
-        }
+        let enabled = light_config.capslock.is_some()
+            || light_config.numslock.is_some()
+            || light_config.scrolllock.is_some()
+            || !light_config.indicators.is_empty();
         Self {
             enabled,
-            led_indicator_data: [0; 1],
             capslock: light_config.capslock.map(|p| SingleLED::new(p)),
             scrolllock: light_config.scrolllock.map(|p| SingleLED::new(p)),
             numslock: light_config.numslock.map(|p| SingleLED::new(p)),
+            indicators: light_config
+                .indicators
+                .into_iter()
+                .map(|i| (SingleLED::new(i.pin), i.condition))
+                .collect(),
         }
     }
 }
@@ -203,10 +259,13 @@ impl<P: OutputPin> LightService<P> {
     impl_led_on_off!(scrolllock, set_scrolllock);
     impl_led_on_off!(numslock, set_numslock);
 
-    pub(crate) fn set_leds(&mut self, led_indicator: LedIndicator) -> Result<(), P::Error> {
-        self.set_capslock(led_indicator.capslock())?;
-        self.set_numslock(led_indicator.numslock())?;
-        self.set_scrolllock(led_indicator.scrolllock())?;
+    pub(crate) fn set_leds(&mut self, state: &IndicatorState) -> Result<(), P::Error> {
+        self.set_capslock(state.leds.capslock())?;
+        self.set_numslock(state.leds.numslock())?;
+        self.set_scrolllock(state.leds.scrolllock())?;
+        for (led, condition) in self.indicators.iter_mut() {
+            led.set(state.is_met(*condition))?;
+        }
 
         Ok(())
     }
@@ -1,7 +1,7 @@
 use crate::{
     debounce::{DebounceState, DebouncerTrait},
-    event::KeyEvent,
-    keyboard::KEY_EVENT_CHANNEL,
+    event::{Event, KeyEvent},
+    keyboard::EVENT_CHANNEL,
     CONNECTION_STATE,
 };
 use core::future::Future;
@@ -222,12 +222,12 @@ impl<
                             let (row, col, key_state) =
                                 (out_idx, in_idx, self.key_states[out_idx][in_idx]);
 
-                            KEY_EVENT_CHANNEL
-                                .send(KeyEvent {
+                            EVENT_CHANNEL
+                                .send(Event::Key(KeyEvent {
                                     row: row as u8,
                                     col: col as u8,
                                     pressed: key_state.pressed,
-                                })
+                                }))
                                 .await;
                         }
                         _ => (),
@@ -0,0 +1,267 @@
+//! USB MIDI support
+//!
+//! MIDI keycodes are processed by [`MidiProcessor`] in the keyboard task,
+//! the generated [`MidiMessage`]s are sent to the host by [`midi_task`] via the USB MIDI streaming interface.
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_usb::{class::midi::MidiClass, driver::Driver};
+
+use crate::{event::KeyEvent, keycode::KeyCode};
+
+pub(crate) const MIDI_CHANNEL_SIZE: usize = 16;
+pub(crate) static MIDI_CHANNEL: Channel<CriticalSectionRawMutex, MidiMessage, MIDI_CHANNEL_SIZE> =
+    Channel::new();
+
+/// Number of `MidiNote*` keycodes: `MidiNoteC0` ~ `MidiNoteB5`
+const NUM_MIDI_NOTE_KEYS: usize =
+    (KeyCode::MidiNoteB5 as u16 - KeyCode::MidiNoteC0 as u16 + 1) as usize;
+
+/// Center value of the 14-bit pitch bend
+const PITCH_BEND_CENTER: u16 = 0x2000;
+
+/// MIDI channel messages that RMK sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum MidiMessage {
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    ControlChange {
+        channel: u8,
+        control: u8,
+        value: u8,
+    },
+    /// 14-bit pitch bend value, 0x2000 is the center
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+}
+
+impl MidiMessage {
+    /// Convert the message to a 4-byte USB MIDI event packet, using cable number 0.
+    ///
+    /// The first byte is the code index number(CIN), which is the same as the high nibble of the status byte for channel messages.
+    pub(crate) fn to_usb_packet(self) -> [u8; 4] {
+        match self {
+            MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => [0x08, 0x80 | (channel & 0x0F), note & 0x7F, velocity & 0x7F],
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => [0x09, 0x90 | (channel & 0x0F), note & 0x7F, velocity & 0x7F],
+            MidiMessage::ControlChange {
+                channel,
+                control,
+                value,
+            } => [0x0B, 0xB0 | (channel & 0x0F), control & 0x7F, value & 0x7F],
+            MidiMessage::PitchBend { channel, value } => [
+                0x0E,
+                0xE0 | (channel & 0x0F),
+                (value & 0x7F) as u8,
+                ((value >> 7) & 0x7F) as u8,
+            ],
+        }
+    }
+}
+
+/// Send all MIDI messages in [`MIDI_CHANNEL`] to the host
+pub(crate) async fn midi_task<'d, D: Driver<'d>>(midi: &mut MidiClass<'d, D>) {
+    loop {
+        let message = MIDI_CHANNEL.receive().await;
+        debug!("Send MIDI message: {:?}", message);
+        if let Err(e) = midi.write_packet(&message.to_usb_packet()).await {
+            error!("Send MIDI message error: {:?}", e);
+        }
+    }
+}
+
+/// Processor of MIDI keycodes, the behavior is similar with QMK's MIDI "advanced" mode
+pub(crate) struct MidiProcessor {
+    /// Whether MIDI keycodes are enabled
+    enabled: bool,
+    /// Octave index, 0 for `MidiOctaveN2`, 9 for `MidiOctave7`
+    octave: u8,
+    /// Transpose in semitones, -6 ~ 6
+    transpose: i8,
+    /// Note velocity, 0 ~ 127
+    velocity: u8,
+    /// MIDI channel, 0 ~ 15
+    channel: u8,
+    /// Channels and notes which are currently on, indexed by note keycode.
+    /// Used to send the same note off even if octave/transpose/channel is changed while the key is held
+    notes: [Option<(u8, u8)>; NUM_MIDI_NOTE_KEYS],
+}
+
+impl Default for MidiProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiProcessor {
+    pub(crate) fn new() -> Self {
+        Self {
+            enabled: true,
+            // MidiOctave2
+            octave: 4,
+            transpose: 0,
+            velocity: 127,
+            channel: 0,
+            notes: [None; NUM_MIDI_NOTE_KEYS],
+        }
+    }
+
+    /// Process a MIDI keycode
+    pub(crate) fn process(&mut self, key: KeyCode, key_event: KeyEvent) {
+        if !key.is_midi() {
+            return;
+        }
+
+        if KeyCode::MidiNoteC0 <= key && key <= KeyCode::MidiNoteB5 {
+            self.process_note(key, key_event);
+            return;
+        }
+
+        if !key_event.pressed {
+            self.process_release(key);
+            return;
+        }
+
+        match key {
+            KeyCode::MidiOn => self.enabled = true,
+            KeyCode::MidiOff => self.enabled = false,
+            KeyCode::MidiToggle => self.enabled = !self.enabled,
+            _ if !self.enabled => (),
+            k if KeyCode::MidiOctaveN2 <= k && k <= KeyCode::MidiOctave7 => {
+                self.octave = (k as u16 - KeyCode::MidiOctaveN2 as u16) as u8
+            }
+            KeyCode::MidiOctaveDOWN => self.octave = self.octave.saturating_sub(1),
+            KeyCode::MidiOctaveUP => self.octave = (self.octave + 1).min(9),
+            k if KeyCode::MidiTransposeN6 <= k && k <= KeyCode::MidiTranspose6 => {
+                self.transpose = (k as i16 - KeyCode::MidiTranspose0 as i16) as i8
+            }
+            KeyCode::MidiTransposeDown => self.transpose = (self.transpose - 1).max(-6),
+            KeyCode::MidiTransposeUp => self.transpose = (self.transpose + 1).min(6),
+            k if KeyCode::MidiVelocity0 <= k && k <= KeyCode::MidiVelocity10 => {
+                // Map 0 ~ 10 to 0 ~ 127
+                let level = (k as u16 - KeyCode::MidiVelocity0 as u16) as u8;
+                self.velocity = if level == 10 { 127 } else { level * 12 };
+            }
+            KeyCode::MidiVelocityDOWN => self.velocity = self.velocity.saturating_sub(13),
+            KeyCode::MidiVelocityUP => self.velocity = (self.velocity + 13).min(127),
+            k if KeyCode::MidiChannel1 <= k && k <= KeyCode::MidiChannel16 => {
+                self.channel = (k as u16 - KeyCode::MidiChannel1 as u16) as u8
+            }
+            KeyCode::MidiChannelDOWN => self.channel = self.channel.wrapping_sub(1) & 0x0F,
+            KeyCode::MidiChannelUP => self.channel = (self.channel + 1) & 0x0F,
+            KeyCode::MidiAllNotesOff => {
+                self.notes = [None; NUM_MIDI_NOTE_KEYS];
+                self.send(MidiMessage::ControlChange {
+                    channel: self.channel,
+                    control: 123,
+                    value: 0,
+                });
+            }
+            KeyCode::MidiPitchBendDown => self.send(MidiMessage::PitchBend {
+                channel: self.channel,
+                value: 0,
+            }),
+            KeyCode::MidiPitchBendUp => self.send(MidiMessage::PitchBend {
+                channel: self.channel,
+                value: 0x3FFF,
+            }),
+            _ => {
+                if let Some(control) = Self::control_number(key) {
+                    self.send(MidiMessage::ControlChange {
+                        channel: self.channel,
+                        control,
+                        value: 127,
+                    });
+                } else {
+                    warn!("Unsupported MIDI key: {:?}", key);
+                }
+            }
+        }
+    }
+
+    /// Process `MidiNote*` keys. Note off is always sent for a pressed note, even if MIDI is disabled after the press.
+    fn process_note(&mut self, key: KeyCode, key_event: KeyEvent) {
+        let idx = (key as u16 - KeyCode::MidiNoteC0 as u16) as usize;
+        if key_event.pressed {
+            if self.enabled {
+                let note = self.compute_note(idx as u8);
+                self.notes[idx] = Some((self.channel, note));
+                self.send(MidiMessage::NoteOn {
+                    channel: self.channel,
+                    note,
+                    velocity: self.velocity,
+                });
+            }
+        } else if let Some((channel, note)) = self.notes[idx].take() {
+            self.send(MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity: self.velocity,
+            });
+        }
+    }
+
+    /// Process the release of the keys which have a "held" state
+    fn process_release(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::MidiPitchBendDown | KeyCode::MidiPitchBendUp => {
+                self.send(MidiMessage::PitchBend {
+                    channel: self.channel,
+                    value: PITCH_BEND_CENTER,
+                })
+            }
+            _ => {
+                if let Some(control) = Self::control_number(key) {
+                    self.send(MidiMessage::ControlChange {
+                        channel: self.channel,
+                        control,
+                        value: 0,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Get the MIDI control number of pedal-like keys
+    fn control_number(key: KeyCode) -> Option<u8> {
+        match key {
+            KeyCode::MidiModulation => Some(1),
+            KeyCode::MidiSustain => Some(64),
+            KeyCode::MidiPortamento => Some(65),
+            KeyCode::MidiSostenuto => Some(66),
+            KeyCode::MidiSoft => Some(67),
+            KeyCode::MidiLegato => Some(68),
+            _ => None,
+        }
+    }
+
+    /// Compute the MIDI note number from the index of note keycode, with current octave and transpose
+    fn compute_note(&self, idx: u8) -> u8 {
+        let note = 12 * self.octave as i16 + idx as i16 + self.transpose as i16;
+        note.clamp(0, 127) as u8
+    }
+
+    fn send(&self, message: MidiMessage) {
+        // Don't block the keyboard if the MIDI interface isn't running
+        if MIDI_CHANNEL.try_send(message).is_err() {
+            warn!("MIDI channel is full, drop message: {:?}", message);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! User channel of the raw HID interface
+//!
+//! The raw HID interface is used by Via/Vial, commands in
+//! [`RAW_HID_USER_COMMAND_START`]..=[`RAW_HID_USER_COMMAND_END`] are reserved for user applications.
+//! Implement [`RawHidHandler`] to process them, then pass it to RMK by [`crate::config::RmkConfig::raw_hid_handler`].
+//! The channel works on both USB and the BLE Vial service.
+//!
+//! ```rust
+//! struct MyRawHid;
+//!
+//! impl RawHidHandler for MyRawHid {
+//!     fn process(&mut self, report: &mut [u8; RAW_HID_REPORT_SIZE]) {
+//!         if report[0] == 0x80 {
+//!             info!("CPU load: {}%", report[1]);
+//!         }
+//!     }
+//! }
+//!
+//! static RAW_HID: StaticCell<MyRawHid> = StaticCell::new();
+//! let keyboard_config = RmkConfig {
+//!     raw_hid_handler: Some(RAW_HID.init(MyRawHid)),
+//!     ..Default::default()
+//! };
+//! ```
+//!
+//! The keyboard can also notify the host by [`send_raw_hid_report`], for example in [`crate::hooks::KeyboardHooks::on_layer_change`].
+
+use core::cell::RefCell;
+
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    channel::Channel,
+};
+
+/// Size of raw HID reports
+pub const RAW_HID_REPORT_SIZE: usize = 32;
+
+/// The first command id reserved for user applications
+pub const RAW_HID_USER_COMMAND_START: u8 = 0x80;
+
+/// The last command id reserved for user applications, 0xFE and 0xFF are used by Vial
+pub const RAW_HID_USER_COMMAND_END: u8 = 0xFD;
+
+/// Number of notifications which can be queued before they're sent to the host
+const RAW_HID_REPORT_CHANNEL_SIZE: usize = 4;
+
+/// Handler of user raw HID commands
+pub trait RawHidHandler: Send {
+    /// Process a report received from the host, `report[0]` is the command id.
+    ///
+    /// The report is sent back to the host as the response after processing,
+    /// so the response data can be written to `report` directly.
+    /// It's called in the Via task, so it should return quickly.
+    fn process(&mut self, report: &mut [u8; RAW_HID_REPORT_SIZE]);
+}
+
+/// The raw HID handler set by user
+static RAW_HID_HANDLER: Mutex<
+    CriticalSectionRawMutex,
+    RefCell<Option<&'static mut dyn RawHidHandler>>,
+> = Mutex::new(RefCell::new(None));
+
+/// Notifications which are sent to the host by the Via task
+pub(crate) static RAW_HID_REPORT_CHANNEL: Channel<
+    CriticalSectionRawMutex,
+    [u8; RAW_HID_REPORT_SIZE],
+    RAW_HID_REPORT_CHANNEL_SIZE,
+> = Channel::new();
+
+/// Set the handler of user raw HID commands
+pub(crate) fn set_raw_hid_handler(handler: Option<&'static mut dyn RawHidHandler>) {
+    RAW_HID_HANDLER.lock(|h| *h.borrow_mut() = handler);
+}
+
+/// Process the report if it's a user command.
+///
+/// Returns `false` if the command id isn't in the user range or the handler is not set.
+pub(crate) fn process_raw_hid_report(report: &mut [u8; RAW_HID_REPORT_SIZE]) -> bool {
+    if !(RAW_HID_USER_COMMAND_START..=RAW_HID_USER_COMMAND_END).contains(&report[0]) {
+        return false;
+    }
+    RAW_HID_HANDLER.lock(|h| match h.borrow_mut().as_mut() {
+        Some(handler) => {
+            handler.process(report);
+            true
+        }
+        None => false,
+    })
+}
+
+/// Send a report to the host without a request, returns `false` if the report is dropped.
+///
+/// The first byte should be a user command id, so that the host application can tell it from Via responses.
+/// Note that the notification might be received by Via/Vial if it's open, so the host application should
+/// handle unexpected reports as well. Reports are dropped if the queue is full or the host is not connected.
+pub fn send_raw_hid_report(report: [u8; RAW_HID_REPORT_SIZE]) -> bool {
+    if RAW_HID_REPORT_CHANNEL.try_send(report).is_err() {
+        warn!("Raw HID report channel is full, the report is dropped");
+        return false;
+    }
+    true
+}
@@ -0,0 +1,197 @@
+//! Color types and color math used by RGB lights
+
+/// A RGB color, 8 bits per channel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const OFF: Rgb = Rgb::new(0, 0, 0);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Scale the color by `factor / 255`
+    pub fn scale(self, factor: u8) -> Self {
+        Self {
+            r: scale8(self.r, factor),
+            g: scale8(self.g, factor),
+            b: scale8(self.b, factor),
+        }
+    }
+
+    /// Blend two colors, `amount` 0 is `self` and 255 is `other`
+    pub fn blend(self, other: Rgb, amount: u8) -> Self {
+        Self {
+            r: blend8(self.r, other.r, amount),
+            g: blend8(self.g, other.g, amount),
+            b: blend8(self.b, other.b, amount),
+        }
+    }
+}
+
+/// A HSV color, all components are in range 0 ~ 255
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hsv {
+    pub h: u8,
+    pub s: u8,
+    pub v: u8,
+}
+
+impl Hsv {
+    pub const fn new(h: u8, s: u8, v: u8) -> Self {
+        Self { h, s, v }
+    }
+
+    /// Convert to RGB, the algorithm is the same as QMK's `hsv_to_rgb`
+    pub fn to_rgb(self) -> Rgb {
+        if self.s == 0 {
+            return Rgb::new(self.v, self.v, self.v);
+        }
+        let (h, s, v) = (self.h as u16, self.s as u16, self.v as u16);
+        // The hue circle is divided into 6 regions
+        let region = h * 6 / 255;
+        let remainder = (h * 2 - region * 85) * 3;
+
+        let p = ((v * (255 - s)) >> 8) as u8;
+        let q = ((v * (255 - ((s * remainder) >> 8))) >> 8) as u8;
+        let t = ((v * (255 - ((s * (255 - remainder)) >> 8))) >> 8) as u8;
+        let v = v as u8;
+
+        match region {
+            0 | 6 => Rgb::new(v, t, p),
+            1 => Rgb::new(q, v, p),
+            2 => Rgb::new(p, v, t),
+            3 => Rgb::new(p, q, v),
+            4 => Rgb::new(t, p, v),
+            _ => Rgb::new(v, p, q),
+        }
+    }
+}
+
+/// Scale `value` by `factor / 255`, `scale8(x, 255) == x`
+pub fn scale8(value: u8, factor: u8) -> u8 {
+    ((value as u16 * (factor as u16 + 1)) >> 8) as u8
+}
+
+/// Linear interpolation between `a` and `b`, `amount` 0 is `a` and 255 is `b`
+pub fn blend8(a: u8, b: u8, amount: u8) -> u8 {
+    let a = a as u16;
+    let b = b as u16;
+    let amount = amount as u16;
+    ((a * (255 - amount) + b * amount + 127) / 255) as u8
+}
+
+/// Color of a heatmap, `heat` 0 is blue and 255 is red
+pub fn heatmap(heat: u8, v: u8) -> Hsv {
+    // Hue 170 is blue, 0 is red
+    Hsv::new(170 - (heat as u16 * 170 / 255) as u8, 255, v)
+}
+
+/// Triangle wave of `phase`, rises from 0 to 254 then falls back to 0 in a full 0 ~ 255 phase
+pub fn triangle8(phase: u8) -> u8 {
+    if phase < 128 {
+        phase * 2
+    } else {
+        (255 - phase) * 2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hsv_to_rgb() {
+        // Primary colors
+        assert_eq!(Hsv::new(0, 255, 255).to_rgb(), Rgb::new(255, 0, 0));
+        assert_eq!(Hsv::new(85, 255, 255).to_rgb(), Rgb::new(0, 255, 0));
+        assert_eq!(Hsv::new(170, 255, 255).to_rgb(), Rgb::new(0, 0, 255));
+        // No saturation is gray
+        assert_eq!(Hsv::new(123, 0, 100).to_rgb(), Rgb::new(100, 100, 100));
+        // No value is black
+        assert_eq!(Hsv::new(42, 255, 0).to_rgb(), Rgb::OFF);
+        // Hue 255 is close to red
+        let rgb = Hsv::new(255, 255, 255).to_rgb();
+        assert_eq!(rgb.r, 255);
+        assert_eq!(rgb.g, 0);
+        assert!(rgb.b < 10);
+    }
+
+    #[test]
+    fn test_hsv_to_rgb_hue_is_continuous() {
+        let mut last = Hsv::new(0, 255, 255).to_rgb();
+        for h in 1..=255 {
+            let rgb = Hsv::new(h, 255, 255).to_rgb();
+            assert!(
+                last.r.abs_diff(rgb.r) <= 12,
+                "hue {}: {:?} -> {:?}",
+                h,
+                last,
+                rgb
+            );
+            assert!(
+                last.g.abs_diff(rgb.g) <= 12,
+                "hue {}: {:?} -> {:?}",
+                h,
+                last,
+                rgb
+            );
+            assert!(
+                last.b.abs_diff(rgb.b) <= 12,
+                "hue {}: {:?} -> {:?}",
+                h,
+                last,
+                rgb
+            );
+            last = rgb;
+        }
+    }
+
+    #[test]
+    fn test_scale8() {
+        assert_eq!(scale8(255, 255), 255);
+        assert_eq!(scale8(200, 255), 200);
+        assert_eq!(scale8(255, 0), 0);
+        assert_eq!(scale8(0, 128), 0);
+        assert_eq!(scale8(200, 128), 100);
+        assert_eq!(Rgb::new(255, 128, 0).scale(128), Rgb::new(128, 64, 0));
+    }
+
+    #[test]
+    fn test_blend() {
+        let a = Rgb::new(0, 100, 255);
+        let b = Rgb::new(255, 100, 0);
+        assert_eq!(a.blend(b, 0), a);
+        assert_eq!(a.blend(b, 255), b);
+        assert_eq!(a.blend(b, 128), Rgb::new(128, 100, 127));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        assert_eq!(heatmap(0, 255), Hsv::new(170, 255, 255));
+        assert_eq!(heatmap(255, 255), Hsv::new(0, 255, 255));
+        // Hotter is closer to red
+        let mut last = heatmap(0, 255).h;
+        for heat in 1..=255 {
+            let h = heatmap(heat, 255).h;
+            assert!(h <= last);
+            last = h;
+        }
+    }
+
+    #[test]
+    fn test_triangle8() {
+        assert_eq!(triangle8(0), 0);
+        assert_eq!(triangle8(64), 128);
+        assert_eq!(triangle8(127), 254);
+        assert_eq!(triangle8(128), 254);
+        assert_eq!(triangle8(255), 0);
+    }
+}
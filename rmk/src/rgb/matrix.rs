@@ -0,0 +1,181 @@
+//! Per-key RGB matrix
+//!
+//! Each LED of the strip is mapped to a key in the matrix by [`RgbMatrixConfig::led_map`].
+//! The [`RgbMatrixService`] receives key events forwarded by the keyboard and the active layer published by the keyboard,
+//! and renders the current [`RgbMatrixEffect`] on its own timer.
+//!
+//! The RGB matrix service should be run concurrently with RMK:
+//! ```rust
+//! let mut rgb_matrix: RgbMatrixService<_, NUM_LEDS> = RgbMatrixService::new(led_strip, rgb_matrix_config);
+//! embassy_futures::join::join(rgb_matrix.run(), run_rmk(/* .. arguments */)).await;
+//! ```
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Ticker};
+
+use super::{
+    color::{heatmap, Hsv, Rgb},
+    LedStripWriter,
+};
+use crate::{config::RgbMatrixConfig, event::KeyEvent};
+
+/// Key events forwarded by the keyboard
+pub(crate) static RGB_MATRIX_CHANNEL: Channel<CriticalSectionRawMutex, KeyEvent, 16> =
+    Channel::new();
+
+/// Current active layer, which is updated by the keyboard
+static ACTIVE_LAYER: AtomicU8 = AtomicU8::new(0);
+
+/// Interval between frames, in milliseconds
+const FRAME_INTERVAL: u64 = 20;
+
+/// Decrease of the reactive highlight per frame, a highlight lasts about 0.6s
+const REACTIVE_DECAY: u8 = 8;
+
+/// Increase of the heat per key press
+const HEAT_STEP: u8 = 32;
+
+/// The heat decreases by 1 every `HEAT_DECAY_FRAMES` frames
+const HEAT_DECAY_FRAMES: u8 = 10;
+
+/// Brightness of the background in reactive mode, in 1/255
+const REACTIVE_BACKGROUND: u8 = 64;
+
+/// Effects of the RGB matrix
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RgbMatrixEffect {
+    /// All keys use the color of the active layer
+    #[default]
+    SolidLayer,
+    /// Keys light up when pressed and fade out, over a dimmed color of the active layer
+    Reactive,
+    /// Frequently pressed keys are red, and cool down to blue over time
+    Heatmap,
+}
+
+/// Forward a key event to the RGB matrix service.
+///
+/// The event is dropped if the RGB matrix service isn't running or is busy, so that the keyboard is never blocked.
+pub(crate) fn send_rgb_matrix_key_event(event: KeyEvent) {
+    let _ = RGB_MATRIX_CHANNEL.try_send(event);
+}
+
+/// Publish current active layer to the RGB matrix service
+pub(crate) fn update_rgb_matrix_layer(layer: u8) {
+    ACTIVE_LAYER.store(layer, Ordering::Relaxed);
+}
+
+/// Per-key RGB service, `N` is the number of LEDs
+pub struct RgbMatrixService<'a, W: LedStripWriter, const N: usize> {
+    writer: W,
+    config: RgbMatrixConfig<'a>,
+    frame: [Rgb; N],
+    /// Reactive highlight of each LED
+    reactive: [u8; N],
+    /// Heat of each LED
+    heat: [u8; N],
+    /// Frame counter used by the heat decay
+    frame_count: u8,
+}
+
+impl<'a, W: LedStripWriter, const N: usize> RgbMatrixService<'a, W, N> {
+    pub fn new(writer: W, config: RgbMatrixConfig<'a>) -> Self {
+        if config.led_map.len() < N {
+            warn!(
+                "RGB matrix LED map has {} LEDs, less than {}",
+                config.led_map.len(),
+                N
+            );
+        }
+        Self {
+            writer,
+            config,
+            frame: [Rgb::OFF; N],
+            reactive: [0; N],
+            heat: [0; N],
+            frame_count: 0,
+        }
+    }
+
+    /// Run the RGB matrix service
+    pub async fn run(&mut self) {
+        let mut ticker = Ticker::every(Duration::from_millis(FRAME_INTERVAL));
+        loop {
+            match select(RGB_MATRIX_CHANNEL.receive(), ticker.next()).await {
+                Either::First(event) => self.process_key_event(event),
+                Either::Second(_) => {
+                    self.render();
+                    if let Err(e) = self.writer.write(&self.frame).await {
+                        error!("Write LED strip error: {:?}", e);
+                    }
+                    self.decay();
+                }
+            }
+        }
+    }
+
+    /// Change current effect
+    pub fn set_effect(&mut self, effect: RgbMatrixEffect) {
+        self.config.effect = effect;
+    }
+
+    fn process_key_event(&mut self, event: KeyEvent) {
+        if !event.pressed {
+            return;
+        }
+        for (i, &(row, col)) in self.config.led_map.iter().take(N).enumerate() {
+            if row == event.row && col == event.col {
+                self.reactive[i] = u8::MAX;
+                self.heat[i] = self.heat[i].saturating_add(HEAT_STEP);
+            }
+        }
+    }
+
+    fn decay(&mut self) {
+        self.reactive
+            .iter_mut()
+            .for_each(|r| *r = r.saturating_sub(REACTIVE_DECAY));
+        self.frame_count += 1;
+        if self.frame_count >= HEAT_DECAY_FRAMES {
+            self.frame_count = 0;
+            self.heat.iter_mut().for_each(|h| *h = h.saturating_sub(1));
+        }
+    }
+
+    /// Color of the active layer, the first layer color is used if the layer has no color
+    fn layer_color(&self) -> Hsv {
+        let layer = ACTIVE_LAYER.load(Ordering::Relaxed) as usize;
+        let color = self
+            .config
+            .layer_colors
+            .get(layer)
+            .or(self.config.layer_colors.first())
+            .copied()
+            .unwrap_or_default();
+        Hsv::new(color.h, color.s, color.v.min(self.config.max_brightness))
+    }
+
+    fn render(&mut self) {
+        let base = self.layer_color();
+        match self.config.effect {
+            RgbMatrixEffect::SolidLayer => self.frame.fill(base.to_rgb()),
+            RgbMatrixEffect::Reactive => {
+                let background = base.to_rgb().scale(REACTIVE_BACKGROUND);
+                // The complementary color of the layer color
+                let highlight = Hsv::new(base.h.wrapping_add(128), base.s, base.v).to_rgb();
+                for (led, &r) in self.frame.iter_mut().zip(self.reactive.iter()) {
+                    *led = background.blend(highlight, r);
+                }
+            }
+            RgbMatrixEffect::Heatmap => {
+                for (led, &h) in self.frame.iter_mut().zip(self.heat.iter()) {
+                    *led = heatmap(h, self.config.max_brightness).to_rgb();
+                }
+            }
+        }
+    }
+}
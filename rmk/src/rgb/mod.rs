@@ -0,0 +1,40 @@
+//! RGB light support
+//!
+//! Addressable LEDs are driven by a [`LedStripWriter`], which writes a frame of colors to the LED strip.
+//! RMK provides [`ws2812::Ws2812Spi`], which drives WS2812 LEDs via SPI and requires `ws2812_spi` feature.
+//! For other drivers, such as RP2040's PIO, implement [`LedStripWriter`] for the driver:
+//! ```rust
+//! struct PioStrip<'d>(PioWs2812<'d, PIO0, 0, NUM_LEDS>);
+//! impl LedStripWriter for PioStrip<'_> {
+//!     type Error = core::convert::Infallible;
+//!     fn write(&mut self, colors: &[Rgb]) -> impl Future<Output = Result<(), Self::Error>> {
+//!         let mut frame = [RGB8::default(); NUM_LEDS];
+//!         for (c, f) in colors.iter().zip(frame.iter_mut()) {
+//!             *f = RGB8::new(c.r, c.g, c.b);
+//!         }
+//!         async move {
+//!             self.0.write(&frame).await;
+//!             Ok(())
+//!         }
+//!     }
+//! }
+//! ```
+
+pub mod color;
+pub mod matrix;
+pub mod underglow;
+#[cfg(feature = "ws2812_spi")]
+pub mod ws2812;
+
+use core::fmt::Debug;
+use core::future::Future;
+
+use self::color::Rgb;
+
+/// Writer of addressable LED strips
+pub trait LedStripWriter {
+    type Error: Debug;
+
+    /// Write colors of all LEDs, `colors[0]` is the first LED of the strip
+    fn write(&mut self, colors: &[Rgb]) -> impl Future<Output = Result<(), Self::Error>>;
+}
@@ -0,0 +1,302 @@
+//! RGB underglow
+//!
+//! The [`RgbLightService`] renders animations of all LEDs in the strip, using the HSV color in the settings.
+//! RGB settings can be changed by RGB keycodes or Via's lighting menu, and they're saved to the storage.
+//!
+//! The RGB light service should be run concurrently with RMK:
+//! ```rust
+//! let mut rgb_light: RgbLightService<_, NUM_LEDS> = RgbLightService::new(led_strip, RGBLightConfig::default());
+//! embassy_futures::join::join(rgb_light.run(), run_rmk(/* .. arguments */)).await;
+//! ```
+
+use core::cell::Cell;
+
+use embassy_futures::select::{select, Either};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    channel::Channel,
+};
+use embassy_time::Timer;
+use num_enum::FromPrimitive;
+
+use super::{
+    color::{scale8, triangle8, Hsv, Rgb},
+    LedStripWriter,
+};
+use crate::{
+    config::RGBLightConfig,
+    keycode::KeyCode,
+    storage::{eeconfig::EeRgbLightConfig, FlashOperationMessage, FLASH_CHANNEL},
+};
+
+pub(crate) static RGB_LIGHT_CHANNEL: Channel<CriticalSectionRawMutex, RgbLightEvent, 8> =
+    Channel::new();
+
+/// Current RGB light settings, which are read and changed by Via
+static RGB_LIGHT_SETTINGS: Mutex<CriticalSectionRawMutex, Cell<EeRgbLightConfig>> =
+    Mutex::new(Cell::new(EeRgbLightConfig::new()));
+
+/// Interval between animation frames, in milliseconds
+const FRAME_INTERVAL: u64 = 20;
+
+/// Step of `RgbSpi`/`RgbSpd` keycodes
+const SPEED_STEP: u8 = 16;
+
+/// Number of lit LEDs in the snake animation
+const SNAKE_LENGTH: usize = 4;
+
+/// Animation modes of the RGB light
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromPrimitive)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum RgbLightMode {
+    /// Static color
+    #[num_enum(default)]
+    Plain = 0,
+    /// The brightness fades in and out
+    Breathing = 1,
+    /// The hue of all LEDs cycles through the color wheel
+    Rainbow = 2,
+    /// A rainbow which rotates along the strip
+    Swirl = 3,
+    /// A few LEDs move along the strip
+    Snake = 4,
+}
+
+impl RgbLightMode {
+    /// The last mode, used to cycle through modes
+    const LAST: RgbLightMode = RgbLightMode::Snake;
+
+    fn next(self) -> Self {
+        if self == Self::LAST {
+            Self::Plain
+        } else {
+            Self::from_primitive(self as u8 + 1)
+        }
+    }
+
+    fn previous(self) -> Self {
+        if self == Self::Plain {
+            Self::LAST
+        } else {
+            Self::from_primitive(self as u8 - 1)
+        }
+    }
+
+    fn is_animated(self) -> bool {
+        self != Self::Plain
+    }
+}
+
+/// Events which trigger the RGB light service
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum RgbLightEvent {
+    /// A RGB keycode is pressed
+    Key(KeyCode),
+    /// RGB light config read from the storage
+    LoadConfig(EeRgbLightConfig),
+    /// RGB light settings are changed by Via, the new settings are applied but not saved
+    Update,
+    /// Save current RGB light settings
+    Save,
+}
+
+/// Send an event to the RGB light service.
+///
+/// The event is dropped if the RGB light service isn't running or is busy, so that the keyboard is never blocked.
+pub(crate) fn send_rgb_light_event(event: RgbLightEvent) {
+    let _ = RGB_LIGHT_CHANNEL.try_send(event);
+}
+
+/// Get current RGB light settings
+pub(crate) fn get_rgb_light_settings() -> EeRgbLightConfig {
+    RGB_LIGHT_SETTINGS.lock(|s| s.get())
+}
+
+/// Apply new RGB light settings without saving them
+pub(crate) fn set_rgb_light_settings(settings: EeRgbLightConfig) {
+    RGB_LIGHT_SETTINGS.lock(|s| s.set(settings));
+    send_rgb_light_event(RgbLightEvent::Update);
+}
+
+fn save_rgb_light_settings(settings: EeRgbLightConfig) {
+    // Don't block if the storage is busy
+    if FLASH_CHANNEL
+        .try_send(FlashOperationMessage::RgbLightConfig(settings))
+        .is_err()
+    {
+        warn!("Flash channel is full, RGB light config isn't saved");
+    }
+}
+
+/// RGB underglow service, `N` is the number of LEDs
+pub struct RgbLightService<W: LedStripWriter, const N: usize> {
+    writer: W,
+    config: RGBLightConfig,
+    frame: [Rgb; N],
+    /// Animation time, which increases faster when the speed is higher
+    tick: u32,
+}
+
+impl<W: LedStripWriter, const N: usize> RgbLightService<W, N> {
+    pub fn new(writer: W, config: RGBLightConfig) -> Self {
+        RGB_LIGHT_SETTINGS.lock(|s| {
+            s.set(
+                EeRgbLightConfig::new()
+                    .with_enable(config.enabled)
+                    .with_mode(RgbLightMode::Plain as u8)
+                    .with_hue(0)
+                    .with_sat(255)
+                    .with_val(config.max_brightness),
+            )
+        });
+        Self {
+            writer,
+            config,
+            frame: [Rgb::OFF; N],
+            tick: 0,
+        }
+    }
+
+    /// Run the RGB light service
+    pub async fn run(&mut self) {
+        loop {
+            let settings = get_rgb_light_settings();
+            self.render(settings);
+            if let Err(e) = self.writer.write(&self.frame).await {
+                error!("Write LED strip error: {:?}", e);
+            }
+
+            let animated = RgbLightMode::from_primitive(settings.mode()).is_animated();
+            if settings.enable() && animated {
+                match select(
+                    RGB_LIGHT_CHANNEL.receive(),
+                    Timer::after_millis(FRAME_INTERVAL),
+                )
+                .await
+                {
+                    Either::First(event) => self.process_event(event),
+                    Either::Second(_) => {
+                        self.tick = self
+                            .tick
+                            .wrapping_add(1 + (settings.speed() / SPEED_STEP) as u32)
+                    }
+                }
+            } else {
+                let event = RGB_LIGHT_CHANNEL.receive().await;
+                self.process_event(event);
+            }
+        }
+    }
+
+    fn process_event(&mut self, event: RgbLightEvent) {
+        match event {
+            RgbLightEvent::Key(key) => self.process_key(key),
+            RgbLightEvent::LoadConfig(settings) => {
+                debug!("Load RGB light config: {:?}", settings);
+                RGB_LIGHT_SETTINGS.lock(|s| s.set(settings));
+            }
+            // Settings are read before rendering the next frame
+            RgbLightEvent::Update => (),
+            RgbLightEvent::Save => save_rgb_light_settings(get_rgb_light_settings()),
+        }
+    }
+
+    fn process_key(&mut self, key: KeyCode) {
+        let settings = get_rgb_light_settings();
+        let mode = RgbLightMode::from_primitive(settings.mode());
+        let new_settings = match key {
+            KeyCode::RgbTog => settings.with_enable(!settings.enable()),
+            KeyCode::RgbModeForward => settings.with_mode(mode.next() as u8),
+            KeyCode::RgbModeReverse => settings.with_mode(mode.previous() as u8),
+            KeyCode::RgbHui => {
+                settings.with_hue(settings.hue().wrapping_add(self.config.rgb_hue_step))
+            }
+            KeyCode::RgbHud => {
+                settings.with_hue(settings.hue().wrapping_sub(self.config.rgb_hue_step))
+            }
+            KeyCode::RgbSai => {
+                settings.with_sat(settings.sat().saturating_add(self.config.rgb_sat_step))
+            }
+            KeyCode::RgbSad => {
+                settings.with_sat(settings.sat().saturating_sub(self.config.rgb_sat_step))
+            }
+            KeyCode::RgbVai => settings.with_val(
+                settings
+                    .val()
+                    .saturating_add(self.config.rgb_val_step)
+                    .min(self.config.max_brightness),
+            ),
+            KeyCode::RgbVad => {
+                settings.with_val(settings.val().saturating_sub(self.config.rgb_val_step))
+            }
+            KeyCode::RgbSpi => settings.with_speed(settings.speed().saturating_add(SPEED_STEP)),
+            KeyCode::RgbSpd => settings.with_speed(settings.speed().saturating_sub(SPEED_STEP)),
+            KeyCode::RgbModePlain => settings.with_mode(RgbLightMode::Plain as u8),
+            KeyCode::RgbModeBreathe => settings.with_mode(RgbLightMode::Breathing as u8),
+            KeyCode::RgbModeRainbow => settings.with_mode(RgbLightMode::Rainbow as u8),
+            KeyCode::RgbModeSwirl => settings.with_mode(RgbLightMode::Swirl as u8),
+            KeyCode::RgbModeSnake => settings.with_mode(RgbLightMode::Snake as u8),
+            _ => {
+                warn!("Unsupported RGB key: {:?}", key);
+                return;
+            }
+        };
+
+        if new_settings != settings {
+            RGB_LIGHT_SETTINGS.lock(|s| s.set(new_settings));
+            save_rgb_light_settings(new_settings);
+        }
+    }
+
+    /// Render current frame
+    fn render(&mut self, settings: EeRgbLightConfig) {
+        if !settings.enable() {
+            self.frame.fill(Rgb::OFF);
+            return;
+        }
+
+        let base = Hsv::new(
+            settings.hue(),
+            settings.sat(),
+            settings.val().min(self.config.max_brightness),
+        );
+        // Phase of the animation, a full period is 256
+        let phase = (self.tick / 2) as u8;
+        match RgbLightMode::from_primitive(settings.mode()) {
+            RgbLightMode::Plain => self.frame.fill(base.to_rgb()),
+            RgbLightMode::Breathing => {
+                let hsv = Hsv::new(base.h, base.s, scale8(base.v, triangle8(phase)));
+                self.frame.fill(hsv.to_rgb());
+            }
+            RgbLightMode::Rainbow => {
+                let hsv = Hsv::new(base.h.wrapping_add(phase), base.s, base.v);
+                self.frame.fill(hsv.to_rgb());
+            }
+            RgbLightMode::Swirl => {
+                for (i, led) in self.frame.iter_mut().enumerate() {
+                    let offset = (i * 256 / N) as u8;
+                    *led = Hsv::new(
+                        base.h.wrapping_add(phase).wrapping_add(offset),
+                        base.s,
+                        base.v,
+                    )
+                    .to_rgb();
+                }
+            }
+            RgbLightMode::Snake => {
+                self.frame.fill(Rgb::OFF);
+                if N == 0 {
+                    return;
+                }
+                let head = (self.tick / 8) as usize % N;
+                for k in 0..SNAKE_LENGTH.min(N) {
+                    // The tail fades out
+                    let fade = (255 - k * 255 / SNAKE_LENGTH) as u8;
+                    self.frame[(head + N - k) % N] = base.to_rgb().scale(fade);
+                }
+            }
+        }
+    }
+}
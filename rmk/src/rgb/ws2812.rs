@@ -0,0 +1,70 @@
+//! WS2812 driver using SPI
+//!
+//! Each bit of WS2812 data is encoded as 4 SPI bits, so the SPI frequency should be about 3.2MHz.
+//! Only MOSI is used, which should be connected to the data pin of the LED strip.
+
+use core::future::Future;
+
+use embedded_hal_async::spi::SpiBus;
+
+use super::{color::Rgb, LedStripWriter};
+
+/// SPI bytes of a single LED
+const BYTES_PER_LED: usize = 12;
+/// Zero bytes sent after the frame to latch the colors, which is longer than 80us at 3.2MHz
+const RESET_BYTES: usize = 40;
+
+/// WS2812 LED strip driven by SPI
+pub struct Ws2812Spi<'a, S: SpiBus<u8>> {
+    spi: S,
+    /// Buffer of encoded SPI data, whose size should be at least [`Ws2812Spi::buffer_size`]
+    buffer: &'a mut [u8],
+}
+
+impl<'a, S: SpiBus<u8>> Ws2812Spi<'a, S> {
+    pub fn new(spi: S, buffer: &'a mut [u8]) -> Self {
+        Self { spi, buffer }
+    }
+
+    /// Size of the buffer needed for `num_leds` LEDs
+    pub const fn buffer_size(num_leds: usize) -> usize {
+        num_leds * BYTES_PER_LED + RESET_BYTES
+    }
+
+    /// Encode a byte of color as 4 SPI bytes, `0` is `0b1000` and `1` is `0b1110`, MSB first
+    fn encode_byte(byte: u8, out: &mut [u8]) {
+        for (i, o) in out.iter_mut().enumerate() {
+            let bits = byte << (i * 2);
+            let high = if bits & 0x80 != 0 { 0xE0 } else { 0x80 };
+            let low = if bits & 0x40 != 0 { 0x0E } else { 0x08 };
+            *o = high | low;
+        }
+    }
+}
+
+impl<S: SpiBus<u8>> LedStripWriter for Ws2812Spi<'_, S> {
+    type Error = S::Error;
+
+    fn write(&mut self, colors: &[Rgb]) -> impl Future<Output = Result<(), Self::Error>> {
+        let num_leds = colors
+            .len()
+            .min(self.buffer.len().saturating_sub(RESET_BYTES) / BYTES_PER_LED);
+        for (color, out) in colors
+            .iter()
+            .zip(self.buffer.chunks_exact_mut(BYTES_PER_LED))
+            .take(num_leds)
+        {
+            // WS2812 uses GRB order
+            Self::encode_byte(color.g, &mut out[0..4]);
+            Self::encode_byte(color.r, &mut out[4..8]);
+            Self::encode_byte(color.b, &mut out[8..12]);
+        }
+        let len = num_leds * BYTES_PER_LED;
+        let end = (len + RESET_BYTES).min(self.buffer.len());
+        self.buffer[len..end].fill(0);
+        async move {
+            self.spi.write(&self.buffer[..end]).await?;
+            self.spi.flush().await
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Step sequencer, modelled on QMK's sequencer
+//!
+//! The sequencer plays the configured MIDI note of each active track at every step,
+//! the step duration is determined by the tempo and the resolution.
+//! Notes are sent to the host via the USB MIDI interface.
+
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::{
+    config::{SequencerConfig, SequencerResolution, SEQUENCER_STEPS},
+    keycode::KeyCode,
+    midi::{MidiMessage, MIDI_CHANNEL},
+};
+
+pub(crate) static SEQUENCER_CHANNEL: Channel<CriticalSectionRawMutex, SequencerAction, 4> =
+    Channel::new();
+
+/// Time between the note on and note off of a step
+const NOTE_RELEASE_TIMEOUT: Duration = Duration::from_millis(30);
+
+/// Sequencer control action
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum SequencerAction {
+    On,
+    Off,
+    Toggle,
+    TempoDown,
+    TempoUp,
+    ResolutionDown,
+    ResolutionUp,
+    /// Enable all steps for active tracks
+    StepsAll,
+    /// Disable all steps for active tracks
+    StepsClear,
+    /// Toggle the step for active tracks
+    ToggleStep(u8),
+    /// Select the track as the only active track, or deactivate all tracks if it's active
+    SelectTrack(u8),
+}
+
+impl SequencerAction {
+    /// Convert a `Sequencer*` keycode to sequencer action
+    pub(crate) fn from_keycode(key: KeyCode) -> Option<Self> {
+        match key {
+            KeyCode::SequencerOn => Some(Self::On),
+            KeyCode::SequencerOff => Some(Self::Off),
+            KeyCode::SequencerToggle => Some(Self::Toggle),
+            KeyCode::SequencerTempoDown => Some(Self::TempoDown),
+            KeyCode::SequencerTempoUp => Some(Self::TempoUp),
+            KeyCode::SequencerResolutionDown => Some(Self::ResolutionDown),
+            KeyCode::SequencerResolutionUp => Some(Self::ResolutionUp),
+            KeyCode::SequencerStepsAll => Some(Self::StepsAll),
+            KeyCode::SequencerStepsClear => Some(Self::StepsClear),
+            _ if KeyCode::SequencerStep0 <= key && key <= KeyCode::SequencerStep15 => Some(
+                Self::ToggleStep((key as u16 - KeyCode::SequencerStep0 as u16) as u8),
+            ),
+            _ if KeyCode::SequencerTrack0 <= key && key <= KeyCode::SequencerTrack7 => Some(
+                Self::SelectTrack((key as u16 - KeyCode::SequencerTrack0 as u16) as u8),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl SequencerResolution {
+    fn prev(self) -> Self {
+        match self {
+            Self::Res2 | Self::Res2T => Self::Res2,
+            Self::Res4 => Self::Res2T,
+            Self::Res4T => Self::Res4,
+            Self::Res8 => Self::Res4T,
+            Self::Res8T => Self::Res8,
+            Self::Res16 => Self::Res8T,
+            Self::Res16T => Self::Res16,
+            Self::Res32 => Self::Res16T,
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Self::Res2 => Self::Res2T,
+            Self::Res2T => Self::Res4,
+            Self::Res4 => Self::Res4T,
+            Self::Res4T => Self::Res8,
+            Self::Res8 => Self::Res8T,
+            Self::Res8T => Self::Res16,
+            Self::Res16 => Self::Res16T,
+            Self::Res16T | Self::Res32 => Self::Res32,
+        }
+    }
+
+    /// Get the step duration from the duration of a beat(quarter note)
+    fn step_duration(self, beat: Duration) -> Duration {
+        match self {
+            Self::Res2 => beat * 2,
+            Self::Res2T => beat * 4 / 3,
+            Self::Res4 => beat,
+            Self::Res4T => beat * 2 / 3,
+            Self::Res8 => beat / 2,
+            Self::Res8T => beat / 3,
+            Self::Res16 => beat / 4,
+            Self::Res16T => beat / 6,
+            Self::Res32 => beat / 8,
+        }
+    }
+}
+
+pub(crate) struct Sequencer {
+    enabled: bool,
+    config: SequencerConfig,
+    current_step: usize,
+}
+
+impl Sequencer {
+    pub(crate) fn new(config: SequencerConfig) -> Self {
+        Self {
+            enabled: false,
+            config,
+            current_step: 0,
+        }
+    }
+
+    /// Sequencer task, which plays the steps and processes sequencer actions
+    pub(crate) async fn run(&mut self) {
+        loop {
+            if !self.enabled {
+                let action = SEQUENCER_CHANNEL.receive().await;
+                self.process_action(action);
+                continue;
+            }
+
+            let step_start = Instant::now();
+            let step_end = step_start + self.step_duration();
+            let tracks = self.config.steps[self.current_step];
+            self.send_notes(tracks, true);
+
+            // Release the notes after a short time, then wait until the next step
+            let release_at = step_start + NOTE_RELEASE_TIMEOUT.min(self.step_duration() / 2);
+            let mut released = false;
+            loop {
+                let deadline = if released { step_end } else { release_at };
+                match select(Timer::at(deadline), SEQUENCER_CHANNEL.receive()).await {
+                    Either::First(_) => {
+                        if released {
+                            break;
+                        }
+                        self.send_notes(tracks, false);
+                        released = true;
+                    }
+                    Either::Second(action) => {
+                        self.process_action(action);
+                        if !self.enabled {
+                            break;
+                        }
+                    }
+                }
+            }
+            if !released {
+                self.send_notes(tracks, false);
+            }
+
+            self.current_step = (self.current_step + 1) % SEQUENCER_STEPS;
+        }
+    }
+
+    fn process_action(&mut self, action: SequencerAction) {
+        debug!("Sequencer action: {:?}", action);
+        match action {
+            SequencerAction::On => self.set_enabled(true),
+            SequencerAction::Off => self.set_enabled(false),
+            SequencerAction::Toggle => self.set_enabled(!self.enabled),
+            SequencerAction::TempoDown => {
+                self.config.tempo = self.config.tempo.saturating_sub(1).max(1)
+            }
+            SequencerAction::TempoUp => self.config.tempo = self.config.tempo.saturating_add(1),
+            SequencerAction::ResolutionDown => {
+                self.config.resolution = self.config.resolution.prev()
+            }
+            SequencerAction::ResolutionUp => self.config.resolution = self.config.resolution.next(),
+            SequencerAction::StepsAll => self
+                .config
+                .steps
+                .iter_mut()
+                .for_each(|s| *s |= self.config.active_tracks),
+            SequencerAction::StepsClear => self
+                .config
+                .steps
+                .iter_mut()
+                .for_each(|s| *s &= !self.config.active_tracks),
+            SequencerAction::ToggleStep(step) => {
+                if let Some(s) = self.config.steps.get_mut(step as usize) {
+                    *s ^= self.config.active_tracks;
+                }
+            }
+            SequencerAction::SelectTrack(track) => {
+                // Same as QMK's `SQ_T(n)`
+                let track = 1 << track;
+                self.config.active_tracks = if self.config.active_tracks & track != 0 {
+                    0
+                } else {
+                    track
+                };
+            }
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        if enabled && !self.enabled {
+            // Always start from the first step
+            self.current_step = 0;
+        }
+        self.enabled = enabled;
+    }
+
+    fn step_duration(&self) -> Duration {
+        let beat = Duration::from_millis(60_000 / self.config.tempo.max(1) as u64);
+        self.config.resolution.step_duration(beat)
+    }
+
+    /// Send note on/off of the given tracks
+    fn send_notes(&self, tracks: u8, on: bool) {
+        for (track, &note) in self.config.track_notes.iter().enumerate() {
+            if tracks & (1 << track) == 0 {
+                continue;
+            }
+            let message = if on {
+                MidiMessage::NoteOn {
+                    channel: self.config.channel,
+                    note,
+                    velocity: self.config.velocity,
+                }
+            } else {
+                MidiMessage::NoteOff {
+                    channel: self.config.channel,
+                    note,
+                    velocity: 0,
+                }
+            };
+            if MIDI_CHANNEL.try_send(message).is_err() {
+                warn!("MIDI channel is full, drop sequencer note: {}", note);
+            }
+        }
+    }
+}
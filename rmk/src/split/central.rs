@@ -10,18 +10,24 @@ use embedded_hal_async::digital::Wait;
 use crate::action::KeyAction;
 #[cfg(feature = "_nrf_ble")]
 use crate::ble::nrf::initialize_nrf_ble_keyboard_and_run;
+use crate::bootloader::set_bootloader;
 use crate::config::RmkConfig;
 #[cfg(not(feature = "rapid_debouncer"))]
 use crate::debounce::default_bouncer::DefaultDebouncer;
 #[cfg(feature = "rapid_debouncer")]
 use crate::debounce::fast_debouncer::RapidDebouncer;
 use crate::debounce::{DebounceState, DebouncerTrait};
-use crate::event::KeyEvent;
-use crate::keyboard::{Keyboard, KEYBOARD_REPORT_CHANNEL, KEY_EVENT_CHANNEL};
+use crate::event::{Event, KeyEvent};
+use crate::keyboard::{Keyboard, EVENT_CHANNEL, KEYBOARD_REPORT_CHANNEL};
 use crate::keymap::KeyMap;
 use crate::light::LightService;
 use crate::matrix::{KeyState, MatrixTrait};
+use crate::raw_hid::set_raw_hid_handler;
 use crate::run_usb_keyboard;
+#[cfg(feature = "midi")]
+use crate::sequencer::Sequencer;
+#[cfg(feature = "steno")]
+use crate::steno::Steno;
 use crate::usb::KeyboardUsbDevice;
 use crate::via::process::VialService;
 
@@ -297,11 +303,18 @@ pub async fn initialize_usb_split_central_and_run<
     // For USB keyboard, the "external" storage means the storage initialized by the user.
     #[cfg(any(feature = "_nrf_ble", not(feature = "_no_external_storage")))]
     let (mut storage, keymap) = {
-        let mut s = Storage::new(flash, default_keymap, keyboard_config.storage_config).await;
+        let mut s = Storage::new(
+            flash,
+            default_keymap,
+            keyboard_config.default_macros,
+            keyboard_config.storage_config,
+        )
+        .await;
         let keymap = RefCell::new(
             KeyMap::<TOTAL_ROW, TOTAL_COL, NUM_LAYER>::new_from_storage(
                 default_keymap,
                 Some(&mut s),
+                keyboard_config.default_macros,
             )
             .await,
         );
@@ -309,7 +322,17 @@ pub async fn initialize_usb_split_central_and_run<
     };
 
     #[cfg(all(not(feature = "_nrf_ble"), feature = "_no_external_storage"))]
-    let keymap = RefCell::new(KeyMap::<TOTAL_ROW, TOTAL_COL, NUM_LAYER>::new(default_keymap).await);
+    let keymap = RefCell::new(
+        KeyMap::<TOTAL_ROW, TOTAL_COL, NUM_LAYER>::new(
+            default_keymap,
+            keyboard_config.default_macros,
+        )
+        .await,
+    );
+
+    // The bootloader is used by both the keyboard and Via
+    set_bootloader(keyboard_config.bootloader);
+    set_raw_hid_handler(keyboard_config.raw_hid_handler);
 
     let keyboard_report_sender = KEYBOARD_REPORT_CHANNEL.sender();
     let keyboard_report_receiver = KEYBOARD_REPORT_CHANNEL.receiver();
@@ -320,11 +343,17 @@ pub async fn initialize_usb_split_central_and_run<
             &keymap,
             &keyboard_report_sender,
             keyboard_config.behavior_config,
+            keyboard_config.hooks,
+            keyboard_config.processor,
         ),
         KeyboardUsbDevice::new(usb_driver, keyboard_config.usb_config),
         VialService::new(&keymap, keyboard_config.vial_config),
         LightService::from_config(keyboard_config.light_config),
     );
+    #[cfg(feature = "midi")]
+    let mut sequencer = Sequencer::new(keyboard_config.sequencer_config);
+    #[cfg(feature = "steno")]
+    let mut steno = Steno::new(keyboard_config.steno_config);
 
     // Run usb keyboard
     run_usb_keyboard(
@@ -335,6 +364,10 @@ pub async fn initialize_usb_split_central_and_run<
         &mut storage,
         &mut light_service,
         &mut vial_service,
+        #[cfg(feature = "midi")]
+        &mut sequencer,
+        #[cfg(feature = "steno")]
+        &mut steno,
         &keyboard_report_receiver,
     )
     .await
@@ -420,12 +453,12 @@ impl<
                                 self.key_states[out_idx][in_idx],
                             );
 
-                            KEY_EVENT_CHANNEL
-                                .send(KeyEvent {
+                            EVENT_CHANNEL
+                                .send(Event::Key(KeyEvent {
                                     row,
                                     col,
                                     pressed: key_state.pressed,
-                                })
+                                }))
                                 .await;
                         }
                         _ => (),
@@ -650,12 +683,12 @@ impl<
                                     self.key_states[row_idx][col_idx],
                                 );
 
-                                KEY_EVENT_CHANNEL
-                                    .send(KeyEvent {
+                                EVENT_CHANNEL
+                                    .send(Event::Key(KeyEvent {
                                         row,
                                         col,
                                         pressed: key_state.pressed,
-                                    })
+                                    }))
                                     .await;
                             }
                             _ => (),
@@ -4,7 +4,10 @@ use core::sync::atomic::Ordering;
 ///!
 use super::SplitMessage;
 use crate::CONNECTION_STATE;
-use crate::{event::KeyEvent, keyboard::KEY_EVENT_CHANNEL};
+use crate::{
+    event::{Event, KeyEvent},
+    keyboard::EVENT_CHANNEL,
+};
 use embassy_futures::select::select;
 
 #[derive(Debug, Clone, Copy)]
@@ -62,7 +65,7 @@ impl<
 
     /// Run the monitor.
     ///
-    /// The monitor receives from the peripheral and forward the message to `KEY_EVENT_CHANNEL`.
+    /// The monitor receives from the peripheral and forward the key event to `EVENT_CHANNEL`.
     pub(crate) async fn run(mut self) -> ! {
         let mut conn_state = CONNECTION_STATE.load(Ordering::Acquire);
         // Send once on start
@@ -87,12 +90,12 @@ impl<
 
                             if CONNECTION_STATE.load(core::sync::atomic::Ordering::Acquire) {
                                 // Only when the connection is established, send the key event.
-                                KEY_EVENT_CHANNEL
-                                    .send(KeyEvent {
+                                EVENT_CHANNEL
+                                    .send(Event::Key(KeyEvent {
                                         row: e.row + ROW_OFFSET as u8,
                                         col: e.col + COL_OFFSET as u8,
                                         pressed: e.pressed,
-                                    })
+                                    }))
                                     .await;
                             } else {
                                 warn!("Key event from peripheral is ignored because the connection is not established.");
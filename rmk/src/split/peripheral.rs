@@ -6,7 +6,8 @@ use crate::debounce::default_bouncer::DefaultDebouncer;
 use crate::debounce::fast_debouncer::RapidDebouncer;
 use crate::debounce::DebouncerTrait;
 use crate::direct_pin::DirectPinMatrix;
-use crate::keyboard::KEY_EVENT_CHANNEL;
+use crate::event::Event;
+use crate::keyboard::EVENT_CHANNEL;
 use crate::matrix::{Matrix, MatrixTrait};
 use crate::CONNECTION_STATE;
 #[cfg(feature = "_nrf_ble")]
@@ -185,7 +186,7 @@ impl<S: SplitWriter + SplitReader> SplitPeripheral<S> {
     /// If also receives split messages from the central through `SplitReader`.
     pub(crate) async fn run(&mut self) -> ! {
         loop {
-            match select(self.split_driver.read(), KEY_EVENT_CHANNEL.receive()).await {
+            match select(self.split_driver.read(), EVENT_CHANNEL.receive()).await {
                 embassy_futures::select::Either::First(m) => match m {
                     // Currently only handle the central state message
                     Ok(split_message) => match split_message {
@@ -200,6 +201,10 @@ impl<S: SplitWriter + SplitReader> SplitPeripheral<S> {
                     }
                 },
                 embassy_futures::select::Either::Second(e) => {
+                    // Only key events are sent to the central
+                    let Event::Key(e) = e else {
+                        continue;
+                    };
                     // Only send the key event if the connection is established
                     if CONNECTION_STATE.load(core::sync::atomic::Ordering::Acquire) {
                         info!("Writing split message to central");
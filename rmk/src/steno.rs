@@ -0,0 +1,191 @@
+//! Stenography support, modelled on QMK's steno
+//!
+//! Steno keys are collected by [`Steno`] into a chord, which is sent to the host when all steno keys are released.
+//! GeminiPR and TX Bolt chords are sent via the USB CDC-ACM(serial) interface,
+//! the Plover HID protocol sends the state of all steno keys via a vendor defined hid interface.
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_usb::{class::cdc_acm::CdcAcmClass, driver::Driver};
+use heapless::Vec;
+
+use crate::{
+    config::{StenoConfig, StenoProtocol},
+    hid::{HidWriterWrapper, UsbHidWriter},
+    keycode::KeyCode,
+};
+
+/// Steno key events from the keyboard, `(keycode, pressed)`
+pub(crate) static STENO_CHANNEL: Channel<CriticalSectionRawMutex, (KeyCode, bool), 16> =
+    Channel::new();
+
+/// Report id of Plover HID report
+const PLOVER_HID_REPORT_ID: u8 = 0x50;
+
+/// TX Bolt code of each steno key, indexed by `keycode - StenoFn`.
+/// The highest 2 bits are the group of the key, 0 means that the key isn't supported by TX Bolt.
+const TX_BOLT_MAP: [u8; NUM_STENO_KEYS] = [
+    0x00, // Fn
+    0xD0, 0xD0, 0xD0, 0xD0, 0xD0, 0xD0, // #1 ~ #6
+    0x01, 0x01, // S1-, S2-
+    0x02, 0x04, 0x08, 0x10, 0x20, 0x41, // T- K- P- W- H- R-
+    0x42, 0x44, // A- O-
+    0x48, 0x48, // *1 *2
+    0x00, 0x00, 0x00, // res1 res2 pwr
+    0x48, 0x48, // *3 *4
+    0x50, 0x60, // -E -U
+    0x81, 0x82, 0x84, 0x88, 0x90, 0xA0, 0xC1, 0xC2, 0xC4, // -F -R -P -B -L -G -T -S -D
+    0xD0, 0xD0, 0xD0, 0xD0, 0xD0, 0xD0, // #7 ~ #C
+    0xC8, // -Z
+];
+
+/// Bit index in Plover HID report of each steno key, indexed by `keycode - StenoFn`.
+///
+/// Plover HID key order: `S1- S2- T- K- P- W- H- R- A- O- *1 *2 *3 *4 -E -U -F -R -P -B -L -G -T -S -D -Z #1 ~ #C X1 ~ X26`,
+/// keys which have no Plover equivalent are mapped to `X1 ~ X4`.
+const PLOVER_HID_MAP: [u8; NUM_STENO_KEYS] = [
+    38, // Fn -> X1
+    26, 27, 28, 29, 30, 31, // #1 ~ #6
+    0, 1, // S1-, S2-
+    2, 3, 4, 5, 6, 7, // T- K- P- W- H- R-
+    8, 9, // A- O-
+    10, 11, // *1 *2
+    39, 40, 41, // res1 res2 pwr -> X2 ~ X4
+    12, 13, // *3 *4
+    14, 15, // -E -U
+    16, 17, 18, 19, 20, 21, 22, 23, 24, // -F -R -P -B -L -G -T -S -D
+    32, 33, 34, 35, 36, 37, // #7 ~ #C
+    25, // -Z
+];
+
+/// Number of steno keys: `StenoFn` ~ `StenoZR`
+const NUM_STENO_KEYS: usize = (KeyCode::StenoZR as u16 - KeyCode::StenoFn as u16 + 1) as usize;
+
+/// Steno chord processor
+pub(crate) struct Steno {
+    protocol: StenoProtocol,
+    /// Steno keys which are currently pressed, bit `n` is `StenoFn + n`
+    pressed: u64,
+    /// Steno keys which are pressed since the chord is started
+    chord: u64,
+}
+
+impl Steno {
+    pub(crate) fn new(config: StenoConfig) -> Self {
+        Self {
+            protocol: config.protocol,
+            pressed: 0,
+            chord: 0,
+        }
+    }
+
+    /// Steno task, which processes steno keys and sends steno chords to the host
+    pub(crate) async fn run<'d, D: Driver<'d>>(
+        &mut self,
+        serial: &mut CdcAcmClass<'d, D>,
+        hid_writer: &mut UsbHidWriter<'d, D, 9>,
+    ) {
+        loop {
+            let (key, pressed) = STENO_CHANNEL.receive().await;
+            if key.is_steno_key() {
+                self.process_steno_key(key, pressed, serial, hid_writer)
+                    .await;
+            } else if pressed {
+                match key {
+                    KeyCode::StenoBolt => self.set_protocol(StenoProtocol::TxBolt),
+                    KeyCode::StenoGemini => self.set_protocol(StenoProtocol::GeminiPR),
+                    _ => warn!("Unsupported steno key: {:?}", key),
+                }
+            }
+        }
+    }
+
+    async fn process_steno_key<'d, D: Driver<'d>>(
+        &mut self,
+        key: KeyCode,
+        pressed: bool,
+        serial: &mut CdcAcmClass<'d, D>,
+        hid_writer: &mut UsbHidWriter<'d, D, 9>,
+    ) {
+        let bit = 1u64 << (key as u16 - KeyCode::StenoFn as u16);
+        if pressed {
+            self.pressed |= bit;
+            self.chord |= bit;
+        } else {
+            self.pressed &= !bit;
+        }
+
+        match self.protocol {
+            StenoProtocol::PloverHid => {
+                // Plover does the chording, send the current state of all keys
+                let report = self.plover_hid_report();
+                if let Err(e) = hid_writer.write(&report).await {
+                    error!("Send Plover HID report error: {:?}", e);
+                }
+            }
+            StenoProtocol::GeminiPR | StenoProtocol::TxBolt => {
+                if self.pressed != 0 || self.chord == 0 {
+                    return;
+                }
+                let packet = if self.protocol == StenoProtocol::GeminiPR {
+                    self.gemini_packet()
+                } else {
+                    self.tx_bolt_packet()
+                };
+                self.chord = 0;
+                // Drop the chord if no steno software opens the serial port
+                if !serial.dtr() {
+                    warn!("Steno serial port isn't opened, drop chord");
+                    return;
+                }
+                debug!("Send steno packet: {:?}", packet.as_slice());
+                if let Err(e) = serial.write_packet(&packet).await {
+                    error!("Send steno packet error: {:?}", e);
+                }
+            }
+        }
+    }
+
+    fn set_protocol(&mut self, protocol: StenoProtocol) {
+        info!("Switch steno protocol to {:?}", protocol);
+        self.protocol = protocol;
+        self.pressed = 0;
+        self.chord = 0;
+    }
+
+    /// GeminiPR packet: 6 bytes, 7 keys per byte, the highest bit of the first byte is set
+    fn gemini_packet(&self) -> Vec<u8, 6> {
+        let mut packet = [0u8; 6];
+        packet[0] = 0x80;
+        for i in 0..NUM_STENO_KEYS {
+            if self.chord & (1 << i) != 0 {
+                packet[i / 7] |= 0x40 >> (i % 7);
+            }
+        }
+        Vec::from_slice(&packet).unwrap()
+    }
+
+    /// TX Bolt packet: a byte for each non-empty key group, ended with a zero byte
+    fn tx_bolt_packet(&self) -> Vec<u8, 6> {
+        let mut groups = [0u8; 4];
+        for (i, &code) in TX_BOLT_MAP.iter().enumerate() {
+            if self.chord & (1 << i) != 0 && code != 0 {
+                groups[(code >> 6) as usize] |= code;
+            }
+        }
+        let mut packet: Vec<u8, 6> = groups.into_iter().filter(|&g| g != 0).collect();
+        let _ = packet.push(0);
+        packet
+    }
+
+    /// Plover HID report: report id + 64-bit key bitmap, the first key is the highest bit of the first byte
+    fn plover_hid_report(&self) -> [u8; 9] {
+        let mut report = [0u8; 9];
+        report[0] = PLOVER_HID_REPORT_ID;
+        for (i, &idx) in PLOVER_HID_MAP.iter().enumerate() {
+            if self.pressed & (1 << i) != 0 {
+                report[1 + idx as usize / 8] |= 0x80 >> (idx % 8);
+            }
+        }
+        report
+    }
+}
@@ -8,6 +8,7 @@ pub(crate) struct Eeconfig {
     keymap_config: EeKeymapConfig,
     backlight_config: EeBacklightConfig,
     audio_config: EeAudioConfig,
+    haptic_config: EeHapticConfig,
     rgb_light_config: EeRgbLightConfig,
     layout_option: u32,
 }
@@ -51,13 +52,13 @@ pub(crate) struct EeKeymapConfig {
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub(crate) struct EeBacklightConfig {
     #[bits(1)]
-    enable: bool,
+    pub(crate) enable: bool,
     #[bits(1)]
-    breathing: bool,
+    pub(crate) breathing: bool,
     #[bits(1)]
     reserved: bool,
     #[bits(5)]
-    level: u8,
+    pub(crate) level: u8,
 }
 
 #[bitfield(u8, order = Msb)]
@@ -65,11 +66,31 @@ pub(crate) struct EeBacklightConfig {
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub(crate) struct EeAudioConfig {
     #[bits(1)]
-    enable: bool,
+    pub(crate) enable: bool,
     #[bits(1)]
-    clicky_enable: bool,
+    pub(crate) clicky_enable: bool,
     #[bits(6)]
-    level: u8,
+    pub(crate) level: u8,
+}
+
+#[bitfield(u32, order = Msb)]
+#[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct EeHapticConfig {
+    #[bits(1)]
+    pub(crate) enable: bool,
+    #[bits(1)]
+    pub(crate) key_press: bool,
+    #[bits(1)]
+    pub(crate) layer_change: bool,
+    #[bits(1)]
+    pub(crate) combo: bool,
+    #[bits(7)]
+    pub(crate) effect: u8,
+    #[bits(7)]
+    pub(crate) dwell: u8,
+    #[bits(14)]
+    _reserved: u16,
 }
 
 #[bitfield(u64, order = Msb)]
@@ -77,17 +98,17 @@ pub(crate) struct EeAudioConfig {
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub(crate) struct EeRgbLightConfig {
     #[bits(1)]
-    enable: bool,
+    pub(crate) enable: bool,
     #[bits(8)]
-    mode: u8,
+    pub(crate) mode: u8,
     #[bits(8)]
-    hue: u8,
+    pub(crate) hue: u8,
     #[bits(8)]
-    sat: u8,
+    pub(crate) sat: u8,
     #[bits(8)]
-    val: u8,
+    pub(crate) val: u8,
     #[bits(8)]
-    speed: u8,
+    pub(crate) speed: u8,
     #[bits(23)]
     _reserved: u32,
 }
@@ -1,4 +1,4 @@
-mod eeconfig;
+pub(crate) mod eeconfig;
 pub mod nor_flash;
 
 use crate::config::StorageConfig;
@@ -17,13 +17,21 @@ use sequential_storage::{
 #[cfg(feature = "_nrf_ble")]
 use {crate::ble::nrf::bonder::BondInfo, core::mem};
 
-use crate::keyboard_macro::MACRO_SPACE_SIZE;
+use crate::keyboard_macro::{MacroSequence, MACRO_SPACE_SIZE};
 use crate::{
-    action::KeyAction,
+    action::{KeyAction, KEY_ACTION_ENCODING_VERSION, KEY_ACTION_MAX_SIZE},
+    audio::{send_audio_event, AudioEvent},
+    backlight::{send_backlight_event, BacklightEvent},
+    haptic::{send_haptic_event, HapticEvent},
+    host_layout::HostLayout,
+    reboot_keyboard,
+    rgb::underglow::{send_rgb_light_event, RgbLightEvent},
     via::keycode_convert::{from_via_keycode, to_via_keycode},
 };
 
-use self::eeconfig::EeKeymapConfig;
+use self::eeconfig::{
+    EeAudioConfig, EeBacklightConfig, EeHapticConfig, EeKeymapConfig, EeRgbLightConfig,
+};
 
 // Sync messages from server to flash
 pub(crate) static FLASH_CHANNEL: Channel<CriticalSectionRawMutex, FlashOperationMessage, 4> =
@@ -39,7 +47,7 @@ pub(crate) enum FlashOperationMessage {
     // Current active BLE profile number
     #[cfg(feature = "_nrf_ble")]
     ActiveBleProfile(u8),
-    // Clear the storage
+    // Clear the storage, then reboot the keyboard
     Reset,
     // Clear info of given slot number
     ClearSlot(u8),
@@ -57,6 +65,16 @@ pub(crate) enum FlashOperationMessage {
     },
     // Current saved connection type
     ConnectionType(u8),
+    // Audio settings
+    AudioConfig(EeAudioConfig),
+    // Haptic settings
+    HapticConfig(EeHapticConfig),
+    // Keyboard layout of the host
+    HostLayout(HostLayout),
+    // Backlight settings
+    BacklightConfig(EeBacklightConfig),
+    // RGB light settings
+    RgbLightConfig(EeRgbLightConfig),
 }
 
 #[repr(u32)]
@@ -66,9 +84,17 @@ pub(crate) enum StorageKeys {
     RgbLightConfig,
     KeymapConfig,
     LayoutConfig,
-    KeymapKeys,
+    // Keymap keys saved as Via keycodes, they're migrated to `KeymapKeys` when the keymap is read
+    LegacyKeymapKeys,
     MacroData,
     ConnectionType,
+    AudioConfig,
+    HapticConfig,
+    // Keymap keys saved in the versioned `KeyAction` encoding
+    KeymapKeys,
+    // Version of the `KeyAction` encoding used by saved keymap keys
+    KeyActionVersion,
+    HostLayout,
     #[cfg(feature = "_nrf_ble")]
     ActiveBleProfile = 0xEE,
     #[cfg(feature = "_nrf_ble")]
@@ -83,8 +109,14 @@ impl StorageKeys {
             2 => Some(StorageKeys::RgbLightConfig),
             3 => Some(StorageKeys::KeymapConfig),
             4 => Some(StorageKeys::LayoutConfig),
-            5 => Some(StorageKeys::KeymapKeys),
+            5 => Some(StorageKeys::LegacyKeymapKeys),
             6 => Some(StorageKeys::MacroData),
+            7 => Some(StorageKeys::ConnectionType),
+            8 => Some(StorageKeys::AudioConfig),
+            9 => Some(StorageKeys::HapticConfig),
+            10 => Some(StorageKeys::KeymapKeys),
+            11 => Some(StorageKeys::KeyActionVersion),
+            12 => Some(StorageKeys::HostLayout),
             #[cfg(feature = "_nrf_ble")]
             0xEF => Some(StorageKeys::BleBondInfo),
             _ => None,
@@ -98,8 +130,15 @@ pub(crate) enum StorageData {
     LayoutConfig(LayoutConfig),
     KeymapConfig(EeKeymapConfig),
     KeymapKey(KeymapKey),
+    LegacyKeymapKey(KeymapKey),
+    KeyActionVersion(u8),
     MacroData([u8; MACRO_SPACE_SIZE]),
     ConnectionType(u8),
+    AudioConfig(EeAudioConfig),
+    HapticConfig(EeHapticConfig),
+    HostLayout(HostLayout),
+    BacklightConfig(EeBacklightConfig),
+    RgbLightConfig(EeRgbLightConfig),
     #[cfg(feature = "_nrf_ble")]
     BondInfo(BondInfo),
     #[cfg(feature = "_nrf_ble")]
@@ -147,13 +186,29 @@ impl Value<'_> for StorageData {
                 Ok(3)
             }
             StorageData::KeymapKey(k) => {
+                if buffer.len() < 5 + KEY_ACTION_MAX_SIZE {
+                    return Err(SerializationError::BufferTooSmall);
+                }
                 buffer[0] = StorageKeys::KeymapKeys as u8;
+                buffer[1] = KEY_ACTION_ENCODING_VERSION;
+                buffer[2] = k.layer as u8;
+                buffer[3] = k.col as u8;
+                buffer[4] = k.row as u8;
+                Ok(5 + k.action.serialize_into(&mut buffer[5..]))
+            }
+            StorageData::LegacyKeymapKey(k) => {
+                buffer[0] = StorageKeys::LegacyKeymapKeys as u8;
                 BigEndian::write_u16(&mut buffer[1..3], to_via_keycode(k.action));
                 buffer[3] = k.layer as u8;
                 buffer[4] = k.col as u8;
                 buffer[5] = k.row as u8;
                 Ok(6)
             }
+            StorageData::KeyActionVersion(v) => {
+                buffer[0] = StorageKeys::KeyActionVersion as u8;
+                buffer[1] = *v;
+                Ok(2)
+            }
             StorageData::MacroData(d) => {
                 if buffer.len() < MACRO_SPACE_SIZE + 1 {
                     return Err(SerializationError::BufferTooSmall);
@@ -167,6 +222,34 @@ impl Value<'_> for StorageData {
                 buffer[1] = *ty;
                 Ok(2)
             }
+            StorageData::AudioConfig(c) => {
+                buffer[0] = StorageKeys::AudioConfig as u8;
+                buffer[1] = c.into_bits();
+                Ok(2)
+            }
+            StorageData::BacklightConfig(c) => {
+                buffer[0] = StorageKeys::LedLightConfig as u8;
+                buffer[1] = c.into_bits();
+                Ok(2)
+            }
+            StorageData::RgbLightConfig(c) => {
+                if buffer.len() < 9 {
+                    return Err(SerializationError::BufferTooSmall);
+                }
+                buffer[0] = StorageKeys::RgbLightConfig as u8;
+                BigEndian::write_u64(&mut buffer[1..9], c.into_bits());
+                Ok(9)
+            }
+            StorageData::HapticConfig(c) => {
+                buffer[0] = StorageKeys::HapticConfig as u8;
+                BigEndian::write_u32(&mut buffer[1..5], c.into_bits());
+                Ok(5)
+            }
+            StorageData::HostLayout(l) => {
+                buffer[0] = StorageKeys::HostLayout as u8;
+                buffer[1] = *l as u8;
+                Ok(2)
+            }
             #[cfg(feature = "_nrf_ble")]
             StorageData::BondInfo(b) => {
                 if buffer.len() < 121 {
@@ -210,8 +293,17 @@ impl Value<'_> for StorageData {
                         }))
                     }
                 }
-                StorageKeys::LedLightConfig => Err(SerializationError::Custom(0)),
-                StorageKeys::RgbLightConfig => Err(SerializationError::Custom(0)),
+                StorageKeys::LedLightConfig => Ok(StorageData::BacklightConfig(
+                    EeBacklightConfig::from_bits(buffer[1]),
+                )),
+                StorageKeys::RgbLightConfig => {
+                    if buffer.len() < 9 {
+                        return Err(SerializationError::InvalidData);
+                    }
+                    Ok(StorageData::RgbLightConfig(EeRgbLightConfig::from_bits(
+                        BigEndian::read_u64(&buffer[1..9]),
+                    )))
+                }
                 StorageKeys::KeymapConfig => Ok(StorageData::KeymapConfig(
                     EeKeymapConfig::from_bits(BigEndian::read_u16(&buffer[1..3])),
                 )),
@@ -224,28 +316,60 @@ impl Value<'_> for StorageData {
                     }))
                 }
                 StorageKeys::KeymapKeys => {
+                    if buffer.len() < 6 {
+                        return Err(SerializationError::InvalidData);
+                    }
+                    // Keys saved by another version of encoding cannot be decoded
+                    if buffer[1] != KEY_ACTION_ENCODING_VERSION {
+                        return Err(SerializationError::InvalidFormat);
+                    }
+                    let layer = buffer[2] as usize;
+                    let col = buffer[3] as usize;
+                    let row = buffer[4] as usize;
+                    let action = KeyAction::deserialize_from(&buffer[5..])
+                        .ok_or(SerializationError::InvalidData)?;
+
+                    Ok(StorageData::KeymapKey(KeymapKey {
+                        row,
+                        col,
+                        layer,
+                        action,
+                    }))
+                }
+                StorageKeys::LegacyKeymapKeys => {
                     let action = from_via_keycode(BigEndian::read_u16(&buffer[1..3]));
                     let layer = buffer[3] as usize;
                     let col = buffer[4] as usize;
                     let row = buffer[5] as usize;
 
                     // row, col, layer are used to calculate key only, not used here
-                    Ok(StorageData::KeymapKey(KeymapKey {
+                    Ok(StorageData::LegacyKeymapKey(KeymapKey {
                         row,
                         col,
                         layer,
                         action,
                     }))
                 }
+                StorageKeys::KeyActionVersion => Ok(StorageData::KeyActionVersion(buffer[1])),
                 StorageKeys::MacroData => {
-                    if buffer.len() < MACRO_SPACE_SIZE + 1 {
-                        return Err(SerializationError::InvalidData);
-                    }
+                    // The saved macro buffer might be saved with a different `RMK_MACRO_SPACE_SIZE`,
+                    // it's truncated or padded with 0 to the current size
+                    let data = &buffer[1..];
+                    let len = data.len().min(MACRO_SPACE_SIZE);
                     let mut buf = [0_u8; MACRO_SPACE_SIZE];
-                    buf.copy_from_slice(&buffer[1..MACRO_SPACE_SIZE + 1]);
+                    buf[..len].copy_from_slice(&data[..len]);
                     Ok(StorageData::MacroData(buf))
                 }
                 StorageKeys::ConnectionType => Ok(StorageData::ConnectionType(buffer[1])),
+                StorageKeys::AudioConfig => Ok(StorageData::AudioConfig(EeAudioConfig::from_bits(
+                    buffer[1],
+                ))),
+                StorageKeys::HapticConfig => Ok(StorageData::HapticConfig(
+                    EeHapticConfig::from_bits(BigEndian::read_u32(&buffer[1..5])),
+                )),
+                StorageKeys::HostLayout => HostLayout::from_u8(buffer[1])
+                    .map(StorageData::HostLayout)
+                    .ok_or(SerializationError::InvalidData),
                 #[cfg(feature = "_nrf_ble")]
                 StorageKeys::BleBondInfo => {
                     // Make `transmute_copy` happy, because the compiler doesn't know the size of buffer
@@ -270,11 +394,17 @@ impl StorageData {
             StorageData::StorageConfig(_) => StorageKeys::StorageConfig as u32,
             StorageData::LayoutConfig(_) => StorageKeys::LayoutConfig as u32,
             StorageData::KeymapConfig(_) => StorageKeys::KeymapConfig as u32,
-            StorageData::KeymapKey(_) => {
+            StorageData::KeymapKey(_) | StorageData::LegacyKeymapKey(_) => {
                 panic!("To get storage key for KeymapKey, use `get_keymap_key` instead");
             }
+            StorageData::KeyActionVersion(_) => StorageKeys::KeyActionVersion as u32,
             StorageData::MacroData(_) => StorageKeys::MacroData as u32,
             StorageData::ConnectionType(_) => StorageKeys::ConnectionType as u32,
+            StorageData::AudioConfig(_) => StorageKeys::AudioConfig as u32,
+            StorageData::HapticConfig(_) => StorageKeys::HapticConfig as u32,
+            StorageData::HostLayout(_) => StorageKeys::HostLayout as u32,
+            StorageData::BacklightConfig(_) => StorageKeys::LedLightConfig as u32,
+            StorageData::RgbLightConfig(_) => StorageKeys::RgbLightConfig as u32,
             #[cfg(feature = "_nrf_ble")]
             StorageData::BondInfo(b) => get_bond_info_key(b.slot_num),
             #[cfg(feature = "_nrf_ble")]
@@ -345,6 +475,7 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
     pub(crate) async fn new(
         flash: F,
         keymap: &[[[KeyAction; COL]; ROW]; NUM_LAYER],
+        default_macros: Option<&MacroSequence>,
         config: StorageConfig,
     ) -> Self {
         // Check storage setting
@@ -352,6 +483,10 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
             config.num_sectors >= 2,
             "Number of used sector for storage must larger than 1"
         );
+        assert!(
+            macro_item_size::<F>() <= F::ERASE_SIZE,
+            "Macro space doesn't fit in a flash sector, decrease `RMK_MACRO_SPACE_SIZE`"
+        );
 
         // If config.start_addr == 0, use last `num_sectors` sectors or sectors begin at 0x0006_0000 for nRF52
         // Other wise, use storage config setting
@@ -401,7 +536,7 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
         if !storage.check_enable().await {
             // Initialize storage from keymap and config
             if storage
-                .initialize_storage_with_config(keymap)
+                .initialize_storage_with_config(keymap, default_macros)
                 .await
                 .is_err()
             {
@@ -419,6 +554,8 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
             }
         }
 
+        storage.load_device_configs().await;
+
         storage
     }
 
@@ -445,7 +582,15 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
                     )
                 }
                 FlashOperationMessage::Reset => {
-                    sequential_storage::erase_all(&mut self.flash, self.storage_range.clone()).await
+                    // Reboot, so that the storage is re-initialized with the default keymap
+                    if let Err(e) =
+                        sequential_storage::erase_all(&mut self.flash, self.storage_range.clone())
+                            .await
+                    {
+                        print_storage_error::<F>(e);
+                    }
+                    reboot_keyboard();
+                    Ok(())
                 }
                 FlashOperationMessage::DefaultLayer(default_layer) => {
                     // Read out layout options, update layer option and save back
@@ -508,6 +653,61 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
                     )
                     .await
                 }
+                FlashOperationMessage::AudioConfig(c) => {
+                    store_item(
+                        &mut self.flash,
+                        self.storage_range.clone(),
+                        &mut storage_cache,
+                        &mut self.buffer,
+                        &(StorageKeys::AudioConfig as u32),
+                        &StorageData::AudioConfig(c),
+                    )
+                    .await
+                }
+                FlashOperationMessage::BacklightConfig(c) => {
+                    store_item(
+                        &mut self.flash,
+                        self.storage_range.clone(),
+                        &mut storage_cache,
+                        &mut self.buffer,
+                        &(StorageKeys::LedLightConfig as u32),
+                        &StorageData::BacklightConfig(c),
+                    )
+                    .await
+                }
+                FlashOperationMessage::RgbLightConfig(c) => {
+                    store_item(
+                        &mut self.flash,
+                        self.storage_range.clone(),
+                        &mut storage_cache,
+                        &mut self.buffer,
+                        &(StorageKeys::RgbLightConfig as u32),
+                        &StorageData::RgbLightConfig(c),
+                    )
+                    .await
+                }
+                FlashOperationMessage::HapticConfig(c) => {
+                    store_item(
+                        &mut self.flash,
+                        self.storage_range.clone(),
+                        &mut storage_cache,
+                        &mut self.buffer,
+                        &(StorageKeys::HapticConfig as u32),
+                        &StorageData::HapticConfig(c),
+                    )
+                    .await
+                }
+                FlashOperationMessage::HostLayout(l) => {
+                    store_item(
+                        &mut self.flash,
+                        self.storage_range.clone(),
+                        &mut storage_cache,
+                        &mut self.buffer,
+                        &(StorageKeys::HostLayout as u32),
+                        &StorageData::HostLayout(l),
+                    )
+                    .await
+                }
                 #[cfg(feature = "_nrf_ble")]
                 FlashOperationMessage::ActiveBleProfile(profile) => {
                     let data = StorageData::ActiveBleProfile(profile);
@@ -560,39 +760,108 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
         }
     }
 
+    /// Read the saved keymap to `keymap`, and migrate keys saved in the legacy encoding.
+    ///
+    /// Returns an error if any item in the storage can't be read, the keymap isn't migrated in that case.
     pub(crate) async fn read_keymap(
         &mut self,
         keymap: &mut [[[KeyAction; COL]; ROW]; NUM_LAYER],
     ) -> Result<(), ()> {
         let mut storage_cache = NoCache::new();
-        if let Ok(mut key_iterator) = fetch_all_items::<u32, _, _>(
+        // Keymap keys are saved as Via keycodes if the version isn't saved
+        let legacy = !matches!(
+            fetch_item::<u32, StorageData, _>(
+                &mut self.flash,
+                self.storage_range.clone(),
+                &mut storage_cache,
+                &mut self.buffer,
+                &(StorageKeys::KeyActionVersion as u32),
+            )
+            .await,
+            Ok(Some(StorageData::KeyActionVersion(
+                KEY_ACTION_ENCODING_VERSION
+            )))
+        );
+
+        let mut key_iterator = fetch_all_items::<u32, _, _>(
             &mut self.flash,
             self.storage_range.clone(),
             &mut storage_cache,
             &mut self.buffer,
         )
         .await
+        .map_err(|e| print_storage_error::<F>(e))?;
+
+        // Iterator the storage, read all keymap keys.
+        // An item which can't be read aborts the reading, so that a partially read keymap is never migrated
+        while let Some((_key, item)) = key_iterator
+            .next::<u32, StorageData>(&mut self.buffer)
+            .await
+            .map_err(|e| print_storage_error::<F>(e))?
         {
-            // Iterator the storage, read all keymap keys
-            while let Ok(Some((_key, item))) = key_iterator
-                .next::<u32, StorageData>(&mut self.buffer)
-                .await
-            {
-                match item {
-                    StorageData::KeymapKey(key) => {
-                        assert!(key.layer < NUM_LAYER);
-                        assert!(key.row < ROW);
-                        assert!(key.col < COL);
-                        keymap[key.layer][key.row][key.col] = key.action;
-                    }
-                    _ => continue,
+            match item {
+                StorageData::KeymapKey(key) => {
+                    assert!(key.layer < NUM_LAYER);
+                    assert!(key.row < ROW);
+                    assert!(key.col < COL);
+                    keymap[key.layer][key.row][key.col] = key.action;
                 }
+                // Legacy keys are stale after the migration
+                StorageData::LegacyKeymapKey(key) if legacy => {
+                    assert!(key.layer < NUM_LAYER);
+                    assert!(key.row < ROW);
+                    assert!(key.col < COL);
+                    keymap[key.layer][key.row][key.col] = key.action;
+                }
+                _ => continue,
             }
-        };
+        }
+
+        // The version is saved after all keys are migrated
+        if legacy {
+            info!(
+                "Migrating keymap in storage to action encoding v{}",
+                KEY_ACTION_ENCODING_VERSION
+            );
+            self.save_keymap(keymap).await?;
+        }
 
         Ok(())
     }
 
+    /// Read the saved default layer and layout option
+    pub(crate) async fn read_layout_config(&mut self) -> Option<(u8, u32)> {
+        if let Ok(Some(StorageData::LayoutConfig(c))) = fetch_item::<u32, StorageData, _>(
+            &mut self.flash,
+            self.storage_range.clone(),
+            &mut NoCache::new(),
+            &mut self.buffer,
+            &(StorageKeys::LayoutConfig as u32),
+        )
+        .await
+        {
+            Some((c.default_layer, c.layout_option))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) async fn read_host_layout(&mut self) -> Option<HostLayout> {
+        if let Ok(Some(StorageData::HostLayout(l))) = fetch_item::<u32, StorageData, _>(
+            &mut self.flash,
+            self.storage_range.clone(),
+            &mut NoCache::new(),
+            &mut self.buffer,
+            &(StorageKeys::HostLayout as u32),
+        )
+        .await
+        {
+            Some(l)
+        } else {
+            None
+        }
+    }
+
     pub(crate) async fn read_macro_cache(&mut self, macro_cache: &mut [u8]) -> Result<(), ()> {
         // Read storage and send back from send_channel
         let read_data = fetch_item::<u32, StorageData, _>(
@@ -616,6 +885,7 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
     async fn initialize_storage_with_config(
         &mut self,
         keymap: &[[[KeyAction; COL]; ROW]; NUM_LAYER],
+        default_macros: Option<&MacroSequence>,
     ) -> Result<(), ()> {
         let mut cache = NoCache::new();
         // Save storage config
@@ -647,6 +917,30 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
         .await
         .map_err(|e| print_storage_error::<F>(e))?;
 
+        // Save default macros
+        if let Some(macros) = default_macros {
+            let macro_data = StorageData::MacroData(*macros.as_bytes());
+            store_item(
+                &mut self.flash,
+                self.storage_range.clone(),
+                &mut cache,
+                &mut self.buffer,
+                &macro_data.key(),
+                &macro_data,
+            )
+            .await
+            .map_err(|e| print_storage_error::<F>(e))?;
+        }
+
+        self.save_keymap(keymap).await
+    }
+
+    /// Save all keys of the keymap, then save the version of the `KeyAction` encoding
+    async fn save_keymap(
+        &mut self,
+        keymap: &[[[KeyAction; COL]; ROW]; NUM_LAYER],
+    ) -> Result<(), ()> {
+        let mut cache = NoCache::new();
         for (layer, layer_data) in keymap.iter().enumerate() {
             for (row, row_data) in layer_data.iter().enumerate() {
                 for (col, action) in row_data.iter().enumerate() {
@@ -673,7 +967,68 @@ impl<F: AsyncNorFlash, const ROW: usize, const COL: usize, const NUM_LAYER: usiz
             }
         }
 
-        Ok(())
+        let version = StorageData::KeyActionVersion(KEY_ACTION_ENCODING_VERSION);
+        store_item(
+            &mut self.flash,
+            self.storage_range.clone(),
+            &mut cache,
+            &mut self.buffer,
+            &version.key(),
+            &version,
+        )
+        .await
+        .map_err(|e| print_storage_error::<F>(e))
+    }
+
+    /// Read saved configs of devices, such as buzzer, haptic feedback and lights, and send them to the corresponding services
+    async fn load_device_configs(&mut self) {
+        if let Ok(Some(StorageData::AudioConfig(c))) = fetch_item::<u32, StorageData, _>(
+            &mut self.flash,
+            self.storage_range.clone(),
+            &mut NoCache::new(),
+            &mut self.buffer,
+            &(StorageKeys::AudioConfig as u32),
+        )
+        .await
+        {
+            send_audio_event(AudioEvent::LoadConfig(c));
+        }
+
+        if let Ok(Some(StorageData::HapticConfig(c))) = fetch_item::<u32, StorageData, _>(
+            &mut self.flash,
+            self.storage_range.clone(),
+            &mut NoCache::new(),
+            &mut self.buffer,
+            &(StorageKeys::HapticConfig as u32),
+        )
+        .await
+        {
+            send_haptic_event(HapticEvent::LoadConfig(c));
+        }
+
+        if let Ok(Some(StorageData::BacklightConfig(c))) = fetch_item::<u32, StorageData, _>(
+            &mut self.flash,
+            self.storage_range.clone(),
+            &mut NoCache::new(),
+            &mut self.buffer,
+            &(StorageKeys::LedLightConfig as u32),
+        )
+        .await
+        {
+            send_backlight_event(BacklightEvent::LoadConfig(c));
+        }
+
+        if let Ok(Some(StorageData::RgbLightConfig(c))) = fetch_item::<u32, StorageData, _>(
+            &mut self.flash,
+            self.storage_range.clone(),
+            &mut NoCache::new(),
+            &mut self.buffer,
+            &(StorageKeys::RgbLightConfig as u32),
+        )
+        .await
+        {
+            send_rgb_light_event(RgbLightEvent::LoadConfig(c));
+        }
     }
 
     async fn check_enable(&mut self) -> bool {
@@ -705,15 +1060,30 @@ fn print_storage_error<F: AsyncNorFlash>(e: SSError<F::Error>) {
     }
 }
 
+/// Size that the macro buffer takes in a flash sector.
+///
+/// The macro buffer is saved as a single item, which can't be split across sectors.
+/// A sector of `sequential-storage` also contains 2 page markers at both ends, and each item has an 8-byte header.
+const fn macro_item_size<F: AsyncNorFlash>() -> usize {
+    let word = if F::WRITE_SIZE > F::READ_SIZE {
+        F::WRITE_SIZE
+    } else {
+        F::READ_SIZE
+    };
+    // Item data = 4-byte key + 1-byte type + macro buffer
+    4 * word + 8usize.div_ceil(word) * word + (4 + 1 + MACRO_SPACE_SIZE).div_ceil(word) * word
+}
+
+/// Max value of `RMK_MACRO_SPACE_SIZE`, same as the limit in `build.rs`
+const MAX_MACRO_SPACE_SIZE: usize = 4000;
+
 const fn get_buffer_size() -> usize {
     // The buffer size needed = size_of(StorageData) = MACRO_SPACE_SIZE + 8(generally)
+    // The macro buffer saved by a firmware with larger `RMK_MACRO_SPACE_SIZE` should be readable as well,
+    // so the buffer is sized by the max macro space size.
     // According to doc of `sequential-storage`, for some flashes it should be aligned in 32 bytes
     // To make sure the buffer works, do this alignment always
-    let buffer_size = if MACRO_SPACE_SIZE < 248 {
-        256
-    } else {
-        MACRO_SPACE_SIZE + 8
-    };
+    let buffer_size = MAX_MACRO_SPACE_SIZE + 8;
 
     let remainder = buffer_size % 32;
 
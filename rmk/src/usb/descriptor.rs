@@ -1,7 +1,11 @@
+use serde::Serialize;
 use ssmarshal::serialize;
-use usbd_hid::descriptor::{
-    generator_prelude::*, MediaKeyboardReport, MouseReport, SystemControlReport,
-};
+use usbd_hid::descriptor::generator_prelude::*;
+
+/// Max number of consumer control keys, such as media keys, which can be pressed at the same time
+pub(crate) const NUM_CONSUMER_KEYS: usize = 4;
+/// Max number of system control keys which can be pressed at the same time
+pub(crate) const NUM_SYSTEM_KEYS: usize = 3;
 
 /// KeyboardReport describes a report and its companion descriptor that can be
 /// used to send keyboard button presses to a host and receive the status of the
@@ -45,8 +49,95 @@ pub(crate) struct ViaReport {
     pub(crate) output_data: [u8; 32],
 }
 
+/// JoystickReport describes a gamepad report with 32 buttons and 6 analog axes.
+/// It's sent via a separate hid interface, so no report id is needed.
+#[cfg(feature = "joystick")]
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = 0x05) = {
+        (usage_page = BUTTON, usage_min = 0x01, usage_max = 0x20) = {
+            #[packed_bits 32] #[item_settings data,variable,absolute] buttons=input;
+        };
+        (usage_page = GENERIC_DESKTOP,) = {
+            (usage = X,) = {
+                #[item_settings data,variable,absolute] x=input;
+            };
+            (usage = Y,) = {
+                #[item_settings data,variable,absolute] y=input;
+            };
+            (usage = 0x32,) = {
+                #[item_settings data,variable,absolute] z=input;
+            };
+            (usage = 0x33,) = {
+                #[item_settings data,variable,absolute] rx=input;
+            };
+            (usage = 0x34,) = {
+                #[item_settings data,variable,absolute] ry=input;
+            };
+            (usage = 0x35,) = {
+                #[item_settings data,variable,absolute] rz=input;
+            };
+        };
+    }
+)]
+#[cfg(feature = "joystick")]
+#[derive(Default)]
+pub struct JoystickReport {
+    pub(crate) buttons: u32,
+    pub(crate) x: i16,
+    pub(crate) y: i16,
+    pub(crate) z: i16,
+    pub(crate) rx: i16,
+    pub(crate) ry: i16,
+    pub(crate) rz: i16,
+}
+
+/// Consumer control report with multiple usages, so that media keys can be pressed together.
+/// Empty slots are filled with 0.
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL) = {
+        (usage_page = CONSUMER, usage_min = 0x00, usage_max = 0x514) = {
+            #[item_settings data,array,absolute,not_null] usage_ids=input;
+        };
+    }
+)]
+pub(crate) struct MultiMediaKeyboardReport {
+    pub(crate) usage_ids: [u16; NUM_CONSUMER_KEYS],
+}
+
+/// System control report with multiple usages, such as power and sleep keys.
+/// Empty slots are filled with 0.
+#[gen_hid_descriptor(
+    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = SYSTEM_CONTROL) = {
+        (usage_min = 0x81, usage_max = 0xB7, logical_min = 1) = {
+            #[item_settings data,array,absolute,not_null] usage_ids=input;
+        };
+    }
+)]
+pub(crate) struct MultiSystemControlReport {
+    pub(crate) usage_ids: [u8; NUM_SYSTEM_KEYS],
+}
+
+/// Report descriptor of Plover HID protocol, which is a vendor defined report with 64 key bits.
+/// See https://github.com/dnaq/plover-machine-hid
+#[cfg(feature = "steno")]
+pub(crate) const PLOVER_HID_DESC: &[u8] = &[
+    0x06, 0x50, 0xFF, // Usage Page (Vendor Defined 0xFF50)
+    0x0A, 0x56, 0x4C, // Usage (0x4C56)
+    0xA1, 0x01, // Collection (Application)
+    0x85, 0x50, //   Report ID (0x50)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x40, //   Report Count (64)
+    0x05, 0x0A, //   Usage Page (Ordinal)
+    0x19, 0x00, //   Usage Minimum (0)
+    0x29, 0x3F, //   Usage Maximum (63)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0xC0, // End Collection
+];
+
 /// Predefined report ids for composite hid report.
-/// Should be same with `#[gen_hid_descriptor]`
+/// Should be same with [`COMPOSITE_REPORT_DESC`]
 /// DO NOT EDIT
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -69,58 +160,149 @@ impl CompositeReportType {
     }
 }
 
+/// Resolution multiplier of the wheel and pan axes.
+/// When the host enables it in the feature report, a wheel or pan unit is 1/120 of a detent.
+pub(crate) const RESOLUTION_MULTIPLIER: i16 = 120;
+
+/// Size of the mouse report, without report id
+pub(crate) const MOUSE_REPORT_SIZE: usize = 9;
+
+/// Length of the report descriptor returned by [`composite_report_desc`]
+pub(crate) const COMPOSITE_REPORT_DESC_LEN: usize = 180;
+
+/// Report descriptor of mouse, consumer control and system control reports, with the given report ids.
+///
+/// The mouse report has 16-bit relative axes, and a feature report with the resolution multipliers
+/// of the wheel(bits 0-1) and pan(bits 2-3) axes, which enables high-resolution scrolling.
+/// It's written by hand because `#[gen_hid_descriptor]` doesn't support feature items and logical collections.
+#[rustfmt::skip]
+pub(crate) const fn composite_report_desc(
+    mouse_id: u8,
+    media_id: u8,
+    system_id: u8,
+) -> [u8; COMPOSITE_REPORT_DESC_LEN] {
+    [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x02, // Usage (Mouse)
+        0xA1, 0x01, // Collection (Application)
+        0x85, mouse_id, //   Report ID
+        0x09, 0x01, //   Usage (Pointer)
+        0xA1, 0x00, //   Collection (Physical)
+        0x05, 0x09, //     Usage Page (Button)
+        0x19, 0x01, //     Usage Minimum (1)
+        0x29, 0x08, //     Usage Maximum (8)
+        0x15, 0x00, //     Logical Minimum (0)
+        0x25, 0x01, //     Logical Maximum (1)
+        0x75, 0x01, //     Report Size (1)
+        0x95, 0x08, //     Report Count (8)
+        0x81, 0x02, //     Input (Data, Variable, Absolute)
+        0x05, 0x01, //     Usage Page (Generic Desktop)
+        0x09, 0x30, //     Usage (X)
+        0x09, 0x31, //     Usage (Y)
+        0x16, 0x01, 0x80, //     Logical Minimum (-32767)
+        0x26, 0xFF, 0x7F, //     Logical Maximum (32767)
+        0x75, 0x10, //     Report Size (16)
+        0x95, 0x02, //     Report Count (2)
+        0x81, 0x06, //     Input (Data, Variable, Relative)
+        0xA1, 0x02, //     Collection (Logical)
+        0x09, 0x48, //       Usage (Resolution Multiplier)
+        0x15, 0x00, //       Logical Minimum (0)
+        0x25, 0x01, //       Logical Maximum (1)
+        0x35, 0x01, //       Physical Minimum (1)
+        0x45, RESOLUTION_MULTIPLIER as u8, //       Physical Maximum (120)
+        0x75, 0x02, //       Report Size (2)
+        0x95, 0x01, //       Report Count (1)
+        0xB1, 0x02, //       Feature (Data, Variable, Absolute)
+        0x35, 0x00, //       Physical Minimum (0)
+        0x45, 0x00, //       Physical Maximum (0)
+        0x09, 0x38, //       Usage (Wheel)
+        0x16, 0x01, 0x80, //       Logical Minimum (-32767)
+        0x26, 0xFF, 0x7F, //       Logical Maximum (32767)
+        0x75, 0x10, //       Report Size (16)
+        0x95, 0x01, //       Report Count (1)
+        0x81, 0x06, //       Input (Data, Variable, Relative)
+        0xC0, //     End Collection
+        0xA1, 0x02, //     Collection (Logical)
+        0x09, 0x48, //       Usage (Resolution Multiplier)
+        0x15, 0x00, //       Logical Minimum (0)
+        0x25, 0x01, //       Logical Maximum (1)
+        0x35, 0x01, //       Physical Minimum (1)
+        0x45, RESOLUTION_MULTIPLIER as u8, //       Physical Maximum (120)
+        0x75, 0x02, //       Report Size (2)
+        0x95, 0x01, //       Report Count (1)
+        0xB1, 0x02, //       Feature (Data, Variable, Absolute)
+        0x35, 0x00, //       Physical Minimum (0)
+        0x45, 0x00, //       Physical Maximum (0)
+        0x05, 0x0C, //       Usage Page (Consumer)
+        0x0A, 0x38, 0x02, //       Usage (AC Pan)
+        0x16, 0x01, 0x80, //       Logical Minimum (-32767)
+        0x26, 0xFF, 0x7F, //       Logical Maximum (32767)
+        0x75, 0x10, //       Report Size (16)
+        0x95, 0x01, //       Report Count (1)
+        0x81, 0x06, //       Input (Data, Variable, Relative)
+        0xC0, //     End Collection
+        0x75, 0x04, //     Report Size (4)
+        0x95, 0x01, //     Report Count (1)
+        0xB1, 0x03, //     Feature (Constant, Variable, Absolute)
+        0xC0, //   End Collection
+        0xC0, // End Collection
+        0x05, 0x0C, // Usage Page (Consumer)
+        0x09, 0x01, // Usage (Consumer Control)
+        0xA1, 0x01, // Collection (Application)
+        0x85, media_id, //   Report ID
+        0x19, 0x00, //   Usage Minimum (0)
+        0x2A, 0x14, 0x05, //   Usage Maximum (0x514)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x26, 0x14, 0x05, //   Logical Maximum (0x514)
+        0x75, 0x10, //   Report Size (16)
+        0x95, NUM_CONSUMER_KEYS as u8, //   Report Count
+        0x81, 0x00, //   Input (Data, Array, Absolute)
+        0xC0, // End Collection
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x80, // Usage (System Control)
+        0xA1, 0x01, // Collection (Application)
+        0x85, system_id, //   Report ID
+        0x19, 0x81, //   Usage Minimum (0x81)
+        0x29, 0xB7, //   Usage Maximum (0xB7)
+        0x15, 0x01, //   Logical Minimum (1)
+        0x26, 0xB7, 0x00, //   Logical Maximum (0xB7)
+        0x75, 0x08, //   Report Size (8)
+        0x95, NUM_SYSTEM_KEYS as u8, //   Report Count
+        0x81, 0x00, //   Input (Data, Array, Absolute)
+        0xC0, // End Collection
+    ]
+}
+
+/// Report descriptor of the USB composite hid interface
+pub(crate) const COMPOSITE_REPORT_DESC: [u8; COMPOSITE_REPORT_DESC_LEN] = composite_report_desc(
+    CompositeReportType::Mouse as u8,
+    CompositeReportType::Media as u8,
+    CompositeReportType::System as u8,
+);
+
+/// Mouse report with 16-bit axes, see [`composite_report_desc`]
+#[derive(Serialize)]
+struct MouseReport {
+    buttons: u8,
+    x: i16,
+    y: i16,
+    wheel: i16,
+    pan: i16,
+}
+
 /// A composite hid report which contains mouse, consumer, system reports.
 /// Report id is used to distinguish from them.
-#[gen_hid_descriptor(
-    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = MOUSE) = {
-        (collection = PHYSICAL, usage = POINTER) = {
-            (report_id = 0x01,) = {
-                (usage_page = BUTTON, usage_min = BUTTON_1, usage_max = BUTTON_8) = {
-                    #[packed_bits 8] #[item_settings data,variable,absolute] buttons=input;
-                };
-                (usage_page = GENERIC_DESKTOP,) = {
-                    (usage = X,) = {
-                        #[item_settings data,variable,relative] x=input;
-                    };
-                    (usage = Y,) = {
-                        #[item_settings data,variable,relative] y=input;
-                    };
-                    (usage = WHEEL,) = {
-                        #[item_settings data,variable,relative] wheel=input;
-                    };
-                };
-                (usage_page = CONSUMER,) = {
-                    (usage = AC_PAN,) = {
-                        #[item_settings data,variable,relative] pan=input;
-                    };
-                };
-            };
-        };
-    },
-    (collection = APPLICATION, usage_page = CONSUMER, usage = CONSUMER_CONTROL) = {
-        (report_id = 0x02,) = {
-            (usage_page = CONSUMER, usage_min = 0x00, usage_max = 0x514) = {
-            #[item_settings data,array,absolute,not_null] media_usage_id=input;
-            }
-        };
-    },
-    (collection = APPLICATION, usage_page = GENERIC_DESKTOP, usage = SYSTEM_CONTROL) = {
-        (report_id = 0x03,) = {
-            (usage_min = 0x81, usage_max = 0xB7, logical_min = 1) = {
-                #[item_settings data,array,absolute,not_null] system_usage_id=input;
-            };
-        };
-    }
-)]
-#[derive(Default)]
+///
+/// The wheel and pan values are in high-resolution units, which are 1/[`RESOLUTION_MULTIPLIER`] of a detent.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CompositeReport {
     pub(crate) buttons: u8,
-    pub(crate) x: i8,
-    pub(crate) y: i8,
-    pub(crate) wheel: i8, // Scroll down (negative) or up (positive) this many units
-    pub(crate) pan: i8,   // Scroll left (negative) or right (positive) this many units
-    pub(crate) media_usage_id: u16,
-    pub(crate) system_usage_id: u8,
+    pub(crate) x: i16,
+    pub(crate) y: i16,
+    pub(crate) wheel: i16, // Scroll down (negative) or up (positive) this many units
+    pub(crate) pan: i16,   // Scroll left (negative) or right (positive) this many units
+    pub(crate) media_usage_ids: [u16; NUM_CONSUMER_KEYS],
+    pub(crate) system_usage_ids: [u8; NUM_SYSTEM_KEYS],
 }
 
 impl CompositeReport {
@@ -137,7 +319,7 @@ impl CompositeReport {
         data: &mut [u8],
         report_type: CompositeReportType,
     ) -> Result<usize, ssmarshal::Error> {
-        // Use usbd-hid's report to do serialization, but not so efficient.
+        // Use ssmarshal to do serialization, but not so efficient.
         match report_type {
             CompositeReportType::None => Ok(0),
             CompositeReportType::Mouse => {
@@ -151,17 +333,242 @@ impl CompositeReport {
                 Ok(serialize(data, &mouse_report)?)
             }
             CompositeReportType::Media => {
-                let consumer_report = MediaKeyboardReport {
-                    usage_id: self.media_usage_id,
+                let consumer_report = MultiMediaKeyboardReport {
+                    usage_ids: self.media_usage_ids,
                 };
                 Ok(serialize(data, &consumer_report)?)
             }
             CompositeReportType::System => {
-                let system_report = SystemControlReport {
-                    usage_id: self.system_usage_id,
+                let system_report = MultiSystemControlReport {
+                    usage_ids: self.system_usage_ids,
                 };
                 Ok(serialize(data, &system_report)?)
             }
         }
     }
 }
+
+/// Max number of contacts in the touchpad report
+#[cfg(feature = "touchpad")]
+pub(crate) const NUM_TOUCHPAD_CONTACTS: usize = 5;
+
+/// Max value of X/Y of the contacts in the touchpad report
+#[cfg(feature = "touchpad")]
+pub(crate) const TOUCHPAD_LOGICAL_MAX: u16 = 4095;
+
+/// Size of the touchpad input report, without report id
+#[cfg(feature = "touchpad")]
+pub(crate) const TOUCHPAD_REPORT_SIZE: usize = 6 * NUM_TOUCHPAD_CONTACTS + 4;
+
+/// Report ids of the touchpad interface.
+/// Should be same with [`TOUCHPAD_REPORT_DESC`]
+#[cfg(feature = "touchpad")]
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum TouchpadReportType {
+    /// Input report of contacts
+    Touchpad = 0x01,
+    /// Feature report of contact count maximum and pad type
+    Capabilities = 0x02,
+    /// Feature report of the certification status blob
+    Certification = 0x03,
+    /// Feature report of the input mode, 0 is mouse, 3 is touchpad
+    InputMode = 0x04,
+    /// Feature report of the surface switch and button switch
+    FunctionSwitch = 0x05,
+}
+
+#[cfg(feature = "touchpad")]
+impl TouchpadReportType {
+    pub(crate) fn from_u8(report_id: u8) -> Option<Self> {
+        match report_id {
+            0x01 => Some(Self::Touchpad),
+            0x02 => Some(Self::Capabilities),
+            0x03 => Some(Self::Certification),
+            0x04 => Some(Self::InputMode),
+            0x05 => Some(Self::FunctionSwitch),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "touchpad")]
+#[rustfmt::skip]
+const TOUCHPAD_HEAD_DESC: &[u8] = &[
+    0x05, 0x0D, // Usage Page (Digitizer)
+    0x09, 0x05, // Usage (Touch Pad)
+    0xA1, 0x01, // Collection (Application)
+    0x85, TouchpadReportType::Touchpad as u8, //   Report ID
+];
+
+/// Descriptor of a contact, it's repeated [`NUM_TOUCHPAD_CONTACTS`] times.
+/// The touchpad is reported as 10cm x 6.5cm.
+#[cfg(feature = "touchpad")]
+#[rustfmt::skip]
+const TOUCHPAD_CONTACT_DESC: &[u8] = &[
+    0x05, 0x0D, //   Usage Page (Digitizer)
+    0x09, 0x22, //   Usage (Finger)
+    0xA1, 0x02, //   Collection (Logical)
+    0x15, 0x00, //     Logical Minimum (0)
+    0x25, 0x01, //     Logical Maximum (1)
+    0x09, 0x47, //     Usage (Confidence)
+    0x09, 0x42, //     Usage (Tip Switch)
+    0x75, 0x01, //     Report Size (1)
+    0x95, 0x02, //     Report Count (2)
+    0x81, 0x02, //     Input (Data, Variable, Absolute)
+    0x95, 0x06, //     Report Count (6)
+    0x81, 0x03, //     Input (Constant, Variable, Absolute)
+    0x09, 0x51, //     Usage (Contact Identifier)
+    0x25, 0x7F, //     Logical Maximum (127)
+    0x75, 0x08, //     Report Size (8)
+    0x95, 0x01, //     Report Count (1)
+    0x81, 0x02, //     Input (Data, Variable, Absolute)
+    0x05, 0x01, //     Usage Page (Generic Desktop)
+    0x26, 0xFF, 0x0F, //     Logical Maximum (4095)
+    0x75, 0x10, //     Report Size (16)
+    0x55, 0x0E, //     Unit Exponent (-2)
+    0x65, 0x11, //     Unit (Centimeter)
+    0x35, 0x00, //     Physical Minimum (0)
+    0x46, 0xE8, 0x03, //     Physical Maximum (1000)
+    0x09, 0x30, //     Usage (X)
+    0x81, 0x02, //     Input (Data, Variable, Absolute)
+    0x46, 0x8A, 0x02, //     Physical Maximum (650)
+    0x09, 0x31, //     Usage (Y)
+    0x81, 0x02, //     Input (Data, Variable, Absolute)
+    0x65, 0x00, //     Unit (None)
+    0x55, 0x00, //     Unit Exponent (0)
+    0x45, 0x00, //     Physical Maximum (0)
+    0xC0, //   End Collection
+];
+
+#[cfg(feature = "touchpad")]
+#[rustfmt::skip]
+const TOUCHPAD_TAIL_DESC: &[u8] = &[
+    0x55, 0x0C, //   Unit Exponent (-4)
+    0x66, 0x01, 0x10, //   Unit (Seconds)
+    0x47, 0xFF, 0xFF, 0x00, 0x00, //   Physical Maximum (65535)
+    0x27, 0xFF, 0xFF, 0x00, 0x00, //   Logical Maximum (65535)
+    0x75, 0x10, //   Report Size (16)
+    0x95, 0x01, //   Report Count (1)
+    0x05, 0x0D, //   Usage Page (Digitizer)
+    0x09, 0x56, //   Usage (Scan Time)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x65, 0x00, //   Unit (None)
+    0x55, 0x00, //   Unit Exponent (0)
+    0x45, 0x00, //   Physical Maximum (0)
+    0x09, 0x54, //   Usage (Contact Count)
+    0x25, 0x7F, //   Logical Maximum (127)
+    0x75, 0x08, //   Report Size (8)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x05, 0x09, //   Usage Page (Button)
+    0x09, 0x01, //   Usage (Button 1)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x95, 0x07, //   Report Count (7)
+    0x81, 0x03, //   Input (Constant, Variable, Absolute)
+    0x05, 0x0D, //   Usage Page (Digitizer)
+    0x85, TouchpadReportType::Capabilities as u8, //   Report ID
+    0x09, 0x55, //   Usage (Contact Count Maximum)
+    0x09, 0x59, //   Usage (Pad Type)
+    0x25, 0x0F, //   Logical Maximum (15)
+    0x75, 0x04, //   Report Size (4)
+    0x95, 0x02, //   Report Count (2)
+    0xB1, 0x02, //   Feature (Data, Variable, Absolute)
+    0x06, 0x00, 0xFF, //   Usage Page (Vendor Defined 0xFF00)
+    0x85, TouchpadReportType::Certification as u8, //   Report ID
+    0x09, 0xC5, //   Usage (0xC5), certification blob set by `TouchpadProcessor::with_certification`
+    0x26, 0xFF, 0x00, //   Logical Maximum (255)
+    0x75, 0x08, //   Report Size (8)
+    0x96, 0x00, 0x01, //   Report Count (256)
+    0xB1, 0x02, //   Feature (Data, Variable, Absolute)
+    0xC0, // End Collection
+    0x05, 0x0D, // Usage Page (Digitizer)
+    0x09, 0x0E, // Usage (Device Configuration)
+    0xA1, 0x01, // Collection (Application)
+    0x85, TouchpadReportType::InputMode as u8, //   Report ID
+    0x09, 0x22, //   Usage (Finger)
+    0xA1, 0x02, //   Collection (Logical)
+    0x09, 0x52, //     Usage (Input Mode)
+    0x15, 0x00, //     Logical Minimum (0)
+    0x25, 0x0A, //     Logical Maximum (10)
+    0x75, 0x08, //     Report Size (8)
+    0x95, 0x01, //     Report Count (1)
+    0xB1, 0x02, //     Feature (Data, Variable, Absolute)
+    0xC0, //   End Collection
+    0x09, 0x22, //   Usage (Finger)
+    0xA1, 0x00, //   Collection (Physical)
+    0x85, TouchpadReportType::FunctionSwitch as u8, //     Report ID
+    0x09, 0x57, //     Usage (Surface Switch)
+    0x09, 0x58, //     Usage (Button Switch)
+    0x75, 0x01, //     Report Size (1)
+    0x95, 0x02, //     Report Count (2)
+    0x25, 0x01, //     Logical Maximum (1)
+    0xB1, 0x02, //     Feature (Data, Variable, Absolute)
+    0x95, 0x06, //     Report Count (6)
+    0xB1, 0x03, //     Feature (Constant, Variable, Absolute)
+    0xC0, //   End Collection
+    0xC0, // End Collection
+];
+
+#[cfg(feature = "touchpad")]
+const TOUCHPAD_REPORT_DESC_LEN: usize = TOUCHPAD_HEAD_DESC.len()
+    + TOUCHPAD_CONTACT_DESC.len() * NUM_TOUCHPAD_CONTACTS
+    + TOUCHPAD_TAIL_DESC.len();
+
+/// Report descriptor of the precision touchpad(Windows PTP) interface.
+///
+/// See https://learn.microsoft.com/en-us/windows-hardware/design/component-guidelines/touchpad-required-hid-top-level-collections
+#[cfg(feature = "touchpad")]
+pub(crate) const TOUCHPAD_REPORT_DESC: [u8; TOUCHPAD_REPORT_DESC_LEN] = touchpad_report_desc();
+
+#[cfg(feature = "touchpad")]
+const fn touchpad_report_desc() -> [u8; TOUCHPAD_REPORT_DESC_LEN] {
+    let mut desc = [0; TOUCHPAD_REPORT_DESC_LEN];
+    let mut len = 0;
+    let mut i = 0;
+    while i < TOUCHPAD_HEAD_DESC.len() {
+        desc[len] = TOUCHPAD_HEAD_DESC[i];
+        len += 1;
+        i += 1;
+    }
+    let mut contact = 0;
+    while contact < NUM_TOUCHPAD_CONTACTS {
+        i = 0;
+        while i < TOUCHPAD_CONTACT_DESC.len() {
+            desc[len] = TOUCHPAD_CONTACT_DESC[i];
+            len += 1;
+            i += 1;
+        }
+        contact += 1;
+    }
+    i = 0;
+    while i < TOUCHPAD_TAIL_DESC.len() {
+        desc[len] = TOUCHPAD_TAIL_DESC[i];
+        len += 1;
+        i += 1;
+    }
+    desc
+}
+
+/// A contact in the touchpad report
+#[cfg(feature = "touchpad")]
+#[derive(Serialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TouchpadContact {
+    /// Bit 0 is confidence, bit 1 is tip switch
+    pub(crate) flags: u8,
+    pub(crate) contact_id: u8,
+    pub(crate) x: u16,
+    pub(crate) y: u16,
+}
+
+/// Precision touchpad report, see [`TOUCHPAD_REPORT_DESC`]
+#[cfg(feature = "touchpad")]
+#[derive(Serialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TouchpadReport {
+    pub(crate) contacts: [TouchpadContact; NUM_TOUCHPAD_CONTACTS],
+    /// Relative timestamp in 100us units
+    pub(crate) scan_time: u16,
+    pub(crate) contact_count: u8,
+    pub(crate) buttons: u8,
+}
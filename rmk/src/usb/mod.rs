@@ -2,6 +2,10 @@ pub(crate) mod descriptor;
 
 use core::sync::atomic::{AtomicU8, Ordering};
 use embassy_time::Timer;
+#[cfg(feature = "steno")]
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State as CdcAcmState};
+#[cfg(feature = "midi")]
+use embassy_usb::class::midi::MidiClass;
 use embassy_usb::{
     class::hid::{Config, HidReaderWriter, HidWriter, ReportId, RequestHandler, State},
     control::OutResponse,
@@ -11,12 +15,47 @@ use embassy_usb::{
 use static_cell::StaticCell;
 use usbd_hid::descriptor::SerializedDescriptor;
 
+#[cfg(feature = "joystick")]
+use crate::usb::descriptor::JoystickReport;
 use crate::{
     config::KeyboardUsbConfig,
     hid::{UsbHidReader, UsbHidReaderWriter, UsbHidWriter},
-    usb::descriptor::{CompositeReport, ViaReport},
+    input_device::pointing::{resolution_multiplier, set_resolution_multiplier},
+    light::update_indicator_state,
+    usb::descriptor::{CompositeReportType, ViaReport, COMPOSITE_REPORT_DESC},
     CONNECTION_STATE,
 };
+#[cfg(feature = "touchpad")]
+use crate::{
+    input_device::touchpad::{
+        get_touchpad_feature_report, reset_touchpad_mode, set_touchpad_feature_report,
+    },
+    usb::descriptor::{TOUCHPAD_REPORT_DESC, TOUCHPAD_REPORT_SIZE},
+};
+
+// USB MIDI and steno interfaces need more space in the configuration descriptor
+#[cfg(not(any(feature = "midi", feature = "steno")))]
+const CONFIG_DESC_SIZE: usize = 256;
+#[cfg(any(feature = "midi", feature = "steno"))]
+const CONFIG_DESC_SIZE: usize = 512;
+
+// The certification feature report of touchpad is 257 bytes, which needs a larger control buffer
+#[cfg(not(feature = "touchpad"))]
+const CONTROL_BUF_SIZE: usize = 128;
+#[cfg(feature = "touchpad")]
+const CONTROL_BUF_SIZE: usize = 320;
+
+// embassy-usb supports at most 8 interfaces(`max-interface-count-8`), the builder panics if there're more.
+// Keyboard, other and via use 3 interfaces, gamepad and touchpad use 1, MIDI uses 2 and steno uses 3.
+// The request handlers(1 for the device, 1 for each HID or CDC-ACM interface) are at most 8 in other combinations.
+#[cfg(all(
+    feature = "midi",
+    feature = "steno",
+    any(feature = "joystick", feature = "touchpad")
+))]
+compile_error!(
+    "Too many USB interfaces: `midi` and `steno` features can't be used together with `joystick` or `touchpad` feature"
+);
 
 pub(crate) static USB_STATE: AtomicU8 = AtomicU8::new(UsbState::Disabled as u8);
 
@@ -72,12 +111,27 @@ pub(crate) async fn wait_for_usb_enabled() {
 // 1. Boot keyboard: 1 endpoint in
 // 2. Other: Mouse + System control + Consumer control: 1 endpoint in
 // 3. Via: used to communicate with via: 2 endpoints(in/out)
+// If `joystick` feature is enabled, there's an extra gamepad HID interface(buttons + axes): 1 endpoint in
+// If `midi` feature is enabled, there's an extra USB MIDI streaming interface: 2 endpoints(in/out)
+// If `steno` feature is enabled, there's an extra CDC-ACM serial interface: 3 endpoints(in/out + notification in)
+// and an extra Plover HID interface: 1 endpoint in
+// If `touchpad` feature is enabled, there's an extra precision touchpad HID interface: 1 endpoint in
 pub(crate) struct KeyboardUsbDevice<'d, D: Driver<'d>> {
     pub(crate) device: UsbDevice<'d, D>,
     pub(crate) keyboard_hid_writer: UsbHidWriter<'d, D, 8>,
     pub(crate) keyboard_hid_reader: UsbHidReader<'d, D, 1>,
-    pub(crate) other_hid_writer: UsbHidWriter<'d, D, 9>,
+    pub(crate) other_hid_writer: UsbHidWriter<'d, D, 10>,
     pub(crate) via_hid: UsbHidReaderWriter<'d, D, 32, 32>,
+    #[cfg(feature = "joystick")]
+    pub(crate) joystick_hid_writer: UsbHidWriter<'d, D, 16>,
+    #[cfg(feature = "midi")]
+    pub(crate) midi: MidiClass<'d, D>,
+    #[cfg(feature = "steno")]
+    pub(crate) steno_serial: CdcAcmClass<'d, D>,
+    #[cfg(feature = "steno")]
+    pub(crate) steno_hid_writer: UsbHidWriter<'d, D, 9>,
+    #[cfg(feature = "touchpad")]
+    pub(crate) touchpad_hid_writer: UsbHidWriter<'d, D, { TOUCHPAD_REPORT_SIZE + 1 }>,
 }
 
 impl<D: Driver<'static>> KeyboardUsbDevice<'static, D> {
@@ -98,19 +152,19 @@ impl<D: Driver<'static>> KeyboardUsbDevice<'static, D> {
         usb_config.composite_with_iads = true;
 
         // Create embassy-usb DeviceBuilder using the driver and config.
-        static CONFIG_DESC: StaticCell<[u8; 256]> = StaticCell::new();
+        static CONFIG_DESC: StaticCell<[u8; CONFIG_DESC_SIZE]> = StaticCell::new();
         static BOS_DESC: StaticCell<[u8; 256]> = StaticCell::new();
         static MSOS_DESC: StaticCell<[u8; 128]> = StaticCell::new();
-        static CONTROL_BUF: StaticCell<[u8; 128]> = StaticCell::new();
+        static CONTROL_BUF: StaticCell<[u8; CONTROL_BUF_SIZE]> = StaticCell::new();
 
         // UsbDevice builder
         let mut builder = Builder::new(
             driver,
             usb_config,
-            &mut CONFIG_DESC.init([0; 256])[..],
+            &mut CONFIG_DESC.init([0; CONFIG_DESC_SIZE])[..],
             &mut BOS_DESC.init([0; 256])[..],
             &mut MSOS_DESC.init([0; 128])[..],
-            &mut CONTROL_BUF.init([0; 128])[..],
+            &mut CONTROL_BUF.init([0; CONTROL_BUF_SIZE])[..],
         );
 
         static device_handler: StaticCell<UsbDeviceHandler> = StaticCell::new();
@@ -136,13 +190,13 @@ impl<D: Driver<'static>> KeyboardUsbDevice<'static, D> {
 
         static other_request_handler: StaticCell<UsbRequestHandler> = StaticCell::new();
         let other_hid_config = Config {
-            report_descriptor: CompositeReport::desc(),
+            report_descriptor: &COMPOSITE_REPORT_DESC,
             request_handler: Some(other_request_handler.init(UsbRequestHandler {})),
             poll_ms: 1,
             max_packet_size: 64,
         };
         static OTHER_HID_STATE: StaticCell<State> = StaticCell::new();
-        let other_hid: HidWriter<'_, D, 9> = HidWriter::new(
+        let other_hid: HidWriter<'_, D, 10> = HidWriter::new(
             &mut builder,
             OTHER_HID_STATE.init(State::new()),
             other_hid_config,
@@ -159,6 +213,75 @@ impl<D: Driver<'static>> KeyboardUsbDevice<'static, D> {
         let via_hid: HidReaderWriter<'_, D, 32, 32> =
             HidReaderWriter::new(&mut builder, VIA_STATE.init(State::new()), via_config);
 
+        // Gamepad
+        #[cfg(feature = "joystick")]
+        static joystick_request_handler: StaticCell<UsbRequestHandler> = StaticCell::new();
+        #[cfg(feature = "joystick")]
+        let joystick_hid_config = Config {
+            report_descriptor: JoystickReport::desc(),
+            request_handler: Some(joystick_request_handler.init(UsbRequestHandler {})),
+            poll_ms: 1,
+            max_packet_size: 64,
+        };
+        #[cfg(feature = "joystick")]
+        static JOYSTICK_HID_STATE: StaticCell<State> = StaticCell::new();
+        #[cfg(feature = "joystick")]
+        let joystick_hid: HidWriter<'_, D, 16> = HidWriter::new(
+            &mut builder,
+            JOYSTICK_HID_STATE.init(State::new()),
+            joystick_hid_config,
+        );
+
+        // USB MIDI: 1 in jack + 1 out jack
+        #[cfg(feature = "midi")]
+        let midi = MidiClass::new(&mut builder, 1, 1, 64);
+
+        // Steno: serial for GeminiPR/TX Bolt, and Plover HID
+        #[cfg(feature = "steno")]
+        static STENO_SERIAL_STATE: StaticCell<CdcAcmState> = StaticCell::new();
+        #[cfg(feature = "steno")]
+        let steno_serial = CdcAcmClass::new(
+            &mut builder,
+            STENO_SERIAL_STATE.init(CdcAcmState::new()),
+            64,
+        );
+        #[cfg(feature = "steno")]
+        static steno_request_handler: StaticCell<UsbRequestHandler> = StaticCell::new();
+        #[cfg(feature = "steno")]
+        let steno_hid_config = Config {
+            report_descriptor: crate::usb::descriptor::PLOVER_HID_DESC,
+            request_handler: Some(steno_request_handler.init(UsbRequestHandler {})),
+            poll_ms: 1,
+            max_packet_size: 64,
+        };
+        #[cfg(feature = "steno")]
+        static STENO_HID_STATE: StaticCell<State> = StaticCell::new();
+        #[cfg(feature = "steno")]
+        let steno_hid: HidWriter<'_, D, 9> = HidWriter::new(
+            &mut builder,
+            STENO_HID_STATE.init(State::new()),
+            steno_hid_config,
+        );
+
+        // Precision touchpad
+        #[cfg(feature = "touchpad")]
+        static touchpad_request_handler: StaticCell<TouchpadRequestHandler> = StaticCell::new();
+        #[cfg(feature = "touchpad")]
+        let touchpad_hid_config = Config {
+            report_descriptor: &TOUCHPAD_REPORT_DESC,
+            request_handler: Some(touchpad_request_handler.init(TouchpadRequestHandler {})),
+            poll_ms: 1,
+            max_packet_size: 64,
+        };
+        #[cfg(feature = "touchpad")]
+        static TOUCHPAD_HID_STATE: StaticCell<State> = StaticCell::new();
+        #[cfg(feature = "touchpad")]
+        let touchpad_hid: HidWriter<'_, D, { TOUCHPAD_REPORT_SIZE + 1 }> = HidWriter::new(
+            &mut builder,
+            TOUCHPAD_HID_STATE.init(State::new()),
+            touchpad_hid_config,
+        );
+
         // Build usb device
         let usb = builder.build();
         let (reader, writer) = keyboard_hid.split();
@@ -168,6 +291,16 @@ impl<D: Driver<'static>> KeyboardUsbDevice<'static, D> {
             keyboard_hid_writer: UsbHidWriter::new(writer),
             other_hid_writer: UsbHidWriter::new(other_hid),
             via_hid: UsbHidReaderWriter::new(via_hid),
+            #[cfg(feature = "joystick")]
+            joystick_hid_writer: UsbHidWriter::new(joystick_hid),
+            #[cfg(feature = "midi")]
+            midi,
+            #[cfg(feature = "steno")]
+            steno_serial,
+            #[cfg(feature = "steno")]
+            steno_hid_writer: UsbHidWriter::new(steno_hid),
+            #[cfg(feature = "touchpad")]
+            touchpad_hid_writer: UsbHidWriter::new(touchpad_hid),
         }
     }
 }
@@ -175,9 +308,51 @@ impl<D: Driver<'static>> KeyboardUsbDevice<'static, D> {
 struct UsbRequestHandler {}
 
 impl RequestHandler for UsbRequestHandler {
+    fn get_report(&mut self, id: ReportId, buf: &mut [u8]) -> Option<usize> {
+        match id {
+            // Resolution multiplier feature report of mouse, prepend report id
+            ReportId::Feature(id) if id == CompositeReportType::Mouse as u8 && buf.len() >= 2 => {
+                buf[0] = id;
+                buf[1] = resolution_multiplier();
+                Some(2)
+            }
+            _ => None,
+        }
+    }
+
     fn set_report(&mut self, id: ReportId, data: &[u8]) -> OutResponse {
         info!("Set report for {:?}: {:?}", id, data);
-        OutResponse::Accepted
+        match id {
+            // The data of the feature report starts with report id
+            ReportId::Feature(id) if id == CompositeReportType::Mouse as u8 => {
+                if let Some(multiplier) = data.last() {
+                    set_resolution_multiplier(*multiplier);
+                }
+                OutResponse::Accepted
+            }
+            _ => OutResponse::Accepted,
+        }
+    }
+}
+
+/// Request handler of the precision touchpad interface, which handles the feature reports
+#[cfg(feature = "touchpad")]
+struct TouchpadRequestHandler {}
+
+#[cfg(feature = "touchpad")]
+impl RequestHandler for TouchpadRequestHandler {
+    fn get_report(&mut self, id: ReportId, buf: &mut [u8]) -> Option<usize> {
+        match id {
+            ReportId::Feature(id) => get_touchpad_feature_report(id, buf),
+            _ => None,
+        }
+    }
+
+    fn set_report(&mut self, id: ReportId, data: &[u8]) -> OutResponse {
+        match id {
+            ReportId::Feature(id) if set_touchpad_feature_report(id, data) => OutResponse::Accepted,
+            _ => OutResponse::Rejected,
+        }
     }
 }
 
@@ -196,12 +371,18 @@ impl Handler for UsbDeviceHandler {
             info!("Device enabled");
         } else {
             USB_STATE.store(UsbState::Disabled as u8, Ordering::Relaxed);
+            update_indicator_state(|s| s.usb_active = false);
             info!("Device disabled");
         }
     }
 
     fn reset(&mut self) {
         USB_STATE.store(UsbState::Enabled as u8, Ordering::Relaxed);
+        // The host enables high-resolution scrolling and touchpad mode again after enumeration
+        set_resolution_multiplier(0);
+        #[cfg(feature = "touchpad")]
+        reset_touchpad_mode();
+        update_indicator_state(|s| s.usb_active = false);
         info!("Bus reset, the Vbus current limit is 100mA");
     }
 
@@ -214,15 +395,18 @@ impl Handler for UsbDeviceHandler {
         if configured {
             USB_STATE.store(UsbState::Configured as u8, Ordering::Relaxed);
             CONNECTION_STATE.store(true, Ordering::Release);
+            update_indicator_state(|s| s.usb_active = true);
             info!("Device configured, it may now draw up to the configured current from Vbus.")
         } else {
             USB_STATE.store(UsbState::Enabled as u8, Ordering::Relaxed);
+            update_indicator_state(|s| s.usb_active = false);
             info!("Device is no longer configured, the Vbus current limit is 100mA.");
         }
     }
 
     fn suspended(&mut self, suspended: bool) {
         USB_STATE.store(UsbState::Enabled as u8, Ordering::Release);
+        update_indicator_state(|s| s.usb_active = !suspended);
         if suspended {
             info!("Device suspended, the Vbus current limit is 500µA (or 2.5mA for high-power devices with remote wakeup enabled).");
         } else {
@@ -0,0 +1,61 @@
+//! Via custom values of the haptic feedback.
+//!
+//! Stock VIA and Vial have no haptic settings, so they're exposed in the custom channel(`id_custom_channel`) of VIA v3,
+//! which needs a custom menu in the keyboard's VIA definition, see `docs/src/vial_support.md`.
+
+use crate::{
+    haptic::{
+        get_haptic_settings, update_haptic_settings, HAPTIC_DWELL_MAX, HAPTIC_DWELL_MIN,
+        HAPTIC_EFFECT_MAX, HAPTIC_EFFECT_MIN,
+    },
+    usb::descriptor::ViaReport,
+};
+
+/// Channel id of keyboard specific values in VIA v3 custom values
+pub(crate) const CHANNEL_CUSTOM: u8 = 0x00;
+
+/// Value ids of haptic settings in the custom channel
+const HAPTIC_ENABLE: u8 = 0x01;
+const HAPTIC_KEY_PRESS: u8 = 0x02;
+const HAPTIC_LAYER_CHANGE: u8 = 0x03;
+const HAPTIC_COMBO: u8 = 0x04;
+const HAPTIC_EFFECT: u8 = 0x05;
+const HAPTIC_DWELL: u8 = 0x06;
+
+/// Read a haptic value to `report.input_data`
+pub(crate) fn get_haptic_value(report: &mut ViaReport) {
+    let settings = get_haptic_settings();
+    let value_id = report.output_data[2];
+    report.input_data[3] = match value_id {
+        HAPTIC_ENABLE => settings.enable() as u8,
+        HAPTIC_KEY_PRESS => settings.key_press() as u8,
+        HAPTIC_LAYER_CHANGE => settings.layer_change() as u8,
+        HAPTIC_COMBO => settings.combo() as u8,
+        HAPTIC_EFFECT => settings.effect(),
+        HAPTIC_DWELL => settings.dwell(),
+        _ => {
+            warn!("Unsupported haptic value: {}", value_id);
+            return;
+        }
+    };
+}
+
+/// Set a haptic value from `report.output_data`, the new value is saved immediately
+pub(crate) fn set_haptic_value(report: &ViaReport) {
+    let settings = get_haptic_settings();
+    let value_id = report.output_data[2];
+    let value = report.output_data[3];
+    let settings = match value_id {
+        HAPTIC_ENABLE => settings.with_enable(value != 0),
+        HAPTIC_KEY_PRESS => settings.with_key_press(value != 0),
+        HAPTIC_LAYER_CHANGE => settings.with_layer_change(value != 0),
+        HAPTIC_COMBO => settings.with_combo(value != 0),
+        HAPTIC_EFFECT => settings.with_effect(value.clamp(HAPTIC_EFFECT_MIN, HAPTIC_EFFECT_MAX)),
+        HAPTIC_DWELL => settings.with_dwell(value.clamp(HAPTIC_DWELL_MIN, HAPTIC_DWELL_MAX)),
+        _ => {
+            warn!("Unsupported haptic value: {}", value_id);
+            return;
+        }
+    };
+    update_haptic_settings(settings);
+}
@@ -5,6 +5,34 @@ use crate::{
     keycode::{KeyCode, ModifierCombination},
 };
 
+/// Convert a single keycode to via keycode, returns `None` if the keycode has no via equivalent.
+///
+/// The result is converted back to the same keycode by [`from_via_keycode`].
+pub(crate) const fn to_via_key(key: KeyCode) -> Option<u16> {
+    let k = key as u16;
+    if k <= 0xFF {
+        Some(k)
+    } else if k >= KeyCode::Macro0 as u16 && k <= KeyCode::Macro31 as u16 {
+        Some(k & 0xFF | 0x7700)
+    } else if k >= KeyCode::User0 as u16 && k <= KeyCode::User15 as u16 {
+        Some(k & 0xF | 0x7E00)
+    } else if k >= KeyCode::SequencerOn as u16 && k <= KeyCode::SequencerStepsClear as u16 {
+        Some(k - KeyCode::SequencerOn as u16 + 0x7200)
+    } else if k >= KeyCode::SequencerStep0 as u16 && k <= KeyCode::SequencerStep15 as u16 {
+        Some(k - KeyCode::SequencerStep0 as u16 + 0x7209)
+    } else if k >= KeyCode::SequencerTrack0 as u16 && k <= KeyCode::SequencerTrack7 as u16 {
+        Some(k - KeyCode::SequencerTrack0 as u16 + 0x7222)
+    } else {
+        match key {
+            // QK_BOOTLOADER, QK_REBOOT and QK_CLEAR_EEPROM
+            KeyCode::Bootloader => Some(0x7C00),
+            KeyCode::Reboot => Some(0x7C01),
+            KeyCode::ClearStorage => Some(0x7C03),
+            _ => None,
+        }
+    }
+}
+
 pub(crate) fn to_via_keycode(key_action: KeyAction) -> u16 {
     match key_action {
         KeyAction::No => 0x0000,
@@ -16,7 +44,7 @@ pub(crate) fn to_via_keycode(key_action: KeyAction) -> u16 {
                 } else if k.is_user() {
                     k as u16 & 0xF | 0x7E00
                 } else {
-                    k as u16
+                    to_via_key(k).unwrap_or(k as u16)
                 }
             }
             Action::LayerToggleOnly(l) => 0x5200 | l as u16,
@@ -152,7 +180,23 @@ pub(crate) fn from_via_keycode(via_keycode: u16) -> KeyAction {
             warn!("QMK functions {:#X} not supported", via_keycode);
             KeyAction::No
         }
-        0x7700..=0x770F => {
+        0x7200..=0x7208 => {
+            // QK_SEQUENCER, SQ_ON ~ SQ_SCLR
+            let keycode = via_keycode - 0x7200 + KeyCode::SequencerOn as u16;
+            KeyAction::Single(Action::Key(KeyCode::from_primitive(keycode)))
+        }
+        0x7209..=0x7218 => {
+            // SQ_S(n), RMK supports 16 steps
+            let keycode = via_keycode - 0x7209 + KeyCode::SequencerStep0 as u16;
+            KeyAction::Single(Action::Key(KeyCode::from_primitive(keycode)))
+        }
+        0x7222..=0x7229 => {
+            // SQ_T(n), which follows 16 SQ_S(n) and 9 SQ_R(n). RMK supports 8 tracks
+            let keycode = via_keycode - 0x7222 + KeyCode::SequencerTrack0 as u16;
+            KeyAction::Single(Action::Key(KeyCode::from_primitive(keycode)))
+        }
+        0x7700..=0x771F => {
+            // QK_MACRO_N, aka MacroN
             let keycode = via_keycode & 0xFF | 0x500;
             KeyAction::Single(Action::Key(KeyCode::from_primitive(keycode)))
         }
@@ -161,12 +205,15 @@ pub(crate) fn from_via_keycode(via_keycode: u16) -> KeyAction {
             warn!("Backlight and RGB configuration key not supported");
             KeyAction::No
         }
-        0x7C00..=0x7C5F => {
-            // TODO: Reset/GESC/Space Cadet/Haptic/Auto shift(AS)/Dynamic macro
+        0x7C00 => KeyAction::Single(Action::Key(KeyCode::Bootloader)),
+        0x7C01 => KeyAction::Single(Action::Key(KeyCode::Reboot)),
+        0x7C03 => KeyAction::Single(Action::Key(KeyCode::ClearStorage)),
+        0x7C02..=0x7C5F => {
+            // TODO: Debug toggle/GESC/Space Cadet/Haptic/Auto shift(AS)/Dynamic macro
             // - [GESC](https://docs.qmk.fm/#/feature_grave_esc)
             // - [Space Cadet](https://docs.qmk.fm/#/feature_space_cadet)
             warn!(
-                "Reset/GESC/Space Cadet/Haptic/Auto shift(AS)/Dynamic macro not supported: {:#X}",
+                "Debug toggle/GESC/Space Cadet/Haptic/Auto shift(AS)/Dynamic macro not supported: {:#X}",
                 via_keycode
             );
             KeyAction::No
@@ -408,5 +455,25 @@ mod test {
             ModifierCombination::new_from(false, false, true, true, true),
         );
         assert_eq!(0x2704, to_via_keycode(a));
+
+        // QK_MACRO_20 -> Macro20
+        let a = KeyAction::Single(Action::Key(KeyCode::Macro20));
+        assert_eq!(0x7714, to_via_keycode(a));
+        assert_eq!(a, from_via_keycode(0x7714));
+
+        // QK_BOOTLOADER, QK_REBOOT, QK_CLEAR_EEPROM and sequencer keycodes
+        for (key, via_keycode) in [
+            (KeyCode::Bootloader, 0x7C00),
+            (KeyCode::Reboot, 0x7C01),
+            (KeyCode::ClearStorage, 0x7C03),
+            (KeyCode::SequencerToggle, 0x7202),
+            (KeyCode::SequencerStep5, 0x720E),
+            (KeyCode::SequencerTrack0, 0x7222),
+            (KeyCode::SequencerTrack7, 0x7229),
+        ] {
+            let a = KeyAction::Single(Action::Key(key));
+            assert_eq!(via_keycode, to_via_keycode(a));
+            assert_eq!(a, from_via_keycode(via_keycode));
+        }
     }
 }
@@ -0,0 +1,125 @@
+//! Via lighting values of the RGB light.
+//!
+//! Both the legacy lighting protocol used by Vial(value id in `data[1]`)
+//! and the custom value protocol of VIA v3(channel id in `data[1]`, value id in `data[2]`) are supported.
+
+use num_enum::FromPrimitive;
+
+use crate::{
+    rgb::underglow::{
+        get_rgb_light_settings, send_rgb_light_event, set_rgb_light_settings, RgbLightEvent,
+        RgbLightMode,
+    },
+    usb::descriptor::ViaReport,
+};
+
+/// Legacy lighting value ids of QMK's rgblight
+const LEGACY_RGBLIGHT_BRIGHTNESS: u8 = 0x80;
+const LEGACY_RGBLIGHT_EFFECT: u8 = 0x81;
+const LEGACY_RGBLIGHT_EFFECT_SPEED: u8 = 0x82;
+const LEGACY_RGBLIGHT_COLOR: u8 = 0x83;
+
+/// Channel id of QMK's rgblight in VIA v3 custom values
+const CHANNEL_RGBLIGHT: u8 = 0x02;
+
+/// Value ids of QMK's rgblight channel
+const RGBLIGHT_BRIGHTNESS: u8 = 0x01;
+const RGBLIGHT_EFFECT: u8 = 0x02;
+const RGBLIGHT_EFFECT_SPEED: u8 = 0x03;
+const RGBLIGHT_COLOR: u8 = 0x04;
+
+/// Effect ids of QMK's rgblight, 0 means the light is off
+const EFFECT_OFF: u8 = 0;
+const EFFECT_SOLID: u8 = 1;
+const EFFECT_BREATHING: u8 = 2;
+const EFFECT_RAINBOW: u8 = 6;
+const EFFECT_SWIRL: u8 = 9;
+const EFFECT_SNAKE: u8 = 15;
+const EFFECT_KNIGHT: u8 = 21;
+
+/// Locate the rgblight value id and its data in a lighting report.
+///
+/// Returns `None` if the report isn't for rgblight
+fn rgblight_value(data: &[u8]) -> Option<(u8, usize)> {
+    match data[1] {
+        LEGACY_RGBLIGHT_BRIGHTNESS..=LEGACY_RGBLIGHT_COLOR => Some((
+            data[1] - LEGACY_RGBLIGHT_BRIGHTNESS + RGBLIGHT_BRIGHTNESS,
+            2,
+        )),
+        CHANNEL_RGBLIGHT => Some((data[2], 3)),
+        _ => None,
+    }
+}
+
+/// Read a rgblight value to `report.input_data`
+pub(crate) fn get_lighting_value(report: &mut ViaReport) {
+    let Some((value_id, offset)) = rgblight_value(&report.output_data) else {
+        warn!("Unsupported lighting value: {:?}", report.output_data[1..3]);
+        return;
+    };
+    let settings = get_rgb_light_settings();
+    let value = &mut report.input_data[offset..];
+    match value_id {
+        RGBLIGHT_BRIGHTNESS => value[0] = settings.val(),
+        RGBLIGHT_EFFECT => {
+            value[0] = if settings.enable() {
+                match RgbLightMode::from_primitive(settings.mode()) {
+                    RgbLightMode::Plain => EFFECT_SOLID,
+                    RgbLightMode::Breathing => EFFECT_BREATHING,
+                    RgbLightMode::Rainbow => EFFECT_RAINBOW,
+                    RgbLightMode::Swirl => EFFECT_SWIRL,
+                    RgbLightMode::Snake => EFFECT_SNAKE,
+                }
+            } else {
+                EFFECT_OFF
+            }
+        }
+        RGBLIGHT_EFFECT_SPEED => value[0] = settings.speed(),
+        RGBLIGHT_COLOR => {
+            value[0] = settings.hue();
+            value[1] = settings.sat();
+        }
+        _ => warn!("Unsupported rgblight value: {}", value_id),
+    }
+}
+
+/// Set a rgblight value from `report.output_data`, the new value isn't saved until [`save_lighting`] is called
+pub(crate) fn set_lighting_value(report: &ViaReport) {
+    let Some((value_id, offset)) = rgblight_value(&report.output_data) else {
+        warn!("Unsupported lighting value: {:?}", report.output_data[1..3]);
+        return;
+    };
+    let settings = get_rgb_light_settings();
+    let value = &report.output_data[offset..];
+    let settings = match value_id {
+        RGBLIGHT_BRIGHTNESS => settings.with_val(value[0]),
+        RGBLIGHT_EFFECT => {
+            // Variants of an effect are mapped to the same mode
+            let mode = match value[0] {
+                EFFECT_OFF => return set_rgb_light_settings(settings.with_enable(false)),
+                EFFECT_SOLID => RgbLightMode::Plain,
+                EFFECT_BREATHING..EFFECT_RAINBOW => RgbLightMode::Breathing,
+                EFFECT_RAINBOW..EFFECT_SWIRL => RgbLightMode::Rainbow,
+                EFFECT_SWIRL..EFFECT_SNAKE => RgbLightMode::Swirl,
+                EFFECT_SNAKE..EFFECT_KNIGHT => RgbLightMode::Snake,
+                effect => {
+                    warn!("Unsupported rgblight effect: {}", effect);
+                    return;
+                }
+            };
+            settings.with_enable(true).with_mode(mode as u8)
+        }
+        RGBLIGHT_EFFECT_SPEED => settings.with_speed(value[0]),
+        RGBLIGHT_COLOR => settings.with_hue(value[0]).with_sat(value[1]),
+        _ => {
+            warn!("Unsupported rgblight value: {}", value_id);
+            return;
+        }
+    };
+    set_rgb_light_settings(settings);
+}
+
+/// Save current lighting values
+pub(crate) fn save_lighting() {
+    send_rgb_light_event(RgbLightEvent::Save);
+}
@@ -2,7 +2,9 @@ use self::process::VialService;
 use crate::hid::HidReaderWriterWrapper;
 use embassy_time::Timer;
 
+mod haptic;
 pub(crate) mod keycode_convert;
+mod lighting;
 pub(crate) mod process;
 mod protocol;
 mod vial;
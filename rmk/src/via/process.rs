@@ -1,15 +1,23 @@
-use super::{protocol::*, vial::process_vial};
+use super::{
+    haptic::{get_haptic_value, set_haptic_value, CHANNEL_CUSTOM},
+    lighting::{get_lighting_value, save_lighting, set_lighting_value},
+    protocol::*,
+    vial::process_vial,
+};
+use crate::bootloader::jump_to_bootloader;
 use crate::config::VialConfig;
 use crate::{
     hid::{HidError, HidReaderWriterWrapper},
     keyboard_macro::{MACRO_SPACE_SIZE, NUM_MACRO},
     keymap::KeyMap,
+    raw_hid::{process_raw_hid_report, RAW_HID_REPORT_CHANNEL},
     storage::{FlashOperationMessage, FLASH_CHANNEL},
     usb::descriptor::ViaReport,
     via::keycode_convert::{from_via_keycode, to_via_keycode},
 };
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
 use core::cell::RefCell;
+use embassy_futures::select::{select, Either};
 use embassy_time::Instant;
 use num_enum::{FromPrimitive, TryFromPrimitive};
 
@@ -42,7 +50,25 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
             input_data: [0; 32],
             output_data: [0; 32],
         };
-        match hid_interface.read(&mut via_report.output_data).await {
+        // Notifications of the raw HID user channel are sent while waiting for the next request
+        let read_result = match select(
+            hid_interface.read(&mut via_report.output_data),
+            RAW_HID_REPORT_CHANNEL.receive(),
+        )
+        .await
+        {
+            Either::First(result) => result,
+            Either::Second(report) => {
+                via_report.input_data = report;
+                return hid_interface
+                    .write_serialize(&via_report)
+                    .await
+                    .map_err(|e| {
+                        error!("Send raw HID report error: {:?}", e);
+                    });
+            }
+        };
+        match read_result {
             Ok(_) => {
                 self.process_via_packet(&mut via_report, self.keymap).await;
 
@@ -91,8 +117,7 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                             BigEndian::write_u32(&mut report.input_data[2..6], value);
                         }
                         ViaKeyboardInfo::LayoutOptions => {
-                            // TODO: retrieve layout option from storage
-                            let layout_option: u32 = 0;
+                            let layout_option = keymap.borrow().get_layout_option();
                             BigEndian::write_u32(&mut report.input_data[2..6], layout_option);
                         }
                         ViaKeyboardInfo::SwitchMatrixState => {
@@ -115,6 +140,7 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                     Ok(v) => match v {
                         ViaKeyboardInfo::LayoutOptions => {
                             let layout_option = BigEndian::read_u32(&report.output_data[2..6]);
+                            keymap.borrow_mut().set_layout_option(layout_option);
                             FLASH_CHANNEL
                                 .send(FlashOperationMessage::LayoutOptions(layout_option))
                                 .await;
@@ -168,34 +194,28 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
             ViaCommand::DynamicKeymapReset => {
                 warn!("Dynamic keymap reset -- not supported")
             }
-            ViaCommand::CustomSetValue => {
-                // backlight/rgblight/rgb matrix/led matrix/audio settings here
-                warn!("Custom set value -- not supported")
-            }
-            ViaCommand::CustomGetValue => {
-                // backlight/rgblight/rgb matrix/led matrix/audio settings here
-                warn!("Custom get value -- not supported")
-            }
-            ViaCommand::CustomSave => {
-                // backlight/rgblight/rgb matrix/led matrix/audio settings here
-                warn!("Custom get value -- not supported")
-            }
+            ViaCommand::CustomSetValue => match report.output_data[1] {
+                CHANNEL_CUSTOM => set_haptic_value(report),
+                _ => set_lighting_value(report),
+            },
+            ViaCommand::CustomGetValue => match report.output_data[1] {
+                CHANNEL_CUSTOM => get_haptic_value(report),
+                _ => get_lighting_value(report),
+            },
+            // Haptic settings are saved when they're set
+            ViaCommand::CustomSave => save_lighting(),
             ViaCommand::EepromReset => {
+                // The keyboard is rebooted after the storage is cleared
                 warn!("Reseting storage..");
                 FLASH_CHANNEL.send(FlashOperationMessage::Reset).await
-                // TODO: Reboot after a eeprom reset?
-            }
-            ViaCommand::BootloaderJump => {
-                warn!("Bootloader jump -- not supported")
             }
+            ViaCommand::BootloaderJump => jump_to_bootloader(),
             ViaCommand::DynamicKeymapMacroGetCount => {
-                report.input_data[1] = 8;
-                warn!("Macro get count -- to be implemented")
+                report.input_data[1] = NUM_MACRO as u8;
             }
             ViaCommand::DynamicKeymapMacroGetBufferSize => {
                 report.input_data[1] = (MACRO_SPACE_SIZE as u16 >> 8) as u8;
                 report.input_data[2] = (MACRO_SPACE_SIZE & 0xFF) as u8;
-                warn!("Macro get buffer size -- to be implemented")
             }
             ViaCommand::DynamicKeymapMacroGetBuffer => {
                 let offset = BigEndian::read_u16(&report.output_data[1..3]) as usize;
@@ -320,8 +340,11 @@ impl<'a, const ROW: usize, const COL: usize, const NUM_LAYER: usize>
                 keymap,
             ),
             ViaCommand::Unhandled => {
-                info!("Unknown cmd: {}", report.output_data);
-                report.input_data[0] = ViaCommand::Unhandled as u8
+                // Commands in the user range are processed by the user's raw HID handler
+                if !process_raw_hid_report(&mut report.input_data) {
+                    info!("Unknown cmd: {}", report.output_data);
+                    report.input_data[0] = ViaCommand::Unhandled as u8
+                }
             }
         }
     }